@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Control protocol spoken over the daemon's Unix-domain socket.
+//!
+//! Every request and response is a single line of RON. Requests carry the
+//! protocol version so the daemon can reject clients built against an
+//! incompatible revision of this module.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::path::PathBuf;
+
+use crate::Source;
+
+/// Version of the control protocol, bumped on incompatible changes.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// File name of the control socket inside `$XDG_RUNTIME_DIR`.
+pub const SOCKET_NAME: &str = "cosmic-ext-bg.sock";
+
+/// Path of the control socket of the running daemon.
+#[must_use]
+pub fn socket_path() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map_or_else(std::env::temp_dir, PathBuf::from)
+        .join(SOCKET_NAME)
+}
+
+/// A request sent by a client to the daemon.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Request {
+    /// Protocol version the client was built against
+    pub version: u32,
+    /// The command to execute
+    pub command: Command,
+}
+
+impl Request {
+    /// Create a request for the current protocol version.
+    #[must_use]
+    pub fn new(command: Command) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            command,
+        }
+    }
+}
+
+/// Commands understood by the daemon.
+///
+/// Commands taking an `output` apply to the wallpaper shown on that output,
+/// or to every wallpaper when `None`. Outputs sharing the "all" wallpaper
+/// share its slideshow, so stepping one of them steps all of them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Command {
+    /// Show the next image of the slideshow
+    Next { output: Option<String> },
+    /// Show the previous image of the slideshow
+    Previous { output: Option<String> },
+    /// Stop slideshow rotation and animation playback
+    Pause { output: Option<String> },
+    /// Continue slideshow rotation and animation playback
+    Resume { output: Option<String> },
+    /// Re-read the configuration and rebuild all wallpapers
+    Reload,
+    /// Report the state of every output
+    Status,
+}
+
+/// Replies sent by the daemon.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum Response {
+    /// The command was applied
+    Ok,
+    /// Runtime state of each output
+    Status(Vec<OutputStatus>),
+    /// The command could not be applied
+    Error(String),
+}
+
+/// Runtime state of the wallpaper on a single output.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OutputStatus {
+    /// Name of the output, e.g. `DP-1`
+    pub output: String,
+    /// Config entry driving this output (`all` or the output name)
+    pub entry: String,
+    /// The source currently on screen
+    pub source: Option<Source>,
+    /// Whether rotation and playback are paused
+    pub paused: bool,
+    /// Number of images in the slideshow
+    pub queue_len: usize,
+    /// Seconds until the next slideshow rotation, if one is scheduled
+    pub next_rotation_secs: Option<u64>,
+}
+
+/// Serialize a message as a single newline-terminated line.
+///
+/// # Errors
+///
+/// Fails if the message cannot be represented as RON.
+pub fn encode<T: Serialize>(message: &T) -> Result<String, ron::Error> {
+    let mut line = ron::to_string(message)?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// Parse a message from a single line.
+///
+/// # Errors
+///
+/// Fails if the line is not a valid RON encoding of `T`.
+pub fn decode<T: DeserializeOwned>(line: &str) -> Result<T, ron::error::SpannedError> {
+    ron::from_str(line.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_round_trip() {
+        let request = Request::new(Command::Next {
+            output: Some("DP-1".into()),
+        });
+
+        let line = encode(&request).unwrap();
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+
+        let decoded: Request = decode(&line).unwrap();
+        assert_eq!(decoded, request);
+        assert_eq!(decoded.version, PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        let response = Response::Status(vec![OutputStatus {
+            output: "HDMI-A-1".into(),
+            entry: "all".into(),
+            source: Some(Source::Path(PathBuf::from("/tmp/a\nb.png"))),
+            paused: true,
+            queue_len: 3,
+            next_rotation_secs: None,
+        }]);
+
+        let line = encode(&response).unwrap();
+        assert_eq!(line.matches('\n').count(), 1);
+        assert_eq!(decode::<Response>(&line).unwrap(), response);
+    }
+}
@@ -1,16 +1,25 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod ipc;
+pub mod schedule;
 pub mod state;
 
+pub use schedule::{Schedule, ScheduleSlot, SolarConfig, TimeOfDay};
+
 use cosmic_config::{Config as CosmicConfig, ConfigGet, ConfigSet};
 use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, collections::HashSet, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 pub const NAME: &str = "io.github.olafkfreund.CosmicExtBg";
 pub const BACKGROUNDS: &str = "backgrounds";
 pub const DEFAULT_BACKGROUND: &str = "all";
 pub const SAME_ON_ALL: &str = "same-on-all";
+pub const CACHE: &str = "cache";
 
 /// Create a context to the `cosmic-ext-bg` config.
 ///
@@ -40,6 +49,11 @@ impl Context {
         }
     }
 
+    /// Get the image cache limits, falling back to the defaults.
+    pub fn cache(&self) -> CacheConfig {
+        self.0.get::<CacheConfig>(CACHE).unwrap_or_default()
+    }
+
     pub fn default_background(&self) -> Entry {
         self.entry("all").unwrap_or_else(|_| Entry::fallback())
     }
@@ -95,6 +109,29 @@ pub struct Entry {
     pub scaling_mode: ScalingMode,
     #[serde(default)]
     pub sampling_method: SamplingMethod,
+    /// effect used when the wallpaper changes
+    #[serde(default)]
+    pub transition: Transition,
+    /// span one image across all outputs of this entry
+    #[serde(default)]
+    pub span: Option<SpanConfig>,
+    /// show a different image of the slideshow on each output of this entry
+    #[serde(default)]
+    pub distinct_per_output: bool,
+    /// keep animations and videos of the slideshow on screen until they have
+    /// played through, even past the rotation frequency
+    #[serde(default)]
+    pub hold_animations: bool,
+    /// color and blur adjustments applied to the scaled wallpaper
+    #[serde(default)]
+    pub adjustments: Option<Adjustments>,
+    /// bits per channel of the buffers the wallpaper is drawn into
+    #[serde(default)]
+    pub color_depth: ColorDepth,
+    /// ICC profiles of the displays by output name, which frames drawn for
+    /// an output are converted to from sRGB
+    #[serde(default)]
+    pub color_profiles: HashMap<String, PathBuf>,
 }
 
 /// A background image which is colored.
@@ -214,7 +251,7 @@ impl Default for VideoConfig {
 /// Animated image background configuration
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct AnimatedConfig {
-    /// Path to animated image file (GIF, WebP, APNG)
+    /// Path to animated image file (GIF, WebP, APNG, JPEG XL, AVIF)
     pub path: PathBuf,
     /// Optional FPS limit (None means use source FPS)
     #[serde(default)]
@@ -222,6 +259,22 @@ pub struct AnimatedConfig {
     /// Optional loop count (None means infinite)
     #[serde(default)]
     pub loop_count: Option<u32>,
+    /// Memory in MiB the decoded frames may use before they are decoded on
+    /// demand instead (default: 256).
+    #[serde(default = "default_memory_budget_mb")]
+    pub memory_budget_mb: u32,
+}
+
+fn default_memory_budget_mb() -> u32 {
+    256
+}
+
+impl AnimatedConfig {
+    /// Returns the memory budget in bytes.
+    #[must_use]
+    pub fn memory_budget(&self) -> u64 {
+        u64::from(self.memory_budget_mb) * 1024 * 1024
+    }
 }
 
 impl Default for AnimatedConfig {
@@ -230,6 +283,7 @@ impl Default for AnimatedConfig {
             path: PathBuf::new(),
             fps_limit: None,
             loop_count: None,
+            memory_budget_mb: default_memory_budget_mb(),
         }
     }
 }
@@ -247,6 +301,17 @@ pub enum Source {
     Video(VideoConfig),
     /// An animated image background (GIF, WebP, APNG)
     Animated(AnimatedConfig),
+    /// Different sources depending on the time of day
+    Schedule(Schedule),
+    /// Day and night sources following sunrise and sunset
+    Solar(SolarConfig),
+}
+
+impl Source {
+    /// Whether the source picks another source by the time of day.
+    pub fn is_timed(&self) -> bool {
+        matches!(self, Source::Schedule(_) | Source::Solar(_))
+    }
 }
 
 impl Entry {
@@ -260,6 +325,13 @@ impl Entry {
             filter_method: FilterMethod::default(),
             scaling_mode: ScalingMode::default(),
             sampling_method: SamplingMethod::default(),
+            transition: Transition::default(),
+            span: None,
+            distinct_per_output: false,
+            hold_animations: false,
+            adjustments: None,
+            color_depth: ColorDepth::default(),
+            color_profiles: HashMap::new(),
         }
     }
 
@@ -275,12 +347,19 @@ impl Entry {
             filter_method: FilterMethod::default(),
             scaling_mode: ScalingMode::default(),
             sampling_method: SamplingMethod::default(),
+            transition: Transition::default(),
+            span: None,
+            distinct_per_output: false,
+            hold_animations: false,
+            adjustments: None,
+            color_depth: ColorDepth::default(),
+            color_profiles: HashMap::new(),
         }
     }
 }
 
 /// Image filtering method
-#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
 pub enum FilterMethod {
     // nearest neighbor filtering
     Nearest,
@@ -289,18 +368,60 @@ pub enum FilterMethod {
     // lanczos filtering with window 3
     #[default]
     Lanczos,
+    /// Average of the source pixels each output pixel covers
+    Box,
+    /// Mitchell-Netravali cubic filtering, softer than Lanczos with less ringing
+    Mitchell,
+    /// Catmull-Rom cubic filtering, sharp with little ringing
+    CatmullRom,
+    /// Linear filtering of a copy a few times the output size, for quickly
+    /// shrinking very large images
+    SuperSampling,
+}
+
+impl FilterMethod {
+    /// A filter no slower than linear filtering, for frames that are scaled
+    /// over and over. Nearest and box filtering are kept as they are.
+    #[must_use]
+    pub fn for_animation(self) -> Self {
+        match self {
+            FilterMethod::Nearest | FilterMethod::Linear | FilterMethod::Box => self,
+            FilterMethod::Lanczos
+            | FilterMethod::Mitchell
+            | FilterMethod::CatmullRom
+            | FilterMethod::SuperSampling => FilterMethod::Linear,
+        }
+    }
 }
 
 impl From<FilterMethod> for image::imageops::FilterType {
     fn from(method: FilterMethod) -> Self {
         match method {
             FilterMethod::Nearest => image::imageops::FilterType::Nearest,
-            FilterMethod::Linear => image::imageops::FilterType::Triangle,
+            FilterMethod::Linear | FilterMethod::Box | FilterMethod::SuperSampling => {
+                image::imageops::FilterType::Triangle
+            }
             FilterMethod::Lanczos => image::imageops::FilterType::Lanczos3,
+            FilterMethod::Mitchell | FilterMethod::CatmullRom => {
+                image::imageops::FilterType::CatmullRom
+            }
         }
     }
 }
 
+/// Bits per channel of the buffers a wallpaper is drawn into
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// 10 bits for sources with more than 8 bits per channel, such as HDR,
+    /// EXR or 16-bit PNG images, when the compositor supports it
+    #[default]
+    Auto,
+    /// Always 8 bits
+    Eight,
+    /// Always 10 bits when the compositor supports it
+    Ten,
+}
+
 /// Image filtering method
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
 pub enum SamplingMethod {
@@ -309,7 +430,6 @@ pub enum SamplingMethod {
     Alphanumeric,
     // Rotate through images in Random order
     Random,
-    // TODO GnomeWallpapers
 }
 
 /// Image scaling mode
@@ -322,6 +442,241 @@ pub enum ScalingMode {
     /// Zoom the image so that it fill the whole area
     #[default]
     Zoom,
+    /// Show the image pixel for pixel in the middle of the area, cropping it
+    /// or filling the rest with the given RGB color
+    Center([f32; 3]),
+    /// Repeat the image from the top left corner, scaled by the given factor
+    Tile(f32),
+    /// Fit the image and fill the rest of the area with a blurred, zoomed
+    /// copy of it
+    FitBlur,
+    /// Zoom to fill the area, then slowly pan and zoom across the image
+    KenBurns(KenBurns),
+}
+
+/// Slow pan and zoom across a still image, drawn at a low frame rate
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct KenBurns {
+    /// Magnification at the close end of the motion, e.g. `1.25` for 25% closer
+    pub zoom: f32,
+    /// Passes across the image per rotation interval
+    pub speed: f32,
+    /// Most frames drawn per second
+    pub max_fps: u32,
+}
+
+impl Default for KenBurns {
+    fn default() -> Self {
+        Self {
+            zoom: 1.25,
+            speed: 1.0,
+            max_fps: 10,
+        }
+    }
+}
+
+impl KenBurns {
+    /// Returns zoom clamped to a sane range (1.0..=4.0).
+    #[must_use]
+    pub fn clamped_zoom(&self) -> f32 {
+        if self.zoom.is_finite() {
+            self.zoom.clamp(1.0, 4.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns speed clamped to a sane range (0.01..=100.0).
+    #[must_use]
+    pub fn clamped_speed(&self) -> f32 {
+        if self.speed.is_finite() {
+            self.speed.clamp(0.01, 100.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns max_fps clamped to a safe range (1..=60).
+    #[must_use]
+    pub fn clamped_fps(&self) -> u32 {
+        self.max_fps.clamp(1, 60)
+    }
+}
+
+/// Effect used to change from one wallpaper to the next
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// Swap to the next wallpaper at once
+    #[default]
+    None,
+    /// Fade the next wallpaper in over the previous one
+    Crossfade,
+    /// Reveal the next wallpaper from left to right
+    Wipe,
+    /// Push the previous wallpaper out to the left
+    Slide,
+    /// Zoom into the previous wallpaper while fading to the next
+    ZoomIn,
+}
+
+/// Transition between wallpapers, on slideshow rotation and source changes
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Transition {
+    /// The effect to use
+    pub kind: TransitionKind,
+    /// Length of the transition in milliseconds
+    pub duration_ms: u64,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self {
+            kind: TransitionKind::None,
+            duration_ms: 1000,
+        }
+    }
+}
+
+impl Transition {
+    /// Whether changes are animated at all.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.kind != TransitionKind::None && self.duration_ms > 0
+    }
+
+    /// Returns the duration clamped to a sane range (at most 10 seconds).
+    #[must_use]
+    pub fn clamped_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.duration_ms.min(10_000))
+    }
+}
+
+/// Spanning one wallpaper across the combined layout of several outputs
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct SpanConfig {
+    /// Space hidden behind the bezels between adjacent outputs, in logical pixels
+    pub bezel_gap: u32,
+}
+
+/// Adjustments applied to the wallpaper after scaling, such as dimming a
+/// bright image so desktop icons stay readable
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Adjustments {
+    /// Brightness multiplier, 1.0 leaves it unchanged and 0.0 is black
+    pub brightness: f32,
+    /// Contrast multiplier around mid gray, 1.0 leaves it unchanged
+    pub contrast: f32,
+    /// Saturation multiplier, 1.0 leaves it unchanged and 0.0 is grayscale
+    pub saturation: f32,
+    /// Gaussian blur radius (standard deviation) in pixels, 0.0 for none
+    pub blur: f32,
+    /// RGB color laid over the image
+    pub tint: Option<[f32; 3]>,
+    /// Opacity of `tint`, from 0.0 to 1.0
+    pub tint_opacity: f32,
+    /// Darkening towards the corners, from 0.0 to 1.0
+    pub vignette: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            blur: 0.0,
+            tint: None,
+            tint_opacity: 0.3,
+            vignette: 0.0,
+        }
+    }
+}
+
+fn clamp_finite(value: f32, min: f32, max: f32, fallback: f32) -> f32 {
+    if value.is_finite() {
+        value.clamp(min, max)
+    } else {
+        fallback
+    }
+}
+
+impl Adjustments {
+    /// Returns brightness clamped to a sane range (0.0..=2.0).
+    #[must_use]
+    pub fn clamped_brightness(&self) -> f32 {
+        clamp_finite(self.brightness, 0.0, 2.0, 1.0)
+    }
+
+    /// Returns contrast clamped to a sane range (0.0..=4.0).
+    #[must_use]
+    pub fn clamped_contrast(&self) -> f32 {
+        clamp_finite(self.contrast, 0.0, 4.0, 1.0)
+    }
+
+    /// Returns saturation clamped to a sane range (0.0..=4.0).
+    #[must_use]
+    pub fn clamped_saturation(&self) -> f32 {
+        clamp_finite(self.saturation, 0.0, 4.0, 1.0)
+    }
+
+    /// Returns the blur radius clamped to a sane range (0.0..=100.0).
+    #[must_use]
+    pub fn clamped_blur(&self) -> f32 {
+        clamp_finite(self.blur, 0.0, 100.0, 0.0)
+    }
+
+    /// Returns the tint with its opacity clamped to 0.0..=1.0, or `None`
+    /// if there is no visible tint.
+    #[must_use]
+    pub fn clamped_tint(&self) -> Option<([f32; 3], f32)> {
+        let opacity = clamp_finite(self.tint_opacity, 0.0, 1.0, 0.0);
+        self.tint.filter(|_| opacity > 0.0).map(|tint| (tint, opacity))
+    }
+
+    /// Returns vignette clamped to 0.0..=1.0.
+    #[must_use]
+    pub fn clamped_vignette(&self) -> f32 {
+        clamp_finite(self.vignette, 0.0, 1.0, 0.0)
+    }
+
+    /// Whether any of the color adjustments change the image.
+    #[must_use]
+    pub fn changes_color(&self) -> bool {
+        self.clamped_brightness() != 1.0
+            || self.clamped_contrast() != 1.0
+            || self.clamped_saturation() != 1.0
+            || self.clamped_tint().is_some()
+            || self.clamped_vignette() > 0.0
+    }
+
+    /// Whether the adjustments leave the image unchanged.
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        !self.changes_color() && self.clamped_blur() == 0.0
+    }
+}
+
+/// Limits of the daemon's decoded image cache
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Maximum number of images to cache
+    pub max_entries: usize,
+    /// Maximum total size in bytes (0 = unlimited)
+    pub max_size_bytes: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 50,
+            max_size_bytes: 512 * 1024 * 1024, // 512 MB default
+        }
+    }
 }
 
 impl Entry {
@@ -338,6 +693,7 @@ pub struct Config {
     pub outputs: HashSet<String>,
     pub backgrounds: Vec<Entry>,
     pub default_background: Entry,
+    pub cache: CacheConfig,
 }
 
 impl Default for Config {
@@ -347,6 +703,7 @@ impl Default for Config {
             outputs: HashSet::new(),
             backgrounds: Vec::new(),
             default_background: Entry::fallback(),
+            cache: CacheConfig::default(),
         }
     }
 }
@@ -364,6 +721,7 @@ impl Config {
         };
 
         config.default_background = context.default_background();
+        config.cache = context.cache();
 
         if !config.same_on_all {
             config.load_backgrounds(context);
@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Time-of-day schedules, showing a different source in each slot of the day
+//! or following the sun.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::Source;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// A local time of day, to the minute.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl TimeOfDay {
+    /// Create a time of day, or `None` if it is out of range.
+    #[must_use]
+    pub fn new(hour: u8, minute: u8) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(Self { hour, minute })
+    }
+
+    /// Seconds past midnight.
+    #[must_use]
+    pub fn seconds(self) -> u32 {
+        (u32::from(self.hour) * 60 + u32::from(self.minute)) * 60
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    /// Parse a 24-hour `HH:MM` time.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid time of day: {s} (expected HH:MM)");
+
+        let (hour, minute) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+
+        Self::new(hour, minute).ok_or_else(invalid)
+    }
+}
+
+/// One slot of a schedule, shown from `start` until the next slot begins.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ScheduleSlot {
+    /// Local time the slot begins
+    pub start: TimeOfDay,
+    /// What is shown during the slot
+    pub source: Source,
+}
+
+/// Sources shown at different times of the day, repeating daily.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct Schedule {
+    pub slots: Vec<ScheduleSlot>,
+}
+
+impl Schedule {
+    /// Whether the schedule has at least one slot and no nested schedules.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        !self.slots.is_empty() && self.slots.iter().all(|slot| !slot.source.is_timed())
+    }
+
+    /// The slot active at `seconds` past local midnight.
+    ///
+    /// Before the first slot of the day begins, the last slot of the
+    /// previous day is still active.
+    #[must_use]
+    pub fn active_slot(&self, seconds: u32) -> Option<&ScheduleSlot> {
+        let seconds = seconds % SECONDS_PER_DAY;
+
+        self.slots
+            .iter()
+            .filter(|slot| slot.start.seconds() <= seconds)
+            .max_by_key(|slot| slot.start)
+            .or_else(|| self.slots.iter().max_by_key(|slot| slot.start))
+    }
+
+    /// Seconds from `seconds` past local midnight until the next slot
+    /// begins, at most a day.
+    #[must_use]
+    pub fn seconds_until_next(&self, seconds: u32) -> Option<u32> {
+        let seconds = seconds % SECONDS_PER_DAY;
+
+        self.slots
+            .iter()
+            .map(|slot| {
+                (slot.start.seconds() + SECONDS_PER_DAY - seconds - 1) % SECONDS_PER_DAY + 1
+            })
+            .min()
+    }
+}
+
+/// A day and a night source, switched at sunrise and sunset for a location.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SolarConfig {
+    /// Degrees north of the equator, negative for south
+    pub latitude: f64,
+    /// Degrees east of Greenwich, negative for west
+    pub longitude: f64,
+    /// Shown while the sun is up
+    pub day: Box<Source>,
+    /// Shown while the sun is down
+    pub night: Box<Source>,
+    /// Crossfade between the two over civil twilight instead of switching
+    /// at sunrise and sunset
+    #[serde(default)]
+    pub twilight_blend: bool,
+}
+
+impl SolarConfig {
+    /// Whether the location is on Earth and neither source is itself timed.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude)
+            && (-180.0..=180.0).contains(&self.longitude)
+            && !self.day.is_timed()
+            && !self.night.is_timed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use std::path::PathBuf;
+
+    fn slot(start: &str, path: &str) -> ScheduleSlot {
+        ScheduleSlot {
+            start: start.parse().unwrap(),
+            source: Source::Path(PathBuf::from(path)),
+        }
+    }
+
+    fn day() -> Schedule {
+        Schedule {
+            slots: vec![
+                slot("18:30", "/evening.jpg"),
+                slot("07:00", "/morning.jpg"),
+                slot("12:00", "/afternoon.jpg"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_time_of_day_parse() {
+        assert_eq!("7:05".parse(), Ok(TimeOfDay { hour: 7, minute: 5 }));
+        assert_eq!(TimeOfDay::new(23, 59).unwrap().to_string(), "23:59");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("12".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn test_active_slot() {
+        let schedule = day();
+        let active = |hour: u32, minute: u32| {
+            let slot = schedule.active_slot((hour * 60 + minute) * 60).unwrap();
+            slot.start.to_string()
+        };
+
+        assert_eq!(active(7, 0), "07:00");
+        assert_eq!(active(11, 59), "07:00");
+        assert_eq!(active(15, 0), "12:00");
+        // The evening slot lasts past midnight until the morning one.
+        assert_eq!(active(23, 0), "18:30");
+        assert_eq!(active(3, 0), "18:30");
+    }
+
+    #[test]
+    fn test_seconds_until_next() {
+        let schedule = day();
+
+        assert_eq!(schedule.seconds_until_next(11 * 3600), Some(3600));
+        // At a boundary, the next one is the following slot.
+        assert_eq!(
+            schedule.seconds_until_next(12 * 3600),
+            Some(6 * 3600 + 1800)
+        );
+        // Wraps around midnight.
+        assert_eq!(schedule.seconds_until_next(23 * 3600), Some(8 * 3600));
+        assert_eq!(Schedule::default().seconds_until_next(0), None);
+    }
+
+    #[test]
+    fn test_validity() {
+        assert!(day().is_valid());
+        assert!(!Schedule::default().is_valid());
+
+        let nested = Schedule {
+            slots: vec![ScheduleSlot {
+                start: TimeOfDay::new(0, 0).unwrap(),
+                source: Source::Schedule(day()),
+            }],
+        };
+        assert!(!nested.is_valid());
+    }
+
+    #[test]
+    fn test_solar_validity() {
+        let mut solar = SolarConfig {
+            latitude: 59.9,
+            longitude: 10.7,
+            day: Box::new(Source::Path(PathBuf::from("/day.jpg"))),
+            night: Box::new(Source::Color(Color::Single([0.0, 0.0, 0.1]))),
+            twilight_blend: true,
+        };
+        assert!(solar.is_valid());
+
+        solar.latitude = 91.0;
+        assert!(!solar.is_valid());
+
+        solar.latitude = 0.0;
+        solar.night = Box::new(Source::Schedule(day()));
+        assert!(!solar.is_valid());
+    }
+
+    #[test]
+    fn test_ron_round_trip() {
+        let mut schedule = day();
+        schedule.slots.push(ScheduleSlot {
+            start: TimeOfDay::new(22, 0).unwrap(),
+            source: Source::Color(Color::Single([0.0, 0.0, 0.1])),
+        });
+
+        let source = Source::Schedule(schedule);
+        let ron = ron::to_string(&source).unwrap();
+        assert_eq!(ron::from_str::<Source>(&ron).unwrap(), source);
+    }
+}
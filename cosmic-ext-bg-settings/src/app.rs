@@ -230,13 +230,33 @@ async fn open_file_picker(source_type: SourceType) -> Result<std::path::PathBuf,
 fn create_file_filters(source_type: SourceType) -> Vec<FileFilter> {
     match source_type {
         SourceType::Static => {
-            vec![FileFilter::new("Images").glob("*.png").glob("*.jpg").glob("*.jpeg").glob("*.webp").glob("*.jxl")]
+            vec![
+                FileFilter::new("Images")
+                    .glob("*.png")
+                    .glob("*.jpg")
+                    .glob("*.jpeg")
+                    .glob("*.webp")
+                    .glob("*.jxl")
+                    .glob("*.avif")
+                    .glob("*.heic")
+                    .glob("*.heif")
+                    .glob("*.svg")
+                    .glob("*.svgz"),
+                FileFilter::new("GNOME Slideshows").glob("*.xml"),
+            ]
         }
         SourceType::Video => {
             vec![FileFilter::new("Videos").glob("*.mp4").glob("*.mkv").glob("*.webm").glob("*.avi").glob("*.mov")]
         }
         SourceType::Animated => {
-            vec![FileFilter::new("Animated Images").glob("*.gif").glob("*.webp").glob("*.apng")]
+            vec![
+                FileFilter::new("Animated Images")
+                    .glob("*.gif")
+                    .glob("*.webp")
+                    .glob("*.apng")
+                    .glob("*.jxl")
+                    .glob("*.avif"),
+            ]
         }
         SourceType::Shader => {
             vec![FileFilter::new("Shaders").glob("*.wgsl").glob("*.glsl")]
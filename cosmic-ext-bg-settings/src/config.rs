@@ -60,6 +60,6 @@ pub fn source_path(source: &Source) -> Option<&std::path::Path> {
         Source::Video(v) => Some(v.path.as_path()),
         Source::Animated(a) => Some(a.path.as_path()),
         Source::Shader(s) => s.custom_path.as_deref(),
-        Source::Color(_) => None,
+        Source::Color(_) | Source::Schedule(_) | Source::Solar(_) => None,
     }
 }
@@ -8,8 +8,8 @@ use cosmic::iced::Length;
 use cosmic::widget::{button, column, container, dropdown, row, text, text_input, toggler};
 use cosmic::Element;
 use cosmic_ext_bg_config::{
-    AnimatedConfig, Color, Entry, Gradient, ScalingMode, ShaderConfig, ShaderPreset, Source,
-    VideoConfig,
+    AnimatedConfig, Color, Entry, Gradient, KenBurns, ScalingMode, ShaderConfig, ShaderPreset,
+    Source, VideoConfig,
 };
 
 use crate::message::{Message, SourceType};
@@ -25,7 +25,15 @@ static SOURCE_TYPE_NAMES: &[&str] = &[
 ];
 
 /// Scaling mode dropdown options
-static SCALING_MODE_NAMES: &[&str] = &["Zoom (fill)", "Fit (letterbox)", "Stretch"];
+static SCALING_MODE_NAMES: &[&str] = &[
+    "Zoom (fill)",
+    "Fit (letterbox)",
+    "Stretch",
+    "Ken Burns (pan & zoom)",
+    "Center",
+    "Tile",
+    "Fit (blurred fill)",
+];
 
 /// Shader preset dropdown options
 static SHADER_PRESET_NAMES: &[&str] = &["Plasma", "Waves", "Gradient"];
@@ -53,6 +61,8 @@ pub struct WallpaperPage {
     pub animated_fps: Option<u32>,
     /// Animated loop count
     pub animated_loop_count: Option<u32>,
+    /// Animated frame memory budget in MiB
+    pub animated_memory_budget_mb: u32,
     /// Shader FPS limit
     pub shader_fps: u32,
     /// Rotation frequency for directories
@@ -71,6 +81,8 @@ pub struct WallpaperPage {
     pub gradient_radius: f32,
     /// Available outputs (reserved for future multi-display support)
     pub _available_outputs: Vec<String>,
+    /// Entry the page was loaded from, keeping what the page doesn't edit
+    entry: Entry,
     /// Selected source type index for dropdown
     source_type_idx: usize,
     /// Selected scaling mode index for dropdown
@@ -92,6 +104,7 @@ impl Default for WallpaperPage {
             video_hw_accel: true,
             animated_fps: None,
             animated_loop_count: None,
+            animated_memory_budget_mb: AnimatedConfig::default().memory_budget_mb,
             shader_fps: 30,
             rotation_frequency: 900,
             filter_by_theme: false,
@@ -101,6 +114,7 @@ impl Default for WallpaperPage {
             gradient_colors: vec![[0, 0, 128], [128, 0, 128]],
             gradient_radius: 0.5,
             _available_outputs: vec!["all".to_string()],
+            entry: Entry::fallback(),
             source_type_idx: 0,
             scaling_mode_idx: 0,
             shader_preset_idx: 0,
@@ -119,6 +133,12 @@ impl WallpaperPage {
 
     /// Load settings from an entry
     pub fn load_from_entry(&mut self, entry: &Entry) {
+        self.entry = entry.clone();
+        self.load_fields(entry);
+    }
+
+    /// Load the fields the page edits from an entry
+    fn load_fields(&mut self, entry: &Entry) {
         self.selected_output = entry.output.clone();
         self.scaling_mode = entry.scaling_mode.clone();
         self.rotation_frequency = entry.rotation_frequency;
@@ -129,6 +149,10 @@ impl WallpaperPage {
             ScalingMode::Zoom => 0,
             ScalingMode::Fit(_) => 1,
             ScalingMode::Stretch => 2,
+            ScalingMode::KenBurns(_) => 3,
+            ScalingMode::Center(_) => 4,
+            ScalingMode::Tile(_) => 5,
+            ScalingMode::FitBlur => 6,
         };
 
         match &entry.source {
@@ -151,6 +175,7 @@ impl WallpaperPage {
                 self.selected_path = Some(config.path.clone());
                 self.animated_fps = config.fps_limit;
                 self.animated_loop_count = config.loop_count;
+                self.animated_memory_budget_mb = config.memory_budget_mb;
             }
             Source::Shader(config) => {
                 self.source_type = SourceType::Shader;
@@ -189,12 +214,47 @@ impl WallpaperPage {
                     self.gradient_radius = gradient.radius;
                 }
             },
+            // Schedules are edited with cosmic-ext-bg-ctl; show the first slot.
+            Source::Schedule(schedule) => {
+                if let Some(slot) = schedule.slots.first() {
+                    let mut slot_entry = entry.clone();
+                    slot_entry.source = slot.source.clone();
+                    self.load_fields(&slot_entry);
+                }
+            }
+            // Likewise for solar sources; show the day source.
+            Source::Solar(solar) => {
+                let mut day_entry = entry.clone();
+                day_entry.source = Source::clone(&solar.day);
+                self.load_fields(&day_entry);
+            }
         }
     }
 
     /// Build the current entry from page state
+    ///
+    /// Starts from the loaded entry, so settings without a control on this
+    /// page are kept. Schedules and solar sources are kept as well, as they
+    /// are edited with cosmic-ext-bg-ctl.
     pub fn build_entry(&self) -> Entry {
-        let source = match self.source_type {
+        let mut entry = self.entry.clone();
+        entry.output = if self.apply_to_all {
+            "all".to_string()
+        } else {
+            self.selected_output.clone()
+        };
+        if !entry.source.is_timed() {
+            entry.source = self.build_source();
+        }
+        entry.scaling_mode = self.scaling_mode.clone();
+        entry.rotation_frequency = self.rotation_frequency;
+        entry.filter_by_theme = self.filter_by_theme;
+        entry
+    }
+
+    /// Build the source from page state
+    fn build_source(&self) -> Source {
+        match self.source_type {
             SourceType::Static => Source::Path(self.selected_path.clone().unwrap_or_default()),
             SourceType::Video => Source::Video(VideoConfig {
                 path: self.selected_path.clone().unwrap_or_default(),
@@ -206,6 +266,7 @@ impl WallpaperPage {
                 path: self.selected_path.clone().unwrap_or_default(),
                 fps_limit: self.animated_fps,
                 loop_count: self.animated_loop_count,
+                memory_budget_mb: self.animated_memory_budget_mb,
             }),
             SourceType::Shader => Source::Shader(ShaderConfig {
                 preset: if self.custom_shader_path.is_none() {
@@ -230,20 +291,7 @@ impl WallpaperPage {
                     .into(),
                 radius: self.gradient_radius,
             })),
-        };
-
-        let mut entry = Entry::new(
-            if self.apply_to_all {
-                "all".to_string()
-            } else {
-                self.selected_output.clone()
-            },
-            source,
-        );
-        entry.scaling_mode = self.scaling_mode.clone();
-        entry.rotation_frequency = self.rotation_frequency;
-        entry.filter_by_theme = self.filter_by_theme;
-        entry
+        }
     }
 
     /// Build the view for this page
@@ -423,6 +471,23 @@ impl WallpaperPage {
             column().into()
         };
 
+        // Sources following the time of day are only edited with cosmic-ext-bg-ctl
+        let source_section: Element<'_, Message> = if self.entry.source.is_timed() {
+            text::body(
+                "This wallpaper changes with the time of day; edit its sources with \
+                 cosmic-ext-bg-ctl.",
+            )
+            .into()
+        } else {
+            column()
+                .spacing(12)
+                .push(source_dropdown)
+                .push(source_options)
+                .push(video_options)
+                .push(animated_options)
+                .into()
+        };
+
         // Scaling mode dropdown
         let scaling_dropdown =
             dropdown(SCALING_MODE_NAMES, Some(self.scaling_mode_idx), |idx| {
@@ -430,6 +495,10 @@ impl WallpaperPage {
                     0 => ScalingMode::Zoom,
                     1 => ScalingMode::Fit([0.0, 0.0, 0.0]),
                     2 => ScalingMode::Stretch,
+                    3 => ScalingMode::KenBurns(KenBurns::default()),
+                    4 => ScalingMode::Center([0.0, 0.0, 0.0]),
+                    5 => ScalingMode::Tile(1.0),
+                    6 => ScalingMode::FitBlur,
                     _ => ScalingMode::Zoom,
                 };
                 Message::ScalingModeChanged(mode)
@@ -454,10 +523,7 @@ impl WallpaperPage {
             .spacing(12)
             .padding(16)
             .push(text::title4("Source Type"))
-            .push(source_dropdown)
-            .push(source_options)
-            .push(video_options)
-            .push(animated_options)
+            .push(source_section)
             .push(text::title4("Scaling"))
             .push(scaling_dropdown)
             .push(
@@ -0,0 +1,230 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Brightness, contrast, saturation, blur, tint and vignette adjustments of
+//! the scaled wallpaper.
+
+use cosmic_ext_bg_config::Adjustments;
+use image::{DynamicImage, Pixel};
+
+/// Rec. 709 luma weights, for desaturating.
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Apply `adjustments` to a scaled frame.
+///
+/// Images with more than 8 bits per channel are adjusted at full precision;
+/// others stay 8-bit, which is much cheaper for video frames.
+pub fn apply(image: DynamicImage, adjustments: &Adjustments) -> DynamicImage {
+    let blur = adjustments.clamped_blur();
+    let image = if blur > 0.0 {
+        image.fast_blur(blur)
+    } else {
+        image
+    };
+
+    if !adjustments.changes_color() {
+        return image;
+    }
+
+    let color = ColorAdjust::new(adjustments, image.width(), image.height());
+
+    match image {
+        DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_)
+        | DynamicImage::ImageRgb32F(_)
+        | DynamicImage::ImageRgba32F(_) => {
+            let mut buffer = image.into_rgba32f();
+            for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+                let rgb = pixel.to_rgb().0;
+                let [r, g, b] = color.apply(rgb, x, y);
+                pixel.0[..3].copy_from_slice(&[r, g, b]);
+            }
+            DynamicImage::ImageRgba32F(buffer)
+        }
+        image => {
+            let mut buffer = image.into_rgba8();
+            for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+                let rgb = [0, 1, 2].map(|c| f32::from(pixel.0[c]) / 255.0);
+                let adjusted = color.apply(rgb, x, y);
+                for (channel, value) in pixel.0.iter_mut().zip(adjusted) {
+                    *channel = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            }
+            DynamicImage::ImageRgba8(buffer)
+        }
+    }
+}
+
+/// Per-pixel color adjustments with their settings clamped.
+struct ColorAdjust {
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    tint: Option<([f32; 3], f32)>,
+    vignette: f32,
+    center: (f32, f32),
+}
+
+impl ColorAdjust {
+    fn new(adjustments: &Adjustments, width: u32, height: u32) -> Self {
+        Self {
+            brightness: adjustments.clamped_brightness(),
+            contrast: adjustments.clamped_contrast(),
+            saturation: adjustments.clamped_saturation(),
+            tint: adjustments.clamped_tint(),
+            vignette: adjustments.clamped_vignette(),
+            center: (width as f32 / 2.0, height as f32 / 2.0),
+        }
+    }
+
+    fn apply(&self, rgb: [f32; 3], x: u32, y: u32) -> [f32; 3] {
+        let mut rgb = rgb.map(|c| (c * self.brightness - 0.5) * self.contrast + 0.5);
+
+        if self.saturation != 1.0 {
+            let luma: f32 = rgb.iter().zip(LUMA).map(|(c, w)| c * w).sum();
+            rgb = rgb.map(|c| luma + (c - luma) * self.saturation);
+        }
+
+        if let Some((tint, opacity)) = self.tint {
+            for (c, t) in rgb.iter_mut().zip(tint) {
+                *c += (t - *c) * opacity;
+            }
+        }
+
+        if self.vignette > 0.0 {
+            // Squared distance from the center, 1.0 in the corners.
+            let dx = (x as f32 + 0.5) / self.center.0 - 1.0;
+            let dy = (y as f32 + 0.5) / self.center.1 - 1.0;
+            let distance = (dx * dx + dy * dy) / 2.0;
+            let shade = 1.0 - self.vignette * distance;
+            rgb = rgb.map(|c| c * shade);
+        }
+
+        rgb.map(|c| c.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, Rgb32FImage, RgbImage, Rgba};
+
+    fn gray(width: u32, height: u32, value: u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(
+            width,
+            height,
+            Rgb([value, value, value]),
+        ))
+    }
+
+    fn pixel(image: &DynamicImage, x: u32, y: u32) -> [u8; 4] {
+        image.to_rgba8().get_pixel(x, y).0
+    }
+
+    #[test]
+    fn test_identity() {
+        let adjustments = Adjustments::default();
+        assert!(adjustments.is_identity());
+
+        let image = gray(4, 4, 100);
+        assert_eq!(apply(image.clone(), &adjustments), image);
+    }
+
+    #[test]
+    fn test_brightness_and_contrast() {
+        let dimmed = apply(
+            gray(2, 2, 200),
+            &Adjustments {
+                brightness: 0.5,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pixel(&dimmed, 0, 0), [100, 100, 100, 255]);
+
+        let flat = apply(
+            gray(2, 2, 200),
+            &Adjustments {
+                contrast: 0.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pixel(&flat, 1, 1), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn test_saturation_and_tint() {
+        let red =
+            DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 128])));
+
+        let grayscale = apply(
+            red.clone(),
+            &Adjustments {
+                saturation: 0.0,
+                ..Default::default()
+            },
+        );
+        let [r, g, b, a] = pixel(&grayscale, 0, 0);
+        assert!(r == g && g == b, "{r} {g} {b}");
+        // Alpha is kept.
+        assert_eq!(a, 128);
+
+        let tinted = apply(
+            red,
+            &Adjustments {
+                tint: Some([0.0, 0.0, 1.0]),
+                tint_opacity: 1.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pixel(&tinted, 0, 0), [0, 0, 255, 128]);
+    }
+
+    #[test]
+    fn test_vignette() {
+        let image = apply(
+            gray(101, 101, 200),
+            &Adjustments {
+                vignette: 1.0,
+                ..Default::default()
+            },
+        );
+
+        let center = pixel(&image, 50, 50)[0];
+        let edge = pixel(&image, 0, 50)[0];
+        let corner = pixel(&image, 0, 0)[0];
+        assert_eq!(center, 200);
+        assert!(corner < edge && edge < center, "{corner} {edge} {center}");
+    }
+
+    #[test]
+    fn test_keeps_precision() {
+        let deep = DynamicImage::ImageRgb32F(Rgb32FImage::from_pixel(2, 2, Rgb([0.5; 3])));
+        let adjusted = apply(
+            deep,
+            &Adjustments {
+                brightness: 0.999,
+                ..Default::default()
+            },
+        );
+
+        let DynamicImage::ImageRgba32F(buffer) = adjusted else {
+            panic!("expected a floating point image");
+        };
+        assert!((buffer.get_pixel(0, 0).0[0] - 0.4995).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_blur() {
+        let mut image = RgbImage::from_pixel(9, 9, Rgb([0, 0, 0]));
+        image.put_pixel(4, 4, Rgb([255, 255, 255]));
+
+        let blurred = apply(
+            DynamicImage::ImageRgb8(image),
+            &Adjustments {
+                blur: 2.0,
+                ..Default::default()
+            },
+        );
+        assert!(pixel(&blurred, 4, 4)[0] < 255);
+        assert!(pixel(&blurred, 5, 4)[0] > 0);
+    }
+}
@@ -1,9 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
-//! Animated image support for GIF, APNG, and animated WebP wallpapers.
+//! Animated image support for GIF, APNG, animated WebP, animated JPEG XL and
+//! AVIF sequence wallpapers.
 //!
 //! This module provides frame-by-frame playback of animated images,
-//! respecting per-frame delay timings for smooth animation.
+//! respecting per-frame delay timings for smooth animation. AVIF sequences
+//! are decoded through GStreamer, like videos.
 
 use crate::source::{Frame, SourceError, WallpaperSource};
 use cosmic_ext_bg_config::AnimatedConfig;
@@ -11,8 +13,12 @@ use image::{codecs::gif::GifDecoder, AnimationDecoder, DynamicImage};
 use std::{
     collections::VecDeque,
     fs::File,
-    io::BufReader,
-    path::PathBuf,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, TryRecvError},
+    },
     time::{Duration, Instant},
 };
 
@@ -25,6 +31,9 @@ const MAX_ANIMATED_FRAMES: usize = 5_000;
 /// low delays; browsers historically clamp to 10 ms to prevent CPU spin.
 const MIN_FRAME_DELAY_MS: u64 = 10;
 
+/// Frames decoded ahead of the one shown when decoding on demand.
+const STREAM_AHEAD_FRAMES: usize = 4;
+
 /// Helper to create decode errors with context
 fn decode_error(context: &str, err: impl std::fmt::Display) -> SourceError {
     SourceError::io(
@@ -40,6 +49,13 @@ fn calculate_frame_delay(delay: image::Delay) -> Duration {
     Duration::from_millis(delay_ms)
 }
 
+/// Frame delay of a JPEG XL frame lasting `ticks` at `numerator` /
+/// `denominator` ticks per second.
+fn tick_delay(ticks: u32, numerator: u32, denominator: u32) -> Duration {
+    let secs = f64::from(ticks) * f64::from(denominator) / f64::from(numerator.max(1));
+    Duration::from_secs_f64(secs).max(Duration::from_millis(MIN_FRAME_DELAY_MS))
+}
+
 /// A single frame from an animated image
 #[derive(Debug, Clone)]
 struct AnimatedFrame {
@@ -58,6 +74,10 @@ pub struct AnimatedSource {
     last_frame_time: Instant,
     current_frame_delay: Duration,
     loops_completed: u32,
+    /// Loop count stored in the file, used unless the config sets one
+    file_loop_count: Option<u32>,
+    /// Decoder of frames on demand, for images over the memory budget
+    stream: Option<FrameStream>,
     is_prepared: bool,
     target_size: Option<(u32, u32)>,
 }
@@ -72,6 +92,8 @@ impl AnimatedSource {
             last_frame_time: Instant::now(),
             current_frame_delay: Duration::from_millis(100),
             loops_completed: 0,
+            file_loop_count: None,
+            stream: None,
             is_prepared: false,
             target_size: None,
         })
@@ -81,14 +103,10 @@ impl AnimatedSource {
     fn load_frames(&mut self) -> Result<(), SourceError> {
         let path = &self.config.path;
 
-        // Detect format from extension
+        // Detect format from the file signature or extension
         let format = Self::detect_format(path)?;
 
-        match format {
-            AnimatedFormat::Gif => self.load_gif_frames()?,
-            AnimatedFormat::Apng => self.load_apng_frames()?,
-            AnimatedFormat::WebP => self.load_webp_frames()?,
-        }
+        self.load_image_frames(format)?;
 
         if self.frames.is_empty() {
             return Err(SourceError::Io(std::io::Error::new(
@@ -111,8 +129,15 @@ impl AnimatedSource {
         Ok(())
     }
 
-    /// Detect the animated image format from file extension
-    fn detect_format(path: &PathBuf) -> Result<AnimatedFormat, SourceError> {
+    /// Detect the animated image format from the file signature, or from the
+    /// file extension if the file can't be read
+    fn detect_format(path: &Path) -> Result<AnimatedFormat, SourceError> {
+        if let Ok(header) = read_header(path) {
+            if let Some(format) = AnimatedFormat::sniff(&header) {
+                return Ok(format);
+            }
+        }
+
         let ext = path
             .extension()
             .and_then(|e| e.to_str())
@@ -123,6 +148,8 @@ impl AnimatedSource {
             "gif" => Ok(AnimatedFormat::Gif),
             "apng" | "png" => Ok(AnimatedFormat::Apng),
             "webp" => Ok(AnimatedFormat::WebP),
+            "jxl" => Ok(AnimatedFormat::Jxl),
+            "avif" | "avifs" => Ok(AnimatedFormat::Avif),
             _ => Err(decode_error(
                 "Unsupported animated format",
                 ext,
@@ -130,15 +157,29 @@ impl AnimatedSource {
         }
     }
 
-    /// Load frames from a GIF file
-    fn load_gif_frames(&mut self) -> Result<(), SourceError> {
-        let file = File::open(&self.config.path)?;
-        let reader = BufReader::new(file);
+    /// Load the frames of the file, switching to decoding them on demand
+    /// once they outgrow the memory budget
+    fn load_image_frames(&mut self, format: AnimatedFormat) -> Result<(), SourceError> {
+        let frames = match open_frames(&self.config.path, format)? {
+            ImageFrames::Still(image) => {
+                self.frames.push_back(AnimatedFrame {
+                    image,
+                    delay: Duration::MAX, // Static image
+                });
+
+                return Ok(());
+            }
+            ImageFrames::Animated { frames, loop_count } => {
+                // Zero loops means forever.
+                self.file_loop_count = loop_count.filter(|&loops| loops > 0);
+                frames
+            }
+        };
 
-        let decoder = GifDecoder::new(reader)
-            .map_err(|e| decode_error("Failed to decode GIF", e))?;
+        let budget = self.config.memory_budget();
+        let mut used = 0;
 
-        for frame_result in decoder.into_frames() {
+        for frame in frames {
             if self.frames.len() >= MAX_ANIMATED_FRAMES {
                 tracing::warn!(
                     path = ?self.config.path,
@@ -148,111 +189,45 @@ impl AnimatedSource {
                 break;
             }
 
-            let frame = frame_result
-                .map_err(|e| decode_error("Failed to decode GIF frame", e))?;
-
-            let delay = calculate_frame_delay(frame.delay());
-            let image = DynamicImage::ImageRgba8(frame.into_buffer());
+            let frame = frame?;
 
-            self.frames.push_back(AnimatedFrame { image, delay });
-        }
-
-        Ok(())
-    }
-
-    /// Load frames from an APNG file
-    fn load_apng_frames(&mut self) -> Result<(), SourceError> {
-        use image::codecs::png::PngDecoder;
-
-        let file = File::open(&self.config.path)?;
-        let reader = BufReader::new(file);
-
-        let decoder = PngDecoder::new(reader)
-            .map_err(|e| decode_error("Failed to decode PNG", e))?;
-
-        // Check if it's actually animated
-        if !decoder.is_apng().unwrap_or(false) {
-            // Static PNG, load as single frame
-            let image = image::open(&self.config.path)
-                .map_err(|e| decode_error("Failed to decode image", e))?;
-
-            self.frames.push_back(AnimatedFrame {
-                image,
-                delay: Duration::MAX, // Static image
-            });
-
-            return Ok(());
-        }
-
-        // Load APNG frames
-        let apng_decoder = decoder.apng()
-            .map_err(|e| decode_error("Failed to create APNG decoder", e))?;
-
-        for frame_result in apng_decoder.into_frames() {
-            if self.frames.len() >= MAX_ANIMATED_FRAMES {
-                tracing::warn!(
+            used += frame.image.as_bytes().len() as u64;
+            if used > budget {
+                tracing::info!(
                     path = ?self.config.path,
-                    limit = MAX_ANIMATED_FRAMES,
-                    "Frame limit reached, truncating animated image"
+                    budget_mb = self.config.memory_budget_mb,
+                    "Animated image exceeds its memory budget, decoding frames on demand"
                 );
-                break;
+                self.frames.clear();
+                return self.start_stream(format);
             }
 
-            let frame = frame_result
-                .map_err(|e| decode_error("Failed to decode APNG frame", e))?;
-
-            let delay = calculate_frame_delay(frame.delay());
-            let image = DynamicImage::ImageRgba8(frame.into_buffer());
+            self.frames.push_back(frame);
+        }
 
-            self.frames.push_back(AnimatedFrame { image, delay });
+        // A single frame is a still image, however long it lasts.
+        if let [frame] = self.frames.make_contiguous() {
+            frame.delay = Duration::MAX;
         }
 
         Ok(())
     }
 
-    /// Load frames from an animated WebP file
-    fn load_webp_frames(&mut self) -> Result<(), SourceError> {
-        use image::codecs::webp::WebPDecoder;
-
-        let file = File::open(&self.config.path)?;
-        let reader = BufReader::new(file);
-
-        let decoder = WebPDecoder::new(reader)
-            .map_err(|e| decode_error("Failed to decode WebP", e))?;
+    /// Decode frames in the background from now on, showing the first one
+    fn start_stream(&mut self, format: AnimatedFormat) -> Result<(), SourceError> {
+        let mut stream = FrameStream::spawn(self.config.path.clone(), format)?;
 
-        // Check if it has animation
-        if !decoder.has_animation() {
-            // Static WebP, load as single frame
-            let image = image::open(&self.config.path)
-                .map_err(|e| decode_error("Failed to decode image", e))?;
+        // Wait for the first frame, as for a fully decoded image.
+        let (_, first) = stream
+            .receiver
+            .get_mut()
+            .map_err(|_| decode_error("Frame stream poisoned", ""))?
+            .recv()
+            .map_err(|_| decode_error("Failed to decode first frame", ""))?;
 
-            self.frames.push_back(AnimatedFrame {
-                image,
-                delay: Duration::MAX,
-            });
-
-            return Ok(());
-        }
-
-        // Load WebP frames
-        for frame_result in decoder.into_frames() {
-            if self.frames.len() >= MAX_ANIMATED_FRAMES {
-                tracing::warn!(
-                    path = ?self.config.path,
-                    limit = MAX_ANIMATED_FRAMES,
-                    "Frame limit reached, truncating animated image"
-                );
-                break;
-            }
-
-            let frame = frame_result
-                .map_err(|e| decode_error("Failed to decode WebP frame", e))?;
-
-            let delay = calculate_frame_delay(frame.delay());
-            let image = DynamicImage::ImageRgba8(frame.into_buffer());
-
-            self.frames.push_back(AnimatedFrame { image, delay });
-        }
+        self.frames.push_back(first);
+        self.current_frame_idx = 0;
+        self.stream = Some(stream);
 
         Ok(())
     }
@@ -267,8 +242,20 @@ impl AnimatedSource {
         }
     }
 
+    /// Whether the configured or stored number of loops has been played
+    fn loops_done(&self) -> bool {
+        self.config
+            .loop_count
+            .or(self.file_loop_count)
+            .is_some_and(|max_loops| self.loops_completed >= max_loops)
+    }
+
     /// Advance to the next frame
     fn advance_frame(&mut self) -> bool {
+        if self.stream.is_some() {
+            return self.advance_stream();
+        }
+
         if self.frames.len() <= 1 {
             return false; // Static image or empty
         }
@@ -280,10 +267,8 @@ impl AnimatedSource {
             self.loops_completed += 1;
 
             // Check if we've hit the loop limit
-            if let Some(max_loops) = self.config.loop_count {
-                if self.loops_completed >= max_loops {
-                    return false; // Stop animating
-                }
+            if self.loops_done() {
+                return false; // Stop animating
             }
         }
 
@@ -296,6 +281,43 @@ impl AnimatedSource {
 
         true
     }
+
+    /// Advance to the next frame from the background decoder, if it's ready
+    fn advance_stream(&mut self) -> bool {
+        // The last frame stays up once the loops are done.
+        if self.loops_done() {
+            return false;
+        }
+
+        let Some(stream) = self.stream.as_mut() else {
+            return false;
+        };
+        let Ok(receiver) = stream.receiver.get_mut() else {
+            return false;
+        };
+
+        let (index, frame) = match receiver.try_recv() {
+            Ok(next) => next,
+            // Not decoded yet, so the current frame is shown a little longer.
+            Err(TryRecvError::Empty) => return false,
+            // The decoder failed and logged why.
+            Err(TryRecvError::Disconnected) => return false,
+        };
+
+        if index == 0 {
+            self.loops_completed += 1;
+            if self.loops_done() {
+                return false;
+            }
+        }
+
+        self.current_frame_delay = self.apply_fps_limit(frame.delay);
+        self.frames.clear();
+        self.frames.push_back(frame);
+        self.last_frame_time = Instant::now();
+
+        true
+    }
 }
 
 impl WallpaperSource for AnimatedSource {
@@ -327,7 +349,17 @@ impl WallpaperSource for AnimatedSource {
     }
 
     fn is_animated(&self) -> bool {
-        self.frames.len() > 1
+        self.frames.len() > 1 || self.stream.is_some()
+    }
+
+    fn is_high_depth(&self) -> bool {
+        self.frames
+            .front()
+            .is_some_and(|frame| crate::draw::is_high_depth(&frame.image))
+    }
+
+    fn played_through(&self) -> bool {
+        self.loops_completed > 0
     }
 
     fn prepare(&mut self, width: u32, height: u32) -> Result<(), SourceError> {
@@ -344,9 +376,12 @@ impl WallpaperSource for AnimatedSource {
     }
 
     fn release(&mut self) {
+        // Dropping the receiver stops the background decoder.
+        self.stream = None;
         self.frames.clear();
         self.current_frame_idx = 0;
         self.loops_completed = 0;
+        self.file_loop_count = None;
         self.is_prepared = false;
 
         tracing::debug!("Animated source released");
@@ -357,11 +392,17 @@ impl WallpaperSource for AnimatedSource {
             .map(|f| format!("{:?}", f))
             .unwrap_or_else(|_| "Unknown".to_string());
 
+        let frames = if self.stream.is_some() {
+            "decoded on demand".to_string()
+        } else {
+            format!("{} frames", self.frames.len())
+        };
+
         format!(
-            "Animated {}: {} ({} frames)",
+            "Animated {}: {} ({})",
             format,
             self.config.path.display(),
-            self.frames.len()
+            frames
         )
     }
 }
@@ -372,31 +413,444 @@ impl Drop for AnimatedSource {
     }
 }
 
+/// Frames of an animated image, decoded one at a time in file order
+type FrameIter = Box<dyn Iterator<Item = Result<AnimatedFrame, SourceError>>>;
+
+/// Frames of an animated image file
+enum ImageFrames {
+    /// A PNG, WebP or AVIF file without animation
+    Still(DynamicImage),
+    Animated {
+        frames: FrameIter,
+        /// Loop count stored in the file
+        loop_count: Option<u32>,
+    },
+}
+
+/// Open an animated image file to decode its frames one by one.
+fn open_frames(path: &Path, format: AnimatedFormat) -> Result<ImageFrames, SourceError> {
+    use image::codecs::{png::PngDecoder, webp::WebPDecoder};
+
+    // Frames from the `image` codecs.
+    let animated = move |frames: image::Frames<'static>| ImageFrames::Animated {
+        frames: Box::new(frames.map(move |frame| animated_frame(frame, format))),
+        loop_count: None,
+    };
+
+    match format {
+        AnimatedFormat::Gif => {
+            let reader = BufReader::new(File::open(path)?);
+            let decoder =
+                GifDecoder::new(reader).map_err(|e| decode_error("Failed to decode GIF", e))?;
+
+            Ok(animated(decoder.into_frames()))
+        }
+        AnimatedFormat::Apng => {
+            let reader = BufReader::new(File::open(path)?);
+            let decoder =
+                PngDecoder::new(reader).map_err(|e| decode_error("Failed to decode PNG", e))?;
+
+            // Check if it's actually animated
+            if !decoder.is_apng().unwrap_or(false) {
+                let image = crate::decode::from_decoder(decoder)
+                    .map_err(|e| decode_error("Failed to decode image", e))?;
+                return Ok(ImageFrames::Still(image));
+            }
+
+            let decoder = decoder
+                .apng()
+                .map_err(|e| decode_error("Failed to create APNG decoder", e))?;
+
+            Ok(animated(decoder.into_frames()))
+        }
+        AnimatedFormat::WebP => {
+            let reader = BufReader::new(File::open(path)?);
+            let decoder =
+                WebPDecoder::new(reader).map_err(|e| decode_error("Failed to decode WebP", e))?;
+
+            // Check if it has animation
+            if !decoder.has_animation() {
+                let image = crate::decode::from_decoder(decoder)
+                    .map_err(|e| decode_error("Failed to decode image", e))?;
+                return Ok(ImageFrames::Still(image));
+            }
+
+            Ok(animated(decoder.into_frames()))
+        }
+        AnimatedFormat::Jxl => {
+            let frames = JxlFrames::open(path)?;
+            let loop_count = frames.loop_count;
+
+            Ok(ImageFrames::Animated {
+                frames: Box::new(frames),
+                loop_count,
+            })
+        }
+        AnimatedFormat::Avif => {
+            // Stills have no track for GStreamer to play.
+            #[cfg(feature = "heif")]
+            if !is_avif_sequence(&read_header(path)?) {
+                return Ok(ImageFrames::Still(crate::heif::decode(path)?));
+            }
+
+            let frames = crate::video::ClipFrames::open(path)?.map(|frame| {
+                frame.map(|(image, delay)| AnimatedFrame {
+                    image,
+                    delay: delay.max(Duration::from_millis(MIN_FRAME_DELAY_MS)),
+                })
+            });
+
+            Ok(ImageFrames::Animated {
+                frames: Box::new(frames),
+                loop_count: None,
+            })
+        }
+    }
+}
+
+/// Convert a decoded GIF, APNG or WebP frame.
+fn animated_frame(
+    frame: image::ImageResult<image::Frame>,
+    format: AnimatedFormat,
+) -> Result<AnimatedFrame, SourceError> {
+    let frame =
+        frame.map_err(|e| decode_error(&format!("Failed to decode {:?} frame", format), e))?;
+
+    Ok(AnimatedFrame {
+        delay: calculate_frame_delay(frame.delay()),
+        image: DynamicImage::ImageRgba8(frame.into_buffer()),
+    })
+}
+
+/// Keyframes of a JPEG XL file, rendered one at a time and converted to sRGB
+struct JxlFrames {
+    image: jxl_oxide::JxlImage,
+    /// Ticks per second as a numerator and denominator, for animations
+    ticks_per_second: Option<(u32, u32)>,
+    loop_count: Option<u32>,
+    conversion: Option<crate::icc::Conversion>,
+    next: usize,
+}
+
+impl JxlFrames {
+    fn open(path: &Path) -> Result<Self, SourceError> {
+        let image = jxl_oxide::JxlImage::builder()
+            .open(path)
+            .map_err(|e| decode_error("Failed to decode JPEG XL", e))?;
+
+        let animation = image.image_header().metadata.animation.as_ref();
+        let ticks_per_second =
+            animation.map(|animation| (animation.tps_numerator, animation.tps_denominator));
+        let loop_count = animation.map(|animation| animation.num_loops);
+
+        let conversion = crate::icc::Conversion::to_srgb(&image.rendered_icc())
+            .inspect_err(|err| tracing::debug!(%err, "ignoring JPEG XL color profile"))
+            .ok();
+
+        Ok(Self {
+            image,
+            ticks_per_second,
+            loop_count,
+            conversion,
+            next: 0,
+        })
+    }
+
+    /// Render one keyframe
+    fn render(&self, keyframe: usize) -> Result<AnimatedFrame, SourceError> {
+        let render = self
+            .image
+            .render_frame(keyframe)
+            .map_err(|e| decode_error("Failed to decode JPEG XL frame", e))?;
+
+        let mut stream = render.stream();
+        let (width, height) = (stream.width(), stream.height());
+        let channels = stream.channels();
+        let mut samples = vec![0u8; width as usize * height as usize * channels as usize];
+        stream.write_to_buffer(&mut samples);
+
+        let frame = match channels {
+            1 => image::GrayImage::from_raw(width, height, samples).map(DynamicImage::from),
+            2 => image::GrayAlphaImage::from_raw(width, height, samples).map(DynamicImage::from),
+            3 => image::RgbImage::from_raw(width, height, samples).map(DynamicImage::from),
+            4 => image::RgbaImage::from_raw(width, height, samples).map(DynamicImage::from),
+            _ => None,
+        }
+        .ok_or_else(|| decode_error("Unsupported JPEG XL channel count", channels))?;
+
+        let frame = match &self.conversion {
+            Some(conversion) => conversion.convert(&frame).unwrap_or(frame),
+            None => frame,
+        };
+
+        let delay = match self.ticks_per_second {
+            Some((numerator, denominator)) => tick_delay(render.duration(), numerator, denominator),
+            None => Duration::MAX,
+        };
+
+        Ok(AnimatedFrame {
+            image: DynamicImage::ImageRgba8(frame.into_rgba8()),
+            delay,
+        })
+    }
+}
+
+impl Iterator for JxlFrames {
+    type Item = Result<AnimatedFrame, SourceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let keyframe = self.next;
+        if keyframe >= self.image.num_loaded_keyframes() {
+            return None;
+        }
+
+        self.next += 1;
+        Some(self.render(keyframe))
+    }
+}
+
+/// Frames of an animated image decoded on a background thread, a few ahead
+/// of the one shown, starting over at the end of the file.
+///
+/// Each frame comes with its index, so the source can count loops.
+#[derive(Debug)]
+struct FrameStream {
+    // Only ever used through `get_mut`; the mutex makes the source `Sync`.
+    receiver: Mutex<Receiver<(usize, AnimatedFrame)>>,
+}
+
+impl FrameStream {
+    fn spawn(path: PathBuf, format: AnimatedFormat) -> Result<Self, SourceError> {
+        let (sender, receiver) = mpsc::sync_channel(STREAM_AHEAD_FRAMES);
+
+        std::thread::Builder::new()
+            .name("animated-decoder".to_string())
+            .spawn(move || {
+                loop {
+                    let frames = match open_frames(&path, format) {
+                        Ok(ImageFrames::Animated { frames, .. }) => frames,
+                        Ok(ImageFrames::Still(_)) => return,
+                        Err(why) => {
+                            tracing::error!(?why, ?path, "failed to reopen animated image");
+                            return;
+                        }
+                    };
+
+                    let mut decoded = 0;
+                    for (index, frame) in frames.take(MAX_ANIMATED_FRAMES).enumerate() {
+                        let frame = match frame {
+                            Ok(frame) => frame,
+                            Err(why) => {
+                                tracing::error!(?why, ?path, "failed to decode animated image");
+                                return;
+                            }
+                        };
+
+                        // Sending fails once the source is released.
+                        if sender.send((index, frame)).is_err() {
+                            return;
+                        }
+                        decoded += 1;
+                    }
+
+                    if decoded == 0 {
+                        return;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            receiver: Mutex::new(receiver),
+        })
+    }
+}
+
 /// Supported animated image formats
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum AnimatedFormat {
     Gif,
     Apng,
     WebP,
+    Jxl,
+    Avif,
+}
+
+impl AnimatedFormat {
+    /// The format of a file starting with `header`, from its signature
+    fn sniff(header: &[u8]) -> Option<Self> {
+        const JXL_CONTAINER: &[u8] = b"\0\0\0\x0cJXL \r\n\x87\n";
+
+        if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Apng)
+        } else if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP") {
+            Some(Self::WebP)
+        } else if header.starts_with(&[0xff, 0x0a]) || header.starts_with(JXL_CONTAINER) {
+            Some(Self::Jxl)
+        } else if ftyp_brands(header).any(|brand| brand == b"avif" || brand == b"avis") {
+            Some(Self::Avif)
+        } else {
+            None
+        }
+    }
+}
+
+/// Read the first bytes of a file, enough to tell its format.
+pub fn read_header(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(256);
+    File::open(path)?.take(256).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// The major and compatible brands of the `ftyp` box an ISO base media
+/// file, such as an AVIF image, starts with.
+pub fn ftyp_brands(header: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let brands = match (header.get(..4), header.get(4..8)) {
+        (Some(size), Some(b"ftyp")) => {
+            let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+            header.get(8..size.min(header.len())).unwrap_or_default()
+        }
+        _ => &[],
+    };
+
+    // The major brand is followed by a minor version.
+    brands
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|&(index, _)| index != 1)
+        .map(|(_, brand)| brand)
+}
+
+/// Whether an AVIF file is an image sequence rather than a still.
+fn is_avif_sequence(header: &[u8]) -> bool {
+    ftyp_brands(header).any(|brand| brand == b"avis")
 }
 
 /// Check if a file is an animated image
 ///
-/// Utility function for detecting animated formats. Currently unused but provides
-/// format detection capability for future animation support in wallpaper loading.
-#[allow(dead_code)]
-pub fn is_animated_image(path: &PathBuf) -> bool {
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase());
-
-    matches!(ext.as_deref(), Some("gif" | "apng" | "webp"))
+/// The format is told from the file contents, which are also checked for
+/// more than one frame, so stills in formats that can animate don't count.
+pub fn is_animated_image(path: &Path) -> bool {
+    read_header(path).is_ok_and(|header| is_animated(path, &header))
+}
+
+/// Whether the file at `path`, starting with `header`, is an animated image.
+///
+/// Nothing is decoded: GIF files are only read as far as their second frame,
+/// and JPEG XL files as far as their image header.
+pub fn is_animated(path: &Path, header: &[u8]) -> bool {
+    let reader = || File::open(path).map(BufReader::new);
+
+    match AnimatedFormat::sniff(header) {
+        Some(AnimatedFormat::Gif) => {
+            reader().is_ok_and(|mut reader| gif_has_frames(&mut reader).unwrap_or(false))
+        }
+        Some(AnimatedFormat::Apng) => reader()
+            .ok()
+            .and_then(|reader| image::codecs::png::PngDecoder::new(reader).ok())
+            .is_some_and(|decoder| decoder.is_apng().unwrap_or(false)),
+        Some(AnimatedFormat::WebP) => reader()
+            .ok()
+            .and_then(|reader| image::codecs::webp::WebPDecoder::new(reader).ok())
+            .is_some_and(|decoder| decoder.has_animation()),
+        Some(AnimatedFormat::Jxl) => jxl_has_animation(path),
+        Some(AnimatedFormat::Avif) => is_avif_sequence(header),
+        None => false,
+    }
+}
+
+/// Whether a GIF has more than one frame, skipping over the data of the
+/// first rather than decoding it.
+fn gif_has_frames(reader: &mut BufReader<File>) -> std::io::Result<bool> {
+    fn byte(reader: &mut BufReader<File>) -> std::io::Result<u8> {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn skip_color_table(reader: &mut BufReader<File>, flags: u8) -> std::io::Result<()> {
+        if flags & 0x80 != 0 {
+            reader.seek_relative(3 << ((flags & 0x07) + 1))?;
+        }
+        Ok(())
+    }
+
+    fn skip_sub_blocks(reader: &mut BufReader<File>) -> std::io::Result<()> {
+        loop {
+            match byte(reader)? {
+                0 => return Ok(()),
+                size => reader.seek_relative(i64::from(size))?,
+            }
+        }
+    }
+
+    // The signature and logical screen descriptor
+    let mut screen = [0; 13];
+    reader.read_exact(&mut screen)?;
+    skip_color_table(reader, screen[10])?;
+
+    let mut frames = 0;
+    loop {
+        match byte(reader)? {
+            // Extension: a label, then its data
+            0x21 => {
+                byte(reader)?;
+                skip_sub_blocks(reader)?;
+            }
+            // Image descriptor
+            0x2c => {
+                frames += 1;
+                if frames > 1 {
+                    return Ok(true);
+                }
+
+                let mut descriptor = [0; 9];
+                reader.read_exact(&mut descriptor)?;
+                skip_color_table(reader, descriptor[8])?;
+                // LZW minimum code size, then the image data
+                byte(reader)?;
+                skip_sub_blocks(reader)?;
+            }
+            // Trailer
+            _ => return Ok(false),
+        }
+    }
+}
+
+/// Whether a JPEG XL file is animated, read from its image header alone.
+fn jxl_has_animation(path: &Path) -> bool {
+    use jxl_oxide::{InitializeResult, JxlImage};
+
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+
+    let mut image = JxlImage::builder().build_uninit();
+    let mut buffer = vec![0; 4096];
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) | Err(_) => return false,
+            Ok(read) => read,
+        };
+        if image.feed_bytes(&buffer[..read]).is_err() {
+            return false;
+        }
+
+        match image.try_init() {
+            Ok(InitializeResult::Initialized(image)) => {
+                return image.image_header().metadata.animation.is_some();
+            }
+            Ok(InitializeResult::NeedMoreData(uninit)) => image = uninit,
+            Err(_) => return false,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
 
     #[test]
     fn test_animated_config() {
@@ -404,6 +858,7 @@ mod tests {
             path: PathBuf::from("/tmp/test.gif"),
             fps_limit: Some(30),
             loop_count: None,
+            ..Default::default()
         };
 
         let source = AnimatedSource::new(config);
@@ -427,14 +882,128 @@ mod tests {
             AnimatedSource::detect_format(&PathBuf::from("test.webp")).unwrap(),
             AnimatedFormat::WebP
         );
+        assert_eq!(
+            AnimatedSource::detect_format(&PathBuf::from("test.jxl")).unwrap(),
+            AnimatedFormat::Jxl
+        );
+        assert_eq!(
+            AnimatedSource::detect_format(&PathBuf::from("test.avif")).unwrap(),
+            AnimatedFormat::Avif
+        );
+    }
+
+    #[test]
+    fn test_format_sniffing() {
+        assert_eq!(AnimatedFormat::sniff(b"GIF89a\x08\0"), Some(AnimatedFormat::Gif));
+        assert_eq!(
+            AnimatedFormat::sniff(b"\x89PNG\r\n\x1a\n\0\0"),
+            Some(AnimatedFormat::Apng)
+        );
+        assert_eq!(
+            AnimatedFormat::sniff(b"RIFF\x10\0\0\0WEBPVP8X"),
+            Some(AnimatedFormat::WebP)
+        );
+        assert_eq!(AnimatedFormat::sniff(&[0xff, 0x0a, 0xfa]), Some(AnimatedFormat::Jxl));
+        assert_eq!(
+            AnimatedFormat::sniff(b"\0\0\0\x0cJXL \r\n\x87\n\0\0"),
+            Some(AnimatedFormat::Jxl)
+        );
+        assert_eq!(AnimatedFormat::sniff(b"\xff\xd8\xff\xe0"), None);
+
+        // Brands are read from the ftyp box only, skipping the minor version.
+        let sequence = b"\0\0\0\x1cftypavis\0\0\0\0avifmsf1miafmdat";
+        assert_eq!(AnimatedFormat::sniff(sequence), Some(AnimatedFormat::Avif));
+        assert!(is_avif_sequence(sequence));
+
+        let still = b"\0\0\0\x18ftypavif\0\0\0\0mif1miafavis";
+        assert_eq!(AnimatedFormat::sniff(still), Some(AnimatedFormat::Avif));
+        assert!(!is_avif_sequence(still));
+
+        let heic = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic";
+        assert_eq!(AnimatedFormat::sniff(heic), None);
     }
 
     #[test]
     fn test_is_animated_image() {
-        assert!(is_animated_image(&PathBuf::from("test.gif")));
-        assert!(is_animated_image(&PathBuf::from("test.webp")));
-        assert!(!is_animated_image(&PathBuf::from("test.jpg")));
-        assert!(!is_animated_image(&PathBuf::from("test.png")));
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+        assert!(is_animated_image(&fixtures.join("animated.gif")));
+        assert!(!is_animated_image(&fixtures.join("still.gif")));
+        assert!(!is_animated_image(&fixtures.join("orientation-1.jpg")));
+        assert!(!is_animated_image(&PathBuf::from("missing.gif")));
+    }
+
+    /// Advance `source`, waiting a while for its background decoder.
+    fn advance(source: &mut AnimatedSource) -> bool {
+        for _ in 0..500 {
+            if source.advance_frame() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        false
+    }
+
+    #[test]
+    fn test_streams_over_budget() {
+        // Two 8x8 frames, black then white.
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/animated.gif");
+        let shade =
+            |source: &AnimatedSource| source.frames[0].image.to_rgba8().get_pixel(0, 0).0[0];
+
+        let mut cached = AnimatedSource::new(AnimatedConfig {
+            path: path.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        cached.prepare(8, 8).unwrap();
+        assert!(cached.stream.is_none());
+        assert_eq!(cached.frames.len(), 2);
+
+        let mut streamed = AnimatedSource::new(AnimatedConfig {
+            path: path.clone(),
+            memory_budget_mb: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        streamed.prepare(8, 8).unwrap();
+        assert!(streamed.stream.is_some());
+        assert!(streamed.is_animated());
+        assert_eq!(streamed.frames.len(), 1);
+        assert_eq!(shade(&streamed), 0);
+        assert!(!streamed.played_through());
+
+        // Frames arrive in order, starting over at the end of the file.
+        for expected in [255, 0, 255] {
+            assert!(advance(&mut streamed));
+            assert_eq!(shade(&streamed), expected);
+        }
+        assert_eq!(streamed.loops_completed, 1);
+        assert!(streamed.played_through());
+
+        // The last frame stays up after the last loop.
+        let mut once = AnimatedSource::new(AnimatedConfig {
+            path,
+            loop_count: Some(1),
+            memory_budget_mb: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        once.prepare(8, 8).unwrap();
+        assert!(advance(&mut once));
+        assert!(!advance(&mut once));
+        assert_eq!(shade(&once), 255);
+
+        streamed.release();
+        assert!(streamed.stream.is_none());
+    }
+
+    #[test]
+    fn test_jxl_tick_delay() {
+        assert_eq!(tick_delay(1, 100, 1), Duration::from_millis(10));
+        assert_eq!(tick_delay(3, 30_000, 1001), Duration::from_secs_f64(0.1001));
+        // Zero length frames are clamped like GIF delays.
+        assert_eq!(tick_delay(0, 1000, 1), Duration::from_millis(MIN_FRAME_DELAY_MS));
     }
 
     #[test]
@@ -443,6 +1012,7 @@ mod tests {
             path: PathBuf::from("/tmp/test.gif"),
             fps_limit: Some(30),
             loop_count: None,
+            ..Default::default()
         };
 
         let source = AnimatedSource::new(config).unwrap();
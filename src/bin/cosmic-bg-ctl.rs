@@ -5,14 +5,18 @@
 //! This tool allows setting wallpapers from the command line, including
 //! static images, videos, animated images, and GPU shaders.
 
-use std::io;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
 use cosmic_ext_bg_config::{
-    AnimatedConfig, Color, Context, Entry, Gradient, ScalingMode, ShaderConfig, ShaderPreset,
-    Source, VideoConfig,
+    Adjustments, AnimatedConfig, Color, Context, Entry, FilterMethod, Gradient, KenBurns,
+    ScalingMode, Schedule, ScheduleSlot, ShaderConfig, ShaderPreset, SolarConfig, Source,
+    VideoConfig,
+    ipc::{self, Command, Request, Response},
 };
 
 /// CLI tool for managing cosmic-ext-bg wallpapers
@@ -33,9 +37,13 @@ enum Commands {
         /// Target output (e.g., DP-1, HDMI-A-1). Defaults to "all"
         #[arg(short, long)]
         output: Option<String>,
-        /// Scaling mode: zoom, fit, stretch
+        /// Scaling mode: zoom, fit, fit-blur, stretch, center, tile[:FACTOR], ken-burns
         #[arg(short, long, default_value = "zoom")]
         scaling: String,
+        /// Resampling filter or preset: lanczos (quality), catmull-rom, mitchell,
+        /// linear (fast), box, supersampling, nearest (pixel-art)
+        #[arg(short, long, default_value = "lanczos")]
+        filter: String,
         /// Rotation frequency in seconds (for directories)
         #[arg(short, long)]
         rotation: Option<u64>,
@@ -59,7 +67,7 @@ enum Commands {
         no_hw_accel: bool,
     },
 
-    /// Set an animated image wallpaper (GIF, WebP, APNG)
+    /// Set an animated image wallpaper (GIF, WebP, APNG, JPEG XL, AVIF)
     Animated {
         /// Path to animated image file
         path: PathBuf,
@@ -72,6 +80,9 @@ enum Commands {
         /// Loop count (default: infinite)
         #[arg(long)]
         loops: Option<u32>,
+        /// Memory in MiB for decoded frames before they are decoded on demand
+        #[arg(long, default_value = "256")]
+        memory_budget: u32,
     },
 
     /// Set a GPU shader wallpaper
@@ -101,6 +112,44 @@ enum Commands {
         output: Option<String>,
     },
 
+    /// Set wallpapers that change with the time of day
+    Schedule {
+        /// Slots as START=SOURCE, where START is a 24-hour HH:MM time and
+        /// SOURCE an image, a directory or a #rrggbb color
+        /// (e.g., 07:00=~/day.jpg 19:30=~/Pictures/night)
+        #[arg(required = true)]
+        slots: Vec<String>,
+        /// Target output (e.g., DP-1, HDMI-A-1). Defaults to "all"
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Rotation frequency in seconds (for directories)
+        #[arg(short, long)]
+        rotation: Option<u64>,
+    },
+
+    /// Set day and night wallpapers that follow sunrise and sunset
+    Solar {
+        /// Image, directory or #rrggbb color shown while the sun is up
+        day: String,
+        /// Image, directory or #rrggbb color shown while the sun is down
+        night: String,
+        /// Latitude in degrees, negative for south
+        #[arg(long, allow_hyphen_values = true)]
+        lat: f64,
+        /// Longitude in degrees, negative for west
+        #[arg(long, allow_hyphen_values = true)]
+        lon: f64,
+        /// Crossfade between day and night over civil twilight
+        #[arg(long)]
+        blend: bool,
+        /// Target output (e.g., DP-1, HDMI-A-1). Defaults to "all"
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Dim, blur or tint the wallpaper of an output
+    Adjust(AdjustArgs),
+
     /// Query current wallpaper configuration
     Query {
         /// Target output (e.g., DP-1). If not specified, shows all
@@ -111,6 +160,40 @@ enum Commands {
     /// List available display outputs
     Outputs,
 
+    /// Show the next slideshow image (running daemon)
+    Next {
+        /// Target output (e.g., DP-1). Defaults to all outputs
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Show the previous slideshow image (running daemon)
+    Prev {
+        /// Target output (e.g., DP-1). Defaults to all outputs
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Pause slideshow rotation and animation (running daemon)
+    Pause {
+        /// Target output (e.g., DP-1). Defaults to all outputs
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Resume slideshow rotation and animation (running daemon)
+    Resume {
+        /// Target output (e.g., DP-1). Defaults to all outputs
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Make the running daemon re-read its configuration
+    Reload,
+
+    /// Show what the running daemon is displaying
+    Status,
+
     /// Backup current configuration
     Backup {
         /// Output file path
@@ -132,6 +215,37 @@ enum Commands {
     },
 }
 
+#[derive(Args)]
+struct AdjustArgs {
+    /// Brightness multiplier (0.0-2.0, 1.0 leaves it unchanged)
+    #[arg(long)]
+    brightness: Option<f32>,
+    /// Contrast multiplier (0.0-4.0, 1.0 leaves it unchanged)
+    #[arg(long)]
+    contrast: Option<f32>,
+    /// Saturation multiplier (0.0-4.0, 0.0 is grayscale)
+    #[arg(long)]
+    saturation: Option<f32>,
+    /// Gaussian blur radius in pixels (0 for none)
+    #[arg(long)]
+    blur: Option<f32>,
+    /// Tint color in hex format (e.g., #000000), or "none"
+    #[arg(long)]
+    tint: Option<String>,
+    /// Tint opacity (0.0-1.0)
+    #[arg(long)]
+    tint_opacity: Option<f32>,
+    /// Darken towards the corners (0.0-1.0)
+    #[arg(long)]
+    vignette: Option<f32>,
+    /// Remove all adjustments
+    #[arg(long)]
+    reset: bool,
+    /// Target output (e.g., DP-1, HDMI-A-1). Defaults to "all"
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
 fn main() {
     // Initialize tracing with simple format
     tracing_subscriber::fmt()
@@ -154,8 +268,9 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             path,
             output,
             scaling,
+            filter,
             rotation,
-        } => cmd_set(&context, path, output, &scaling, rotation),
+        } => cmd_set(&context, path, output, &scaling, &filter, rotation),
         Commands::Video {
             path,
             output,
@@ -168,7 +283,8 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             output,
             fps,
             loops,
-        } => cmd_animated(&context, path, output, fps, loops),
+            memory_budget,
+        } => cmd_animated(&context, path, output, fps, loops, memory_budget),
         Commands::Shader {
             preset_or_path,
             output,
@@ -180,8 +296,28 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             radius,
             output,
         } => cmd_color(&context, color, gradient_colors, radius, output),
+        Commands::Schedule {
+            slots,
+            output,
+            rotation,
+        } => cmd_schedule(&context, &slots, output, rotation),
+        Commands::Solar {
+            day,
+            night,
+            lat,
+            lon,
+            blend,
+            output,
+        } => cmd_solar(&context, &day, &night, lat, lon, blend, output),
+        Commands::Adjust(args) => cmd_adjust(&context, args),
         Commands::Query { output } => cmd_query(&context, output),
         Commands::Outputs => cmd_outputs(&context),
+        Commands::Next { output } => cmd_control(Command::Next { output }),
+        Commands::Prev { output } => cmd_control(Command::Previous { output }),
+        Commands::Pause { output } => cmd_control(Command::Pause { output }),
+        Commands::Resume { output } => cmd_control(Command::Resume { output }),
+        Commands::Reload => cmd_control(Command::Reload),
+        Commands::Status => cmd_control(Command::Status),
         Commands::Backup { file } => cmd_backup(&context, file),
         Commands::Restore { file } => cmd_restore(&context, file),
         Commands::Completions { shell } => cmd_completions(&shell),
@@ -189,11 +325,50 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn parse_scaling_mode(scaling: &str) -> Result<ScalingMode, Box<dyn std::error::Error>> {
-    match scaling.to_lowercase().as_str() {
+    let scaling = scaling.to_lowercase();
+
+    // Tiles take an optional scale factor, as in `tile:0.5`.
+    if let Some(factor) = scaling.strip_prefix("tile:") {
+        let factor: f32 = factor
+            .parse()
+            .map_err(|_| format!("Invalid tile scale factor: {factor}"))?;
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(format!("Tile scale factor must be positive: {factor}").into());
+        }
+        return Ok(ScalingMode::Tile(factor));
+    }
+
+    match scaling.as_str() {
         "zoom" => Ok(ScalingMode::Zoom),
         "stretch" => Ok(ScalingMode::Stretch),
         "fit" => Ok(ScalingMode::Fit([0.0, 0.0, 0.0])), // Black background
-        _ => Err(format!("Unknown scaling mode: {scaling}. Use: zoom, fit, stretch").into()),
+        "fit-blur" | "fitblur" => Ok(ScalingMode::FitBlur),
+        "center" => Ok(ScalingMode::Center([0.0, 0.0, 0.0])), // Black background
+        "tile" => Ok(ScalingMode::Tile(1.0)),
+        "ken-burns" | "kenburns" => Ok(ScalingMode::KenBurns(KenBurns::default())),
+        _ => Err(format!(
+            "Unknown scaling mode: {scaling}. \
+             Use: zoom, fit, fit-blur, stretch, center, tile[:FACTOR], ken-burns"
+        )
+        .into()),
+    }
+}
+
+fn parse_filter_method(filter: &str) -> Result<FilterMethod, Box<dyn std::error::Error>> {
+    match filter.to_lowercase().as_str() {
+        "lanczos" | "quality" => Ok(FilterMethod::Lanczos),
+        "catmull-rom" | "catmullrom" => Ok(FilterMethod::CatmullRom),
+        "mitchell" => Ok(FilterMethod::Mitchell),
+        "linear" | "fast" => Ok(FilterMethod::Linear),
+        "box" => Ok(FilterMethod::Box),
+        "supersampling" => Ok(FilterMethod::SuperSampling),
+        "nearest" | "pixel-art" => Ok(FilterMethod::Nearest),
+        _ => Err(format!(
+            "Unknown filter: {filter}. \
+             Use: lanczos, catmull-rom, mitchell, linear, box, supersampling, nearest, \
+             or a preset: quality, fast, pixel-art"
+        )
+        .into()),
     }
 }
 
@@ -215,6 +390,7 @@ fn cmd_set(
     path: PathBuf,
     output: Option<String>,
     scaling: &str,
+    filter: &str,
     rotation: Option<u64>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let path = path.canonicalize().map_err(|e| format!("Invalid path: {e}"))?;
@@ -225,9 +401,11 @@ fn cmd_set(
 
     let output_name = output.unwrap_or_else(|| "all".to_string());
     let scaling_mode = parse_scaling_mode(scaling)?;
+    let filter_method = parse_filter_method(filter)?;
 
     let mut entry = Entry::new(output_name.clone(), Source::Path(path.clone()));
     entry.scaling_mode = scaling_mode;
+    entry.filter_method = filter_method;
     if let Some(freq) = rotation {
         entry.rotation_frequency = freq;
     }
@@ -286,6 +464,7 @@ fn cmd_animated(
     output: Option<String>,
     fps: Option<u32>,
     loops: Option<u32>,
+    memory_budget: u32,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let path = path.canonicalize().map_err(|e| format!("Invalid path: {e}"))?;
 
@@ -299,6 +478,7 @@ fn cmd_animated(
         path: path.clone(),
         fps_limit: fps,
         loop_count: loops,
+        memory_budget_mb: memory_budget,
     };
 
     let entry = Entry::new(output_name.clone(), Source::Animated(animated_config));
@@ -316,6 +496,7 @@ fn cmd_animated(
     if let Some(l) = loops {
         println!("  Loop count: {l}");
     }
+    println!("  Memory budget: {memory_budget} MiB");
     Ok(())
 }
 
@@ -506,6 +687,7 @@ fn print_entry(entry: &Entry) {
             if let Some(loops) = a.loop_count {
                 println!("  Loop count: {loops}");
             }
+            println!("  Memory budget: {} MiB", a.memory_budget_mb);
         }
         Source::Shader(s) => {
             println!("  Type: GPU Shader");
@@ -517,9 +699,230 @@ fn print_entry(entry: &Entry) {
             }
             println!("  FPS limit: {}", s.fps_limit);
         }
+        Source::Schedule(schedule) => {
+            println!("  Type: Schedule");
+            for slot in &schedule.slots {
+                println!("    {}  {}", slot.start, source_summary(&slot.source));
+            }
+        }
+        Source::Solar(solar) => {
+            println!("  Type: Solar");
+            println!("  Location: {}, {}", solar.latitude, solar.longitude);
+            println!("  Day: {}", source_summary(&solar.day));
+            println!("  Night: {}", source_summary(&solar.night));
+            println!("  Twilight blend: {}", solar.twilight_blend);
+        }
     }
     println!("  Scaling: {:?}", entry.scaling_mode);
+    println!("  Filter: {:?}", entry.filter_method);
     println!("  Rotation frequency: {}s", entry.rotation_frequency);
+    if let Some(adjustments) = &entry.adjustments {
+        println!("  Adjustments: {}", adjustments_summary(adjustments));
+    }
+}
+
+/// An image or directory path, or a #rrggbb color.
+fn parse_source_arg(source: &str) -> Result<Source, Box<dyn std::error::Error>> {
+    if source.starts_with('#') {
+        return Ok(Source::Color(Color::Single(parse_hex_color(source)?)));
+    }
+
+    let path = PathBuf::from(source)
+        .canonicalize()
+        .map_err(|e| format!("Invalid path {source}: {e}"))?;
+    Ok(Source::Path(path))
+}
+
+fn parse_schedule_slot(slot: &str) -> Result<ScheduleSlot, Box<dyn std::error::Error>> {
+    let (start, source) = slot
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid schedule slot: {slot}. Use: HH:MM=SOURCE"))?;
+
+    Ok(ScheduleSlot {
+        start: start.parse()?,
+        source: parse_source_arg(source)?,
+    })
+}
+
+fn cmd_schedule(
+    context: &Context,
+    slots: &[String],
+    output: Option<String>,
+    rotation: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut slots = slots
+        .iter()
+        .map(|slot| parse_schedule_slot(slot))
+        .collect::<Result<Vec<_>, _>>()?;
+    slots.sort_by_key(|slot| slot.start);
+
+    let output_name = output.unwrap_or_else(|| "all".to_string());
+    let schedule = Schedule { slots };
+
+    let mut entry = Entry::new(output_name.clone(), Source::Schedule(schedule.clone()));
+    if let Some(freq) = rotation {
+        entry.rotation_frequency = freq;
+    }
+
+    let mut config = cosmic_ext_bg_config::Config::load(context)?;
+    config.set_entry(context, entry)?;
+
+    println!("Set wallpaper schedule for '{output_name}':");
+    for slot in &schedule.slots {
+        println!("  {}  {}", slot.start, source_summary(&slot.source));
+    }
+    Ok(())
+}
+
+fn cmd_solar(
+    context: &Context,
+    day: &str,
+    night: &str,
+    latitude: f64,
+    longitude: f64,
+    twilight_blend: bool,
+    output: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let solar = SolarConfig {
+        latitude,
+        longitude,
+        day: Box::new(parse_source_arg(day)?),
+        night: Box::new(parse_source_arg(night)?),
+        twilight_blend,
+    };
+
+    if !solar.is_valid() {
+        return Err("Latitude must be within -90..90 and longitude within -180..180".into());
+    }
+
+    let output_name = output.unwrap_or_else(|| "all".to_string());
+    let entry = Entry::new(output_name.clone(), Source::Solar(solar.clone()));
+
+    let mut config = cosmic_ext_bg_config::Config::load(context)?;
+    config.set_entry(context, entry)?;
+
+    println!("Set solar wallpaper for '{output_name}' at {latitude}, {longitude}:");
+    println!("  Day: {}", source_summary(&solar.day));
+    println!("  Night: {}", source_summary(&solar.night));
+    Ok(())
+}
+
+fn cmd_adjust(context: &Context, args: AdjustArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let output_name = args.output.unwrap_or_else(|| "all".to_string());
+
+    let mut config = cosmic_ext_bg_config::Config::load(context)?;
+    let mut entry = match config.entry(&output_name) {
+        Some(entry) if output_name != "all" => entry.clone(),
+        _ => {
+            let mut entry = config.default_background.clone();
+            entry.output = output_name.clone();
+            entry
+        }
+    };
+
+    let mut adjustments = if args.reset {
+        Adjustments::default()
+    } else {
+        entry.adjustments.unwrap_or_default()
+    };
+
+    if let Some(brightness) = args.brightness {
+        adjustments.brightness = brightness;
+    }
+    if let Some(contrast) = args.contrast {
+        adjustments.contrast = contrast;
+    }
+    if let Some(saturation) = args.saturation {
+        adjustments.saturation = saturation;
+    }
+    if let Some(blur) = args.blur {
+        adjustments.blur = blur;
+    }
+    if let Some(tint) = args.tint {
+        adjustments.tint = match tint.to_lowercase().as_str() {
+            "none" => None,
+            _ => Some(parse_hex_color(&tint)?),
+        };
+    }
+    if let Some(opacity) = args.tint_opacity {
+        adjustments.tint_opacity = opacity;
+    }
+    if let Some(vignette) = args.vignette {
+        adjustments.vignette = vignette;
+    }
+
+    entry.adjustments = (!adjustments.is_identity()).then_some(adjustments);
+    let summary = entry
+        .adjustments
+        .as_ref()
+        .map_or_else(|| "none".to_string(), adjustments_summary);
+    config.set_entry(context, entry)?;
+
+    println!("Set adjustments for '{output_name}': {summary}");
+    Ok(())
+}
+
+/// One-line description of the adjustments that change the image.
+fn adjustments_summary(adjustments: &Adjustments) -> String {
+    let mut parts = Vec::new();
+    let mut factor = |name: &str, value: f32, unchanged: f32| {
+        if value != unchanged {
+            parts.push(format!("{name} {value}"));
+        }
+    };
+
+    factor("brightness", adjustments.clamped_brightness(), 1.0);
+    factor("contrast", adjustments.clamped_contrast(), 1.0);
+    factor("saturation", adjustments.clamped_saturation(), 1.0);
+    factor("blur", adjustments.clamped_blur(), 0.0);
+    factor("vignette", adjustments.clamped_vignette(), 0.0);
+
+    if let Some((tint, opacity)) = adjustments.clamped_tint() {
+        parts.push(format!(
+            "tint {} at {opacity}",
+            source_summary(&Source::Color(Color::Single(tint)))
+        ));
+    }
+
+    if parts.is_empty() {
+        "none".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// One-line description of a source.
+fn source_summary(source: &Source) -> String {
+    let hex = |c: &[f32; 3]| {
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            (c[0] * 255.0) as u8,
+            (c[1] * 255.0) as u8,
+            (c[2] * 255.0) as u8
+        )
+    };
+
+    match source {
+        Source::Path(path) => path.display().to_string(),
+        Source::Color(Color::Single(rgb)) => hex(rgb),
+        Source::Color(Color::Gradient(g)) => {
+            let colors: Vec<String> = g.colors.iter().map(hex).collect();
+            format!("gradient {}", colors.join(" "))
+        }
+        Source::Video(v) => format!("video {}", v.path.display()),
+        Source::Animated(a) => format!("animated {}", a.path.display()),
+        Source::Shader(s) => match (&s.preset, &s.custom_path) {
+            (Some(preset), _) => format!("shader {preset:?}"),
+            (None, Some(path)) => format!("shader {}", path.display()),
+            (None, None) => "shader".to_string(),
+        },
+        Source::Schedule(schedule) => format!("schedule of {} slots", schedule.slots.len()),
+        Source::Solar(solar) => format!(
+            "solar {} / {}",
+            source_summary(&solar.day),
+            source_summary(&solar.night)
+        ),
+    }
 }
 
 fn cmd_outputs(context: &Context) -> Result<(), Box<dyn std::error::Error>> {
@@ -536,6 +939,56 @@ fn cmd_outputs(context: &Context) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Send a command to the running daemon over its control socket.
+fn send_command(command: Command) -> Result<Response, Box<dyn std::error::Error>> {
+    let path = ipc::socket_path();
+    let stream = UnixStream::connect(&path).map_err(|e| {
+        format!(
+            "Cannot connect to {} ({e}). Is cosmic-ext-bg running?",
+            path.display()
+        )
+    })?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    (&stream).write_all(ipc::encode(&Request::new(command))?.as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    Ok(ipc::decode(&line)?)
+}
+
+fn cmd_control(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match send_command(command)? {
+        Response::Ok => Ok(()),
+        Response::Error(why) => Err(why.into()),
+        Response::Status(outputs) => {
+            if outputs.is_empty() {
+                println!("No outputs are showing a wallpaper");
+            }
+
+            for status in outputs {
+                println!("{}:", status.output);
+                println!("  Entry: {}", status.entry);
+                match &status.source {
+                    Some(Source::Path(path)) => println!("  Showing: {}", path.display()),
+                    Some(source) => println!("  Showing: {source:?}"),
+                    None => println!("  Showing: nothing"),
+                }
+                println!("  Paused: {}", status.paused);
+                if status.queue_len > 1 {
+                    println!("  Slideshow images: {}", status.queue_len);
+                }
+                if let Some(secs) = status.next_rotation_secs {
+                    println!("  Next rotation in: {secs}s");
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
 fn cmd_backup(
     context: &Context,
     file: Option<PathBuf>,
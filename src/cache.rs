@@ -6,22 +6,59 @@
 //! decoded images, allowing multiple wallpapers to share the same image data
 //! when using the same source file.
 //!
-//! # Integration Status
-//!
-//! This module is fully implemented and tested but not yet integrated into the
-//! main wallpaper loading path. Integration is planned for future config updates
-//! to enable memory-efficient caching across multiple outputs using the same images.
-
-#![allow(dead_code)]
+//! The loader thread fills it with decoded originals and with copies of the
+//! next slideshow image pre-scaled for each output, keyed by [`CacheKey`].
 
 use image::DynamicImage;
 use std::{
     collections::HashMap,
-    path::PathBuf,
+    fmt::Debug,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex, RwLock},
     time::Instant,
 };
 
+pub use cosmic_ext_bg_config::CacheConfig;
+
+/// Key of an image in the daemon's cache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Source file of the image
+    pub path: PathBuf,
+    /// For pre-scaled copies: pixel size and [`fingerprint`] of the settings
+    /// they were scaled with. `None` for the decoded original.
+    pub scaled: Option<(u32, u32, u64)>,
+}
+
+impl CacheKey {
+    /// Key of the decoded, unscaled image.
+    pub fn original(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            scaled: None,
+        }
+    }
+
+    /// Key of a copy scaled to `size` with the settings behind `fingerprint`.
+    pub fn scaled(path: &Path, (width, height): (u32, u32), fingerprint: u64) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            scaled: Some((width, height, fingerprint)),
+        }
+    }
+}
+
+/// Hash of the settings a pre-scaled image depends on.
+///
+/// Hashes the `Debug` representation since the config types hold `f32`s and
+/// cannot derive `Hash`.
+pub fn fingerprint(settings: &impl Debug) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    format!("{settings:?}").hash(&mut hasher);
+    hasher.finish()
+}
+
 /// A cached image entry with metadata
 #[derive(Clone)]
 struct CacheEntry {
@@ -50,28 +87,10 @@ impl CacheEntry {
     }
 }
 
-/// Configuration for the image cache
-#[derive(Debug, Clone)]
-pub struct CacheConfig {
-    /// Maximum number of images to cache
-    pub max_entries: usize,
-    /// Maximum total size in bytes (0 = unlimited)
-    pub max_size_bytes: usize,
-}
-
-impl Default for CacheConfig {
-    fn default() -> Self {
-        Self {
-            max_entries: 50,
-            max_size_bytes: 512 * 1024 * 1024, // 512 MB default
-        }
-    }
-}
-
 /// Thread-safe LRU image cache
-pub struct ImageCache {
-    entries: RwLock<HashMap<PathBuf, CacheEntry>>,
-    config: CacheConfig,
+pub struct ImageCache<K = PathBuf> {
+    entries: RwLock<HashMap<K, CacheEntry>>,
+    config: Mutex<CacheConfig>,
     stats: Mutex<CacheStats>,
 }
 
@@ -85,7 +104,7 @@ pub struct CacheStats {
     pub current_size_bytes: usize,
 }
 
-impl ImageCache {
+impl<K: Clone + Debug + Eq + Hash> ImageCache<K> {
     /// Create a new image cache with default configuration
     pub fn new() -> Self {
         Self::with_config(CacheConfig::default())
@@ -101,20 +120,35 @@ impl ImageCache {
 
         Self {
             entries: RwLock::new(HashMap::new()),
-            config,
+            config: Mutex::new(config),
             stats: Mutex::new(CacheStats::default()),
         }
     }
 
+    /// Change the cache limits, evicting entries that no longer fit
+    pub fn set_config(&self, config: CacheConfig) {
+        tracing::debug!(
+            max_entries = config.max_entries,
+            max_size_mb = config.max_size_bytes / (1024 * 1024),
+            "Image cache limits changed"
+        );
+
+        *self.config.lock().unwrap() = config;
+
+        let mut entries = self.entries.write().unwrap();
+        self.evict_if_needed(&mut entries, None);
+        self.update_stats(&entries);
+    }
+
     /// Update cache statistics based on current entries
-    fn update_stats(&self, entries: &HashMap<PathBuf, CacheEntry>) {
+    fn update_stats(&self, entries: &HashMap<K, CacheEntry>) {
         let mut stats = self.stats.lock().unwrap();
         stats.current_entries = entries.len();
         stats.current_size_bytes = entries.values().map(|e| e.size_bytes).sum();
     }
 
     /// Get an image from the cache, returning None if not cached
-    pub fn get(&self, path: &PathBuf) -> Option<Arc<DynamicImage>> {
+    pub fn get(&self, path: &K) -> Option<Arc<DynamicImage>> {
         // Try read lock first for better concurrency
         {
             let entries = self.entries.read().unwrap();
@@ -132,7 +166,7 @@ impl ImageCache {
     }
 
     /// Insert an image into the cache
-    pub fn insert(&self, path: PathBuf, image: DynamicImage) -> Arc<DynamicImage> {
+    pub fn insert(&self, path: K, image: DynamicImage) -> Arc<DynamicImage> {
         let entry = CacheEntry::new(image);
         let image_arc = Arc::clone(&entry.image);
         let entry_size = entry.size_bytes;
@@ -141,7 +175,7 @@ impl ImageCache {
             let mut entries = self.entries.write().unwrap();
 
             // Check if we need to evict entries
-            self.evict_if_needed(&mut entries, entry_size);
+            self.evict_if_needed(&mut entries, Some(entry_size));
 
             // Insert the new entry
             entries.insert(path.clone(), entry);
@@ -160,7 +194,7 @@ impl ImageCache {
     }
 
     /// Get an image from cache or insert it using the provided loader function
-    pub fn get_or_insert<F, E>(&self, path: &PathBuf, loader: F) -> Result<Arc<DynamicImage>, E>
+    pub fn get_or_insert<F, E>(&self, path: &K, loader: F) -> Result<Arc<DynamicImage>, E>
     where
         F: FnOnce() -> Result<DynamicImage, E>,
     {
@@ -175,7 +209,7 @@ impl ImageCache {
     }
 
     /// Remove an image from the cache
-    pub fn remove(&self, path: &PathBuf) -> Option<Arc<DynamicImage>> {
+    pub fn remove(&self, path: &K) -> Option<Arc<DynamicImage>> {
         let mut entries = self.entries.write().unwrap();
         let removed = entries.remove(path).map(|e| e.image);
 
@@ -203,26 +237,33 @@ impl ImageCache {
     }
 
     /// Check if the cache contains an image for the given path
-    pub fn contains(&self, path: &PathBuf) -> bool {
+    pub fn contains(&self, path: &K) -> bool {
         self.entries.read().unwrap().contains_key(path)
     }
 
     /// Get the number of cached images
+    #[allow(dead_code)]
     pub fn len(&self) -> usize {
         self.entries.read().unwrap().len()
     }
 
     /// Check if the cache is empty
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.entries.read().unwrap().is_empty()
     }
 
-    /// Evict entries if needed to make room for a new entry
-    fn evict_if_needed(&self, entries: &mut HashMap<PathBuf, CacheEntry>, new_entry_size: usize) {
+    /// Evict entries if needed to stay within limits, making room for an
+    /// `incoming` entry of the given size
+    fn evict_if_needed(&self, entries: &mut HashMap<K, CacheEntry>, incoming: Option<usize>) {
         let mut stats = self.stats.lock().unwrap();
+        let config = self.config.lock().unwrap().clone();
+
+        let reserved_entries = usize::from(incoming.is_some());
+        let new_entry_size = incoming.unwrap_or(0);
 
         // Check entry count limit
-        while entries.len() >= self.config.max_entries {
+        while entries.len() + reserved_entries > config.max_entries {
             if let Some(path) = self.find_lru_entry(entries) {
                 entries.remove(&path);
                 stats.evictions += 1;
@@ -232,9 +273,9 @@ impl ImageCache {
         }
 
         // Check size limit
-        if self.config.max_size_bytes > 0 {
+        if config.max_size_bytes > 0 {
             let current_size: usize = entries.values().map(|e| e.size_bytes).sum();
-            let mut size_to_free = (current_size + new_entry_size).saturating_sub(self.config.max_size_bytes);
+            let mut size_to_free = (current_size + new_entry_size).saturating_sub(config.max_size_bytes);
 
             while size_to_free > 0 {
                 if let Some(path) = self.find_lru_entry(entries) {
@@ -250,7 +291,7 @@ impl ImageCache {
     }
 
     /// Find the least recently used entry
-    fn find_lru_entry(&self, entries: &HashMap<PathBuf, CacheEntry>) -> Option<PathBuf> {
+    fn find_lru_entry(&self, entries: &HashMap<K, CacheEntry>) -> Option<K> {
         entries
             .iter()
             .min_by_key(|(_, entry)| entry.last_access)
@@ -258,13 +299,13 @@ impl ImageCache {
     }
 }
 
-impl Default for ImageCache {
+impl<K: Clone + Debug + Eq + Hash> Default for ImageCache<K> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl std::fmt::Debug for ImageCache {
+impl<K: Clone + Debug + Eq + Hash> std::fmt::Debug for ImageCache<K> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let stats = self.stats();
         f.debug_struct("ImageCache")
@@ -272,6 +313,7 @@ impl std::fmt::Debug for ImageCache {
             .field("size_mb", &(stats.current_size_bytes / (1024 * 1024)))
             .field("hits", &stats.hits)
             .field("misses", &stats.misses)
+            .field("evictions", &stats.evictions)
             .finish()
     }
 }
@@ -332,6 +374,38 @@ mod tests {
         assert_eq!(stats.evictions, 1);
     }
 
+    #[test]
+    fn test_set_config_shrinks_cache() {
+        let cache = ImageCache::new();
+
+        for i in 0..4 {
+            let path = PathBuf::from(format!("/test/image{}.png", i));
+            cache.insert(path, create_test_image(10, 10));
+        }
+
+        cache.set_config(CacheConfig {
+            max_entries: 10,
+            max_size_bytes: 2 * 10 * 10 * 4,
+        });
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().evictions, 2);
+    }
+
+    #[test]
+    fn test_scaled_keys_are_distinct() {
+        let cache: ImageCache<CacheKey> = ImageCache::new();
+        let path = PathBuf::from("/test/image.png");
+        let zoom = fingerprint(&"Zoom");
+
+        cache.insert(CacheKey::original(&path), create_test_image(40, 20));
+        cache.insert(CacheKey::scaled(&path, (20, 10), zoom), create_test_image(20, 10));
+
+        assert_eq!(cache.get(&CacheKey::original(&path)).unwrap().width(), 40);
+        assert_eq!(cache.get(&CacheKey::scaled(&path, (20, 10), zoom)).unwrap().width(), 20);
+        assert!(cache.get(&CacheKey::scaled(&path, (20, 10), fingerprint(&"Stretch"))).is_none());
+    }
+
     #[test]
     fn test_cache_clear() {
         let cache = ImageCache::new();
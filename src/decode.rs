@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Decoding still images the way they are meant to be seen: upright, as the
+//! EXIF orientation tag of photos says, and with their colors in sRGB.
+
+use std::path::Path;
+
+use image::{DynamicImage, ImageDecoder, ImageResult, metadata::Orientation};
+
+/// Decode an image file of any format the `image` crate detects.
+pub fn open(path: &Path) -> ImageResult<DynamicImage> {
+    from_decoder(
+        image::ImageReader::open(path)?
+            .with_guessed_format()?
+            .into_decoder()?,
+    )
+}
+
+/// Decode an image, then rotate or flip it upright and convert it to sRGB
+/// from its embedded ICC profile.
+///
+/// Unreadable metadata is ignored rather than failing the decode.
+pub fn from_decoder(mut decoder: impl ImageDecoder) -> ImageResult<DynamicImage> {
+    let orientation = decoder.orientation().unwrap_or_else(|err| {
+        tracing::debug!(?err, "failed to read image orientation");
+        Orientation::NoTransforms
+    });
+    let icc = decoder.icc_profile().unwrap_or_else(|err| {
+        tracing::debug!(?err, "failed to read embedded ICC profile");
+        None
+    });
+
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    Ok(match icc {
+        Some(icc) => crate::icc::embedded_to_srgb(image, &icc),
+        None => image,
+    })
+}
+
+/// Decode a JPEG at 1/8, 1/4 or 1/2 of its size, the smallest that is still
+/// `size` pixels long on one side, for a quick look at its colors.
+///
+/// Returns `None` for other formats, and for CMYK and 16-bit JPEGs, which
+/// are left to a full decode.
+pub fn jpeg_preview(path: &Path, size: u16) -> Option<DynamicImage> {
+    use jpeg_decoder::{Decoder, PixelFormat};
+
+    let file = std::fs::File::open(path).ok()?;
+    let mut decoder = Decoder::new(std::io::BufReader::new(file));
+    let (width, height) = decoder.scale(size, size).ok()?;
+    let pixels = decoder.decode().ok()?;
+    let (width, height) = (u32::from(width), u32::from(height));
+
+    match decoder.info()?.pixel_format {
+        PixelFormat::L8 => image::GrayImage::from_raw(width, height, pixels).map(DynamicImage::from),
+        PixelFormat::RGB24 => image::RgbImage::from_raw(width, height, pixels).map(DynamicImage::from),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_exif_orientations() {
+        // Each fixture stores the same picture turned the way its tag
+        // undoes: 24x16, a white 8x8 block top left and a gray one bottom
+        // right, black elsewhere.
+        for tag in 1..=8 {
+            let image = open(&fixture(&format!("orientation-{tag}.jpg"))).unwrap();
+            assert_eq!(
+                (image.width(), image.height()),
+                (24, 16),
+                "orientation {tag}"
+            );
+
+            let luma = image.to_luma8();
+            let block = |x: u32, y: u32| luma.get_pixel(x * 8 + 4, y * 8 + 4).0[0];
+            assert!(
+                block(0, 0) > 230,
+                "orientation {tag}: top left {}",
+                block(0, 0)
+            );
+            assert!(
+                block(2, 1).abs_diff(128) < 20,
+                "orientation {tag}: bottom right {}",
+                block(2, 1)
+            );
+            for (x, y) in [(1, 0), (2, 0), (0, 1), (1, 1)] {
+                assert!(
+                    block(x, y) < 25,
+                    "orientation {tag}: block {x},{y} {}",
+                    block(x, y)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_file() {
+        assert!(open(&fixture("missing.jpg")).is_err());
+    }
+
+    #[test]
+    fn test_jpeg_preview() {
+        // 24x16 at a quarter of its size is the smallest with a side of 4.
+        let preview = jpeg_preview(&fixture("orientation-1.jpg"), 4).unwrap();
+        assert_eq!((preview.width(), preview.height()), (6, 4));
+
+        assert!(jpeg_preview(&fixture("still.gif"), 4).is_none());
+        assert!(jpeg_preview(&fixture("missing.jpg"), 4).is_none());
+    }
+}
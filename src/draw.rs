@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use crate::{CosmicBg, CosmicBgLayer};
+use cosmic_ext_bg_config::ColorDepth;
 use image::{DynamicImage, GenericImageView};
 use sctk::{
     reexports::client::{QueueHandle, protocol::wl_shm},
@@ -8,30 +9,46 @@ use sctk::{
     shm::slot::{Buffer, CreateBufferError, SlotPool},
 };
 
+/// The buffer format to draw a frame in.
+///
+/// `ten_bit` is whether the compositor accepts `Xrgb2101010` buffers; 8-bit
+/// buffers are used when it doesn't, whatever the entry asks for.
+/// `high_depth` is whether the decoded source, rather than the scaled frame,
+/// has more than 8 bits per channel, as scaling may work in floats.
+pub fn format(depth: ColorDepth, ten_bit: bool, high_depth: bool) -> wl_shm::Format {
+    let use_ten_bit = ten_bit
+        && match depth {
+            ColorDepth::Auto => high_depth,
+            ColorDepth::Eight => false,
+            ColorDepth::Ten => true,
+        };
+
+    if use_ten_bit {
+        wl_shm::Format::Xrgb2101010
+    } else {
+        wl_shm::Format::Xrgb8888
+    }
+}
+
+/// Whether the image has more than 8 bits per channel.
+pub fn is_high_depth(image: &DynamicImage) -> bool {
+    let color = image.color();
+    color.bits_per_pixel() > 8 * u16::from(color.channel_count())
+}
+
 pub fn canvas(
     pool: &mut SlotPool,
     image: &DynamicImage,
     width: i32,
     height: i32,
     stride: i32,
+    format: wl_shm::Format,
 ) -> Result<Buffer, CreateBufferError> {
-    // TODO: Check if we need 8-bit or 10-bit
-    let hdr_layer = false;
-
-    let (buffer, canvas) = pool.create_buffer(
-        width,
-        height,
-        stride,
-        if hdr_layer {
-            wl_shm::Format::Xrgb2101010
-        } else {
-            wl_shm::Format::Xrgb8888
-        },
-    )?;
+    let (buffer, canvas) = pool.create_buffer(width, height, stride, format)?;
 
     // Draw to the window:
     {
-        if hdr_layer {
+        if format == wl_shm::Format::Xrgb2101010 {
             xrgb21010_canvas(canvas, image);
         } else {
             xrgb888_canvas(canvas, image);
@@ -57,11 +74,12 @@ pub fn layer_surface(
     // Damage the entire window
     wl_surface.damage_buffer(0, 0, buffer_damage.0, buffer_damage.1);
 
-    // Request our next frame
+    // Request our next frame; animations wait for it before drawing again
     layer
         .layer
         .wl_surface()
         .frame(queue_handle, wl_surface.clone());
+    layer.frame_pending = true;
 
     // Attach and commit to present.
     if let Err(why) = buffer.attach_to(wl_surface) {
@@ -81,21 +99,22 @@ pub fn xrgb21010_canvas(canvas: &mut [u8], image: &DynamicImage) {
         canvas.len(), image.width(), image.height()
     );
 
-    const BIT_MASK: u32 = (1 << 10) - 1;
-
     for (pos, pixel) in image.to_rgb16().pixels().enumerate() {
         let indice = pos * 4;
 
-        let [r, g, b] = pixel.0;
+        let [r, g, b] = pixel.0.map(ten_bit);
 
-        let r = ((u32::from(r) * BIT_MASK) & BIT_MASK) << 20;
-        let g = ((u32::from(g) * BIT_MASK) & BIT_MASK) << 10;
-        let b = (u32::from(b) * BIT_MASK) & BIT_MASK;
-
-        canvas[indice..indice + 4].copy_from_slice(&(r | g | b).to_le_bytes());
+        canvas[indice..indice + 4].copy_from_slice(&((r << 20) | (g << 10) | b).to_le_bytes());
     }
 }
 
+/// Rescale a 16-bit channel to 10 bits, rounding to the nearest value.
+fn ten_bit(value: u16) -> u32 {
+    const MAX: u32 = (1 << 10) - 1;
+
+    (u32::from(value) * MAX + u32::from(u16::MAX) / 2) / u32::from(u16::MAX)
+}
+
 /// Draws the image on an 8-bit canvas.
 pub fn xrgb888_canvas(canvas: &mut [u8], image: &DynamicImage) {
     debug_assert!(
@@ -116,3 +135,106 @@ pub fn xrgb888_canvas(canvas: &mut [u8], image: &DynamicImage) {
         canvas[indice..indice + 4].copy_from_slice(&(r | g | b).to_le_bytes());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_bg_config::FilterMethod;
+    use image::{ImageBuffer, Rgb, Rgb32FImage, RgbImage, Rgba};
+
+    type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+    fn packed(canvas: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes(canvas[pos * 4..pos * 4 + 4].try_into().unwrap())
+    }
+
+    fn channels(pixel: u32) -> [u32; 3] {
+        [(pixel >> 20) & 0x3ff, (pixel >> 10) & 0x3ff, pixel & 0x3ff]
+    }
+
+    #[test]
+    fn test_ten_bit_quantization() {
+        assert_eq!(ten_bit(0), 0);
+        assert_eq!(ten_bit(u16::MAX), 1023);
+        // Halfway rounds to the nearest step rather than wrapping around.
+        assert_eq!(ten_bit(32768), 512);
+        assert_eq!(ten_bit(64), 1);
+        assert_eq!(ten_bit(31), 0);
+    }
+
+    #[test]
+    fn test_ten_bit_canvas() {
+        let mut image = Rgba16Image::new(3, 1);
+        image.put_pixel(0, 0, Rgba([u16::MAX, 0, 0, u16::MAX]));
+        image.put_pixel(1, 0, Rgba([0, 32768, u16::MAX, u16::MAX]));
+        image.put_pixel(2, 0, Rgba([1000, 20000, 40000, 0]));
+
+        let mut canvas = vec![0; 12];
+        xrgb21010_canvas(&mut canvas, &DynamicImage::ImageRgba16(image));
+
+        assert_eq!(packed(&canvas, 0), 0x3ff0_0000);
+        assert_eq!(channels(packed(&canvas, 1)), [0, 512, 1023]);
+        assert_eq!(channels(packed(&canvas, 2)), [16, 312, 624]);
+        // The two padding bits stay clear.
+        assert_eq!(packed(&canvas, 0) >> 30, 0);
+    }
+
+    #[test]
+    fn test_ten_bit_matches_eight_bit() {
+        // Every 8-bit level lands on the nearest 10-bit one.
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(256, 1, |x, _| {
+            Rgb([x as u8, 0, 255 - x as u8])
+        }));
+
+        let mut canvas = vec![0; 256 * 4];
+        xrgb21010_canvas(&mut canvas, &image);
+
+        for level in 0..256 {
+            let expected = (level as f64 * 1023.0 / 255.0).round() as u32;
+            let [r, _, b] = channels(packed(&canvas, level));
+            assert_eq!(r, expected, "level {level}");
+            assert_eq!(b, 1023 - expected, "level {level}");
+        }
+    }
+
+    #[test]
+    fn test_eight_bit_canvas() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([0x12, 0x34, 0x56])));
+
+        let mut canvas = vec![0; 4];
+        xrgb888_canvas(&mut canvas, &image);
+        assert_eq!(packed(&canvas, 0), 0x0012_3456);
+    }
+
+    #[test]
+    fn test_format() {
+        let sdr = DynamicImage::ImageRgb8(RgbImage::new(1, 1));
+        let hdr = DynamicImage::ImageRgb32F(Rgb32FImage::new(1, 1));
+        let deep = DynamicImage::ImageRgba16(Rgba16Image::new(1, 1));
+
+        assert!(!is_high_depth(&sdr));
+        assert!(is_high_depth(&hdr));
+        assert!(is_high_depth(&deep));
+
+        let ten = wl_shm::Format::Xrgb2101010;
+        let eight = wl_shm::Format::Xrgb8888;
+        assert_eq!(format(ColorDepth::Auto, true, true), ten);
+        assert_eq!(format(ColorDepth::Auto, true, false), eight);
+        assert_eq!(format(ColorDepth::Eight, true, true), eight);
+        assert_eq!(format(ColorDepth::Ten, true, false), ten);
+        // Without compositor support, everything is drawn in 8 bits.
+        assert_eq!(format(ColorDepth::Ten, false, true), eight);
+        assert_eq!(format(ColorDepth::Auto, false, true), eight);
+    }
+
+    #[test]
+    fn test_format_follows_source() {
+        // Letterboxing works in floats, which doesn't make a photo deeper.
+        let photo = DynamicImage::ImageRgb8(RgbImage::new(4, 2));
+        let fitted = crate::scaler::fit(&photo, &[0.0; 3], FilterMethod::Lanczos, 8, 8);
+        assert!(is_high_depth(&fitted));
+
+        let format = format(ColorDepth::Auto, true, is_high_depth(&photo));
+        assert_eq!(format, wl_shm::Format::Xrgb8888);
+    }
+}
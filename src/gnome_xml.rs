@@ -0,0 +1,368 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! GNOME background XML slideshows.
+//!
+//! Wallpaper packs often ship an `.xml` file describing a timed sequence of
+//! `<static>` images and `<transition>` crossfades between them. The sequence
+//! repeats from its `<starttime>`, so where it is depends on the wall clock,
+//! not on when the daemon started.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+
+/// Errors from reading a slideshow XML file.
+#[derive(Debug, thiserror::Error)]
+pub enum GnomeXmlError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("XML error: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("not a GNOME background slideshow: {0}")]
+    Invalid(&'static str),
+}
+
+/// Whether `path` names a slideshow XML file rather than an image.
+pub fn is_slideshow(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Static {
+        file: PathBuf,
+        duration: Duration,
+    },
+    Transition {
+        from: PathBuf,
+        to: PathBuf,
+        duration: Duration,
+    },
+}
+
+impl Item {
+    fn duration(&self) -> Duration {
+        match self {
+            Item::Static { duration, .. } | Item::Transition { duration, .. } => *duration,
+        }
+    }
+
+    /// Image on screen once the item has played out.
+    fn target(&self) -> &Path {
+        match self {
+            Item::Static { file, .. } => file,
+            Item::Transition { to, .. } => to,
+        }
+    }
+}
+
+/// What a slideshow shows at one point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlideshowFrame {
+    /// `file` is shown for `remaining` more, followed by `next`.
+    Static {
+        file: PathBuf,
+        next: PathBuf,
+        remaining: Duration,
+    },
+    /// A crossfade from `from` to `to`, `elapsed` into its `duration`.
+    Transition {
+        from: PathBuf,
+        to: PathBuf,
+        elapsed: Duration,
+        duration: Duration,
+    },
+}
+
+impl SlideshowFrame {
+    /// Time until the slideshow moves on to its next item.
+    pub fn remaining(&self) -> Duration {
+        match self {
+            SlideshowFrame::Static { remaining, .. } => *remaining,
+            SlideshowFrame::Transition {
+                elapsed, duration, ..
+            } => duration.saturating_sub(*elapsed),
+        }
+    }
+}
+
+/// A parsed slideshow, repeating from its start time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GnomeSlideshow {
+    /// Local time the first item began
+    start: NaiveDateTime,
+    items: Vec<Item>,
+    /// Length of one pass through `items`
+    cycle: Duration,
+}
+
+impl GnomeSlideshow {
+    /// Read a slideshow file. Relative image paths are resolved against the
+    /// file's directory.
+    pub fn load(path: &Path) -> Result<Self, GnomeXmlError> {
+        let xml = fs::read_to_string(path)?;
+        Self::parse(&xml, path.parent().unwrap_or(Path::new("/")))
+    }
+
+    /// Parse a slideshow, resolving relative image paths against `base`.
+    pub fn parse(xml: &str, base: &Path) -> Result<Self, GnomeXmlError> {
+        let document = roxmltree::Document::parse(xml)?;
+        let root = document.root_element();
+        if !root.has_tag_name("background") {
+            return Err(GnomeXmlError::Invalid("missing <background> element"));
+        }
+
+        let mut start = None;
+        let mut items = Vec::new();
+
+        for node in root.children().filter(|node| node.is_element()) {
+            match node.tag_name().name() {
+                "starttime" => start = parse_start_time(node),
+                "static" => {
+                    let file = child(node, "file").and_then(|file| parse_file(file, base));
+                    if let (Some(file), Some(duration)) = (file, parse_duration(node)) {
+                        items.push(Item::Static { file, duration });
+                    }
+                }
+                "transition" => {
+                    let from = child(node, "from").and_then(|from| parse_file(from, base));
+                    let to = child(node, "to").and_then(|to| parse_file(to, base));
+                    if let (Some(from), Some(to), Some(duration)) = (from, to, parse_duration(node))
+                    {
+                        items.push(Item::Transition { from, to, duration });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let cycle = items
+            .iter()
+            .try_fold(Duration::ZERO, |cycle, item| cycle.checked_add(item.duration()))
+            .ok_or(GnomeXmlError::Invalid("durations too long"))?;
+        if cycle.is_zero() {
+            return Err(GnomeXmlError::Invalid("no timed images"));
+        }
+
+        Ok(Self {
+            start: start.unwrap_or_default(),
+            items,
+            cycle,
+        })
+    }
+
+    /// What the slideshow shows now.
+    pub fn frame_now(&self) -> SlideshowFrame {
+        let elapsed = Local::now().naive_local() - self.start;
+        self.frame_at(elapsed.num_milliseconds() as f64 / 1000.0)
+    }
+
+    /// What the slideshow shows `since_start` seconds after its start time.
+    ///
+    /// The sequence repeats in both directions, so times before the start
+    /// land in an earlier pass.
+    pub fn frame_at(&self, since_start: f64) -> SlideshowFrame {
+        let mut offset = since_start.rem_euclid(self.cycle.as_secs_f64());
+
+        let mut index = self.items.len() - 1;
+        for (i, item) in self.items.iter().enumerate() {
+            let duration = item.duration().as_secs_f64();
+            if offset < duration {
+                index = i;
+                break;
+            }
+            offset -= duration;
+        }
+
+        let item = &self.items[index];
+        // Rounding to `f64` may step past the longest representable duration.
+        let elapsed = Duration::try_from_secs_f64(offset.max(0.0))
+            .map_or(item.duration(), |elapsed| elapsed.min(item.duration()));
+
+        match item {
+            Item::Static { file, duration } => SlideshowFrame::Static {
+                file: file.clone(),
+                next: self.items[(index + 1) % self.items.len()]
+                    .target()
+                    .to_path_buf(),
+                remaining: duration.saturating_sub(elapsed),
+            },
+            Item::Transition { from, to, duration } => SlideshowFrame::Transition {
+                from: from.clone(),
+                to: to.clone(),
+                elapsed,
+                duration: *duration,
+            },
+        }
+    }
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Option<T> {
+    child(node, name)?.text()?.trim().parse().ok()
+}
+
+fn parse_duration(node: roxmltree::Node) -> Option<Duration> {
+    let seconds: f64 = child_text(node, "duration")?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// An image path, or the largest of several `<size>` variants.
+fn parse_file(node: roxmltree::Node, base: &Path) -> Option<PathBuf> {
+    let area = |size: &roxmltree::Node| {
+        let dimension = |name| size.attribute(name)?.parse::<u64>().ok();
+        dimension("width").unwrap_or(0) * dimension("height").unwrap_or(0)
+    };
+
+    let path = node
+        .children()
+        .filter(|size| size.has_tag_name("size"))
+        .max_by_key(area)
+        .and_then(|size| size.text())
+        .or_else(|| node.text())?
+        .trim();
+
+    (!path.is_empty()).then(|| base.join(path))
+}
+
+fn parse_start_time(node: roxmltree::Node) -> Option<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(
+        child_text(node, "year")?,
+        child_text(node, "month")?,
+        child_text(node, "day")?,
+    )?
+    .and_hms_opt(
+        child_text(node, "hour").unwrap_or(0),
+        child_text(node, "minute").unwrap_or(0),
+        child_text(node, "second").unwrap_or(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLIDESHOW: &str = r#"<?xml version="1.0"?>
+<background>
+  <starttime>
+    <year>2011</year><month>11</month><day>24</day>
+    <hour>7</hour><minute>00</minute><second>00</second>
+  </starttime>
+  <static>
+    <duration>100.0</duration>
+    <file>morning.jpg</file>
+  </static>
+  <transition type="overlay">
+    <duration>20.0</duration>
+    <from>morning.jpg</from>
+    <to>/usr/share/backgrounds/night.png</to>
+  </transition>
+  <static>
+    <duration>80.0</duration>
+    <file>
+      <size width="1024" height="768">night-small.png</size>
+      <size width="3840" height="2160">/usr/share/backgrounds/night.png</size>
+    </file>
+  </static>
+</background>"#;
+
+    fn slideshow() -> GnomeSlideshow {
+        GnomeSlideshow::parse(SLIDESHOW, Path::new("/packs/day")).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let slideshow = slideshow();
+
+        assert_eq!(slideshow.cycle, Duration::from_secs(200));
+        assert_eq!(slideshow.start.to_string(), "2011-11-24 07:00:00");
+        assert_eq!(
+            slideshow.items[0],
+            Item::Static {
+                file: PathBuf::from("/packs/day/morning.jpg"),
+                duration: Duration::from_secs(100),
+            }
+        );
+        // The largest size variant is used.
+        assert_eq!(
+            slideshow.items[2].target(),
+            Path::new("/usr/share/backgrounds/night.png")
+        );
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let slideshow = slideshow();
+
+        assert_eq!(
+            slideshow.frame_at(30.0),
+            SlideshowFrame::Static {
+                file: PathBuf::from("/packs/day/morning.jpg"),
+                next: PathBuf::from("/usr/share/backgrounds/night.png"),
+                remaining: Duration::from_secs(70),
+            }
+        );
+
+        let fading = slideshow.frame_at(105.0);
+        assert!(matches!(
+            fading,
+            SlideshowFrame::Transition { elapsed, duration, .. }
+                if elapsed == Duration::from_secs(5) && duration == Duration::from_secs(20)
+        ));
+        assert_eq!(fading.remaining(), Duration::from_secs(15));
+
+        // The last image leads back to the first.
+        let SlideshowFrame::Static {
+            next, remaining, ..
+        } = slideshow.frame_at(150.0)
+        else {
+            panic!("expected a static frame");
+        };
+        assert_eq!(next, PathBuf::from("/packs/day/morning.jpg"));
+        assert_eq!(remaining, Duration::from_secs(50));
+    }
+
+    #[test]
+    fn test_frame_at_repeats() {
+        let slideshow = slideshow();
+
+        assert_eq!(slideshow.frame_at(2030.0), slideshow.frame_at(30.0));
+        // Before the start time, an earlier pass is playing.
+        assert_eq!(slideshow.frame_at(-170.0), slideshow.frame_at(30.0));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let base = Path::new("/");
+
+        assert!(GnomeSlideshow::parse("<wallpapers/>", base).is_err());
+        assert!(GnomeSlideshow::parse("<background></background>", base).is_err());
+        assert!(GnomeSlideshow::parse("<background>", base).is_err());
+
+        // Durations that don't fit are skipped, as are cycles that overflow.
+        let huge = |duration: &str| {
+            format!("<static><duration>{duration}</duration><file>a.jpg</file></static>")
+        };
+        let skipped = format!("<background>{}{}</background>", huge("1e20"), huge("5"));
+        let slideshow = GnomeSlideshow::parse(&skipped, base).unwrap();
+        assert_eq!(slideshow.cycle, Duration::from_secs(5));
+        let long = format!("<background>{}{}</background>", huge("1e19"), huge("1e19"));
+        assert!(GnomeSlideshow::parse(&long, base).is_err());
+        let single = format!("<background>{}</background>", huge("1.8e19"));
+        let slideshow = GnomeSlideshow::parse(&single, base).unwrap();
+        assert!(matches!(slideshow.frame_at(1e30), SlideshowFrame::Static { .. }));
+        assert!(is_slideshow(Path::new("/a/b.XML")));
+        assert!(!is_slideshow(Path::new("/a/b.png")));
+    }
+}
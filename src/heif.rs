@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! HEIF and AVIF stills, decoded with the system `libheif`.
+//!
+//! `libheif` applies the rotation and mirroring stored in the container, so
+//! the EXIF orientation of these files is not applied again. Images with
+//! more than 8 bits per channel are kept at 16 bits, so they can be drawn
+//! into a 10-bit buffer.
+
+use std::path::Path;
+
+use image::{DynamicImage, ImageBuffer, Rgb, Rgba};
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HeifError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("libheif error: {0}")]
+    Heif(#[from] libheif_rs::HeifError),
+    #[error("decoded image has no interleaved RGB plane")]
+    NoPixels,
+}
+
+/// Whether `path` names a HEIF or AVIF file.
+pub fn is_heif(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        ["heic", "heif", "hif", "avif"]
+            .iter()
+            .any(|known| ext.eq_ignore_ascii_case(known))
+    })
+}
+
+/// Decode the primary image of the file at `path`, converted to sRGB from
+/// its embedded ICC profile.
+pub fn decode(path: &Path) -> Result<DynamicImage, HeifError> {
+    let data = std::fs::read(path)?;
+    let context = HeifContext::read_from_bytes(&data)?;
+    let handle = context.primary_image_handle()?;
+
+    let alpha = handle.has_alpha_channel();
+    let depth = handle.luma_bits_per_pixel();
+    let chroma = match (depth > 8, alpha) {
+        (false, false) => RgbChroma::Rgb,
+        (false, true) => RgbChroma::Rgba,
+        (true, false) => RgbChroma::HdrRgbLe,
+        (true, true) => RgbChroma::HdrRgbaLe,
+    };
+
+    let decoded = LibHeif::new().decode(&handle, ColorSpace::Rgb(chroma), None)?;
+    let planes = decoded.planes();
+    let plane = planes.interleaved.ok_or(HeifError::NoPixels)?;
+    let channels = if alpha { 4 } else { 3 };
+
+    let image = if depth > 8 {
+        let rows = rows(
+            plane.data,
+            plane.stride,
+            plane.width as usize * channels * 2,
+        );
+        let samples = rows
+            .flat_map(|row| row.chunks_exact(2))
+            .map(|sample| widen(u16::from_le_bytes([sample[0], sample[1]]), depth))
+            .collect();
+        if alpha {
+            ImageBuffer::<Rgba<u16>, _>::from_raw(plane.width, plane.height, samples)
+                .map(DynamicImage::ImageRgba16)
+        } else {
+            ImageBuffer::<Rgb<u16>, _>::from_raw(plane.width, plane.height, samples)
+                .map(DynamicImage::ImageRgb16)
+        }
+    } else {
+        let samples = rows(plane.data, plane.stride, plane.width as usize * channels)
+            .flatten()
+            .copied()
+            .collect();
+        if alpha {
+            ImageBuffer::<Rgba<u8>, _>::from_raw(plane.width, plane.height, samples)
+                .map(DynamicImage::ImageRgba8)
+        } else {
+            ImageBuffer::<Rgb<u8>, _>::from_raw(plane.width, plane.height, samples)
+                .map(DynamicImage::ImageRgb8)
+        }
+    }
+    .ok_or(HeifError::NoPixels)?;
+
+    Ok(match handle.color_profile_raw() {
+        Some(profile) => crate::icc::embedded_to_srgb(image, &profile.data),
+        None => image,
+    })
+}
+
+/// The pixel bytes of each row of a plane, without the stride padding.
+fn rows(data: &[u8], stride: usize, width: usize) -> impl Iterator<Item = &[u8]> {
+    data.chunks(stride.max(1))
+        .map(move |row| &row[..width.min(row.len())])
+}
+
+/// Stretch a `depth` bit sample over the full 16-bit range.
+fn widen(sample: u16, depth: u8) -> u16 {
+    let max = (1u32 << depth.clamp(1, 16)) - 1;
+    let sample = u32::from(sample).min(max);
+    ((sample * 65535 + max / 2) / max) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_heif() {
+        assert!(is_heif(Path::new("/home/user/Pictures/IMG_0001.HEIC")));
+        assert!(is_heif(Path::new("photo.avif")));
+        assert!(is_heif(Path::new("photo.heif")));
+        assert!(!is_heif(Path::new("photo.jpg")));
+        assert!(!is_heif(Path::new("avif")));
+    }
+
+    #[test]
+    fn test_widen() {
+        assert_eq!(widen(0, 10), 0);
+        assert_eq!(widen(1023, 10), 65535);
+        assert_eq!(widen(4095, 12), 65535);
+        assert_eq!(widen(512, 10), 32800);
+        // Out of range samples are clipped rather than wrapped.
+        assert_eq!(widen(2000, 10), 65535);
+    }
+
+    #[test]
+    fn test_rows_skip_padding() {
+        let data = [1, 2, 3, 0, 4, 5, 6, 0];
+        let pixels: Vec<u8> = rows(&data, 4, 3).flatten().copied().collect();
+        assert_eq!(pixels, [1, 2, 3, 4, 5, 6]);
+    }
+}
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! ICC color management.
+//!
+//! Decoded images are converted from the profile embedded in the file to
+//! sRGB, which everything downstream assumes. Entries with a display profile
+//! convert each drawn frame from sRGB to it before it reaches the buffer.
+
+use std::{path::Path, sync::OnceLock};
+
+use image::{DynamicImage, ImageBuffer, Pixel};
+use moxcms::{
+    CmsError, ColorProfile, DataColorSpace, Layout, Transform8BitExecutor, Transform16BitExecutor,
+    TransformExecutor, TransformF32BitExecutor, TransformOptions,
+};
+
+/// A transform, built the first time an image needs it.
+type Cached<E> = OnceLock<Result<Box<E>, CmsError>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IccError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ICC profile error: {0}")]
+    Cms(#[from] CmsError),
+    #[error("not an RGB profile")]
+    NotRgb,
+}
+
+/// A conversion from one RGB profile to another.
+///
+/// Transforms are kept for each pixel format, since display conversions run
+/// on every drawn frame.
+pub struct Conversion {
+    from: ColorProfile,
+    to: ColorProfile,
+    rgb8: Cached<Transform8BitExecutor>,
+    rgba8: Cached<Transform8BitExecutor>,
+    rgb16: Cached<Transform16BitExecutor>,
+    rgba16: Cached<Transform16BitExecutor>,
+    rgb32f: Cached<TransformF32BitExecutor>,
+    rgba32f: Cached<TransformF32BitExecutor>,
+}
+
+impl std::fmt::Debug for Conversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Conversion").finish_non_exhaustive()
+    }
+}
+
+impl Conversion {
+    fn new(from: ColorProfile, to: ColorProfile) -> Self {
+        Self {
+            from,
+            to,
+            rgb8: OnceLock::new(),
+            rgba8: OnceLock::new(),
+            rgb16: OnceLock::new(),
+            rgba16: OnceLock::new(),
+            rgb32f: OnceLock::new(),
+            rgba32f: OnceLock::new(),
+        }
+    }
+
+    /// Convert from the profile in `icc` to sRGB.
+    pub fn to_srgb(icc: &[u8]) -> Result<Self, IccError> {
+        Ok(Self::new(rgb_profile(icc)?, ColorProfile::new_srgb()))
+    }
+
+    /// Convert from sRGB to the display profile at `path`.
+    pub fn to_display(path: &Path) -> Result<Self, IccError> {
+        Ok(Self::new(
+            ColorProfile::new_srgb(),
+            rgb_profile(&std::fs::read(path)?)?,
+        ))
+    }
+
+    /// Convert the colors of `image`, keeping its pixel format.
+    ///
+    /// Grayscale images are returned as they are.
+    pub fn convert(&self, image: &DynamicImage) -> Result<DynamicImage, IccError> {
+        let options = TransformOptions::default();
+        let eight = |layout| {
+            self.from
+                .create_transform_8bit(layout, &self.to, layout, options)
+        };
+        let sixteen = |layout| {
+            self.from
+                .create_transform_16bit(layout, &self.to, layout, options)
+        };
+        let float = |layout| {
+            self.from
+                .create_transform_f32(layout, &self.to, layout, options)
+        };
+
+        Ok(match image {
+            DynamicImage::ImageRgb8(buffer) => DynamicImage::ImageRgb8(transform(
+                buffer,
+                cached(&self.rgb8, || eight(Layout::Rgb))?,
+            )?),
+            DynamicImage::ImageRgba8(buffer) => DynamicImage::ImageRgba8(transform(
+                buffer,
+                cached(&self.rgba8, || eight(Layout::Rgba))?,
+            )?),
+            DynamicImage::ImageRgb16(buffer) => DynamicImage::ImageRgb16(transform(
+                buffer,
+                cached(&self.rgb16, || sixteen(Layout::Rgb))?,
+            )?),
+            DynamicImage::ImageRgba16(buffer) => DynamicImage::ImageRgba16(transform(
+                buffer,
+                cached(&self.rgba16, || sixteen(Layout::Rgba))?,
+            )?),
+            DynamicImage::ImageRgb32F(buffer) => DynamicImage::ImageRgb32F(transform(
+                buffer,
+                cached(&self.rgb32f, || float(Layout::Rgb))?,
+            )?),
+            DynamicImage::ImageRgba32F(buffer) => DynamicImage::ImageRgba32F(transform(
+                buffer,
+                cached(&self.rgba32f, || float(Layout::Rgba))?,
+            )?),
+            image => image.clone(),
+        })
+    }
+}
+
+/// Convert a decoded image to sRGB from the ICC profile embedded in its
+/// file.
+///
+/// Images whose profile can't be read or applied are returned unconverted.
+pub fn embedded_to_srgb(image: DynamicImage, icc: &[u8]) -> DynamicImage {
+    match Conversion::to_srgb(icc).and_then(|conversion| conversion.convert(&image)) {
+        Ok(converted) => converted,
+        Err(err) => {
+            tracing::debug!(%err, "ignoring embedded ICC profile");
+            image
+        }
+    }
+}
+
+/// The transform in `cell`, built with `create` on first use.
+fn cached<E: ?Sized>(
+    cell: &Cached<E>,
+    create: impl FnOnce() -> Result<Box<E>, CmsError>,
+) -> Result<&E, CmsError> {
+    cell.get_or_init(create).as_deref().map_err(Clone::clone)
+}
+
+fn rgb_profile(icc: &[u8]) -> Result<ColorProfile, IccError> {
+    let profile = ColorProfile::new_from_slice(icc)?;
+    if profile.color_space != DataColorSpace::Rgb {
+        return Err(IccError::NotRgb);
+    }
+    Ok(profile)
+}
+
+fn transform<P: Pixel>(
+    buffer: &ImageBuffer<P, Vec<P::Subpixel>>,
+    executor: &(dyn TransformExecutor<P::Subpixel> + Send + Sync),
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, CmsError>
+where
+    P::Subpixel: Default,
+{
+    let mut converted = buffer.as_raw().clone();
+    executor.transform(buffer.as_raw(), &mut converted)?;
+    Ok(
+        ImageBuffer::from_raw(buffer.width(), buffer.height(), converted)
+            .expect("conversion keeps the buffer size"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, Rgb32FImage, RgbImage, Rgba, RgbaImage};
+
+    fn display_p3() -> Vec<u8> {
+        ColorProfile::new_display_p3().encode().unwrap()
+    }
+
+    #[test]
+    fn test_display_p3_to_srgb() {
+        let conversion = Conversion::to_srgb(&display_p3()).unwrap();
+
+        let mut image = RgbImage::new(3, 1);
+        image.put_pixel(0, 0, Rgb([0, 255, 0]));
+        image.put_pixel(1, 0, Rgb([128, 128, 128]));
+        image.put_pixel(2, 0, Rgb([200, 100, 50]));
+        let DynamicImage::ImageRgb8(srgb) = conversion.convert(&image.into()).unwrap() else {
+            panic!("expected an 8-bit RGB image");
+        };
+
+        // P3 green is outside sRGB, so it is clipped to the most saturated
+        // green sRGB has.
+        let [r, g, b] = srgb.get_pixel(0, 0).0;
+        assert!(r == 0 && g == 255 && b == 0, "{r} {g} {b}");
+
+        // Grays stay gray.
+        let [r, g, b] = srgb.get_pixel(1, 0).0;
+        assert!(r.abs_diff(128) <= 1 && r == g && g == b, "{r} {g} {b}");
+
+        // Other colors are more saturated in sRGB values than in P3 ones.
+        let [r, _, b] = srgb.get_pixel(2, 0).0;
+        assert!(r > 200 && b < 50, "{r} {b}");
+    }
+
+    #[test]
+    fn test_reuses_transforms() {
+        let conversion = Conversion::to_srgb(&display_p3()).unwrap();
+        let image = DynamicImage::from(RgbImage::from_pixel(2, 2, Rgb([200, 100, 50])));
+
+        let first = conversion.convert(&image).unwrap();
+        assert!(conversion.rgb8.get().is_some());
+        assert!(conversion.rgba8.get().is_none());
+        assert_eq!(conversion.convert(&image).unwrap(), first);
+    }
+
+    #[test]
+    fn test_keeps_format_and_alpha() {
+        let conversion = Conversion::to_srgb(&display_p3()).unwrap();
+
+        let image = RgbaImage::from_pixel(1, 1, Rgba([10, 20, 30, 77]));
+        let DynamicImage::ImageRgba8(converted) = conversion.convert(&image.into()).unwrap() else {
+            panic!("expected an 8-bit RGBA image");
+        };
+        assert_eq!(converted.get_pixel(0, 0).0[3], 77);
+
+        let deep = Rgb32FImage::from_pixel(1, 1, Rgb([0.5; 3]));
+        let converted = conversion.convert(&deep.into()).unwrap();
+        assert!(matches!(converted, DynamicImage::ImageRgb32F(_)));
+
+        let gray = DynamicImage::new_luma8(1, 1);
+        assert_eq!(conversion.convert(&gray).unwrap(), gray);
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        assert!(matches!(
+            Conversion::to_srgb(b"not a profile"),
+            Err(IccError::Cms(_))
+        ));
+
+        let gray = ColorProfile::new_gray_with_gamma(2.2).encode().unwrap();
+        assert!(matches!(Conversion::to_srgb(&gray), Err(IccError::NotRgb)));
+
+        assert!(matches!(
+            Conversion::to_display(Path::new("/nonexistent.icc")),
+            Err(IccError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Unix-domain control socket used by `cosmic-ext-bg-ctl` to drive the
+//! running daemon (next / previous / pause / resume / reload / status).
+//!
+//! The protocol types live in [`cosmic_ext_bg_config::ipc`] so that the CLI
+//! and the daemon always agree on the wire format.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    time::Duration,
+};
+
+use cosmic_ext_bg_config::{
+    Config,
+    ipc::{self, Command, PROTOCOL_VERSION, Request, Response},
+};
+use sctk::reexports::calloop::{
+    Interest, LoopHandle, Mode, PostAction,
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+};
+
+use crate::{CosmicBg, error::WallpaperError};
+
+/// Upper bound for a single request line, to avoid unbounded reads.
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// How long a client may take to send its request, and to take the reply.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Bind the control socket and insert it into the event loop.
+pub fn ipc_source(handle: &LoopHandle<'static, CosmicBg>) -> Result<(), WallpaperError> {
+    let path = ipc::socket_path();
+
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(WallpaperError::EventLoopSource {
+                details: format!("another instance is listening on {}", path.display()),
+            });
+        }
+
+        // Left behind by a previous instance that did not exit cleanly.
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    listener.set_nonblocking(true)?;
+
+    tracing::debug!(?path, "control socket listening");
+
+    handle
+        .insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            |_, listener, state| {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(why) = client_source(&state.loop_handle, stream) {
+                                tracing::warn!(?why, "control socket client failed");
+                            }
+                        }
+                        Err(why) if why.kind() == ErrorKind::WouldBlock => break,
+                        Err(why) => {
+                            tracing::error!(?why, "failed to accept control socket client");
+                            break;
+                        }
+                    }
+                }
+
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|_| WallpaperError::EventLoopInsert {
+            source_type: "control socket",
+        })?;
+
+    Ok(())
+}
+
+/// Insert a client into the event loop, reading its request as it arrives
+/// so that a slow client doesn't block drawing.
+///
+/// Clients that haven't sent a full request within [`CLIENT_TIMEOUT`] are
+/// dropped.
+fn client_source(
+    handle: &LoopHandle<'static, CosmicBg>,
+    stream: UnixStream,
+) -> Result<(), WallpaperError> {
+    stream.set_nonblocking(true)?;
+
+    let mut request = Vec::new();
+    let token = handle
+        .insert_source(
+            Generic::new(stream, Interest::READ, Mode::Level),
+            move |_, stream, state| {
+                match read_request(stream, &mut request) {
+                    Ok(false) => return Ok(PostAction::Continue),
+                    Ok(true) => {
+                        if let Err(why) = respond(stream, &request, state) {
+                            tracing::warn!(?why, "control socket client failed");
+                        }
+                    }
+                    Err(why) => tracing::warn!(?why, "control socket client failed"),
+                }
+
+                Ok(PostAction::Remove)
+            },
+        )
+        .map_err(|_| WallpaperError::EventLoopInsert {
+            source_type: "control socket client",
+        })?;
+
+    // Removing a client that was already answered does nothing.
+    let timeout_handle = handle.clone();
+    handle
+        .insert_source(Timer::from_duration(CLIENT_TIMEOUT), move |_, _, _| {
+            timeout_handle.remove(token);
+            TimeoutAction::Drop
+        })
+        .map_err(|_| WallpaperError::EventLoopInsert {
+            source_type: "control socket client timeout",
+        })?;
+
+    Ok(())
+}
+
+/// Read what the client has sent so far, returning whether its request is
+/// complete: a full line, everything up to the end of the stream, or as much
+/// as a request may take.
+fn read_request(mut stream: &UnixStream, request: &mut Vec<u8>) -> std::io::Result<bool> {
+    let mut chunk = [0; 4096];
+
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => return Ok(true),
+            Ok(read) => {
+                request.extend_from_slice(&chunk[..read]);
+
+                if let Some(end) = request.iter().position(|&byte| byte == b'\n') {
+                    request.truncate(end + 1);
+                    return Ok(true);
+                }
+
+                if request.len() >= MAX_REQUEST_BYTES {
+                    request.truncate(MAX_REQUEST_BYTES);
+                    return Ok(true);
+                }
+            }
+            Err(why) if why.kind() == ErrorKind::WouldBlock => return Ok(false),
+            Err(why) if why.kind() == ErrorKind::Interrupted => {}
+            Err(why) => return Err(why),
+        }
+    }
+}
+
+fn respond(mut stream: &UnixStream, request: &[u8], state: &mut CosmicBg) -> std::io::Result<()> {
+    let line = String::from_utf8_lossy(request);
+
+    let response = match ipc::decode::<Request>(&line) {
+        Ok(request) if request.version != PROTOCOL_VERSION => Response::Error(format!(
+            "protocol version mismatch: client {}, daemon {PROTOCOL_VERSION}",
+            request.version
+        )),
+        Ok(request) => handle_command(state, request.command),
+        Err(why) => Response::Error(format!("malformed request: {why}")),
+    };
+
+    let reply = ipc::encode(&response)
+        .map_err(|why| std::io::Error::new(ErrorKind::InvalidData, why))?;
+
+    // Replies are short, so a blocking write only waits on a client that
+    // stopped reading, and not for long.
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.write_all(reply.as_bytes())
+}
+
+fn handle_command(state: &mut CosmicBg, command: Command) -> Response {
+    tracing::debug!(?command, "control command");
+
+    match command {
+        Command::Next { output } => {
+            let response = for_each_wallpaper(state, output.as_deref(), |w| {
+                w.rotate(true);
+            });
+            state.schedule_animations();
+            response
+        }
+        Command::Previous { output } => {
+            let response = for_each_wallpaper(state, output.as_deref(), |w| {
+                w.rotate(false);
+            });
+            state.schedule_animations();
+            response
+        }
+        Command::Pause { output } => {
+            for_each_wallpaper(state, output.as_deref(), |w| w.set_paused(true))
+        }
+        Command::Resume { output } => {
+            let response = for_each_wallpaper(state, output.as_deref(), |w| w.set_paused(false));
+            state.schedule_animations();
+            response
+        }
+        Command::Reload => match cosmic_ext_bg_config::context() {
+            Ok(context) => {
+                state.config = Config::load(&context).unwrap_or_else(|why| {
+                    tracing::error!(?why, "Config file error, falling back to defaults");
+                    Config::default()
+                });
+                state.apply_backgrounds();
+                Response::Ok
+            }
+            Err(why) => Response::Error(format!("failed to open config: {why}")),
+        },
+        Command::Status => Response::Status(
+            state
+                .wallpapers
+                .iter()
+                .flat_map(|w| w.status())
+                .collect(),
+        ),
+    }
+}
+
+/// Apply `f` to the wallpaper shown on `output`, or to every wallpaper.
+fn for_each_wallpaper(
+    state: &mut CosmicBg,
+    output: Option<&str>,
+    mut f: impl FnMut(&mut crate::wallpaper::Wallpaper),
+) -> Response {
+    let mut matched = false;
+
+    for wallpaper in &mut state.wallpapers {
+        let shows_output = output.is_none_or(|name| {
+            wallpaper
+                .layers
+                .iter()
+                .any(|l| l.output_info.name.as_deref() == Some(name))
+        });
+
+        if shows_output {
+            matched = true;
+            f(wallpaper);
+        }
+    }
+
+    if matched || output.is_none() {
+        Response::Ok
+    } else {
+        Response::Error(format!("no wallpaper is shown on output {}", output.unwrap_or_default()))
+    }
+}
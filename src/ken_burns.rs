@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Ken Burns effect: a slow pan and zoom across a still image.
+//!
+//! Each frame is a crop of the decoded image, scaled to the layer. The crop
+//! moves between two viewports picked from the image path, so every image
+//! moves its own way, and back again if it stays on screen longer than one
+//! pass.
+
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Length of a pass when the wallpaper doesn't rotate.
+pub const DEFAULT_PASS: Duration = Duration::from_secs(300);
+
+/// Part of the image in view, as a magnification over filling the layer and
+/// a position within the range the view can pan over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub zoom: f64,
+    /// 0.0 at the left edge of the image, 1.0 at the right
+    pub x: f64,
+    /// 0.0 at the top edge of the image, 1.0 at the bottom
+    pub y: f64,
+}
+
+/// Rectangle of the source image in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crop {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A pan from one corner of the image towards the opposite one, zooming in
+/// or out along the way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motion {
+    pub from: Viewport,
+    pub to: Viewport,
+}
+
+impl Motion {
+    /// The motion for the image at `path`, reaching a magnification of
+    /// `zoom` at its close end.
+    pub fn for_path(path: &Path, zoom: f64) -> Self {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        Self::new(hasher.finish(), zoom)
+    }
+
+    /// The motion picked by `seed`.
+    pub fn new(seed: u64, zoom: f64) -> Self {
+        const CORNERS: [(f64, f64); 4] = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+        let start = (seed % 4) as usize;
+        let (from_zoom, to_zoom) = if seed & 4 == 0 {
+            (1.0, zoom)
+        } else {
+            (zoom, 1.0)
+        };
+
+        let viewport = |corner: usize, zoom| {
+            let (x, y) = CORNERS[corner % 4];
+            Viewport { zoom, x, y }
+        };
+
+        Self {
+            from: viewport(start, from_zoom),
+            to: viewport(start + 2, to_zoom),
+        }
+    }
+
+    /// The viewport `t` of the way through, from 0.0 to 1.0.
+    pub fn at(&self, t: f64) -> Viewport {
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        Viewport {
+            zoom: lerp(self.from.zoom, self.to.zoom),
+            x: lerp(self.from.x, self.to.x),
+            y: lerp(self.from.y, self.to.y),
+        }
+    }
+}
+
+/// How far through the motion the view is after `elapsed`, going back and
+/// forth once every two passes and slowing down at either end.
+pub fn progress(elapsed: Duration, pass: Duration) -> f64 {
+    if pass.is_zero() {
+        return 0.0;
+    }
+
+    let phase = (elapsed.as_secs_f64() / pass.as_secs_f64()) % 2.0;
+    let t = if phase <= 1.0 { phase } else { 2.0 - phase };
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// The rectangle of an `image` sized source shown at `viewport` on a layer
+/// of size `layer`.
+pub fn crop(image: (u32, u32), layer: (u32, u32), viewport: Viewport) -> Crop {
+    let (image_width, image_height) = (f64::from(image.0), f64::from(image.1));
+    let (layer_width, layer_height) = (f64::from(layer.0.max(1)), f64::from(layer.1.max(1)));
+
+    // The crop that zooms the image to fill the layer, then magnified.
+    let fill = (layer_width / image_width).max(layer_height / image_height);
+    let zoom = viewport.zoom.max(1.0);
+    let width = (layer_width / fill / zoom).min(image_width);
+    let height = (layer_height / fill / zoom).min(image_height);
+
+    Crop {
+        x: (image_width - width) * viewport.x.clamp(0.0, 1.0),
+        y: (image_height - height) * viewport.y.clamp(0.0, 1.0),
+        width,
+        height,
+    }
+}
+
+/// Time the current image has been moving, stopped while paused.
+#[derive(Debug, Default)]
+pub struct Clock {
+    started: Option<Instant>,
+    paused_at: Option<Instant>,
+}
+
+impl Clock {
+    /// Start over with the next frame drawn.
+    pub fn reset(&mut self) {
+        self.started = None;
+    }
+
+    /// Time the image has been moving at `now`, starting the clock if this
+    /// is its first frame.
+    pub fn elapsed(&mut self, now: Instant) -> Duration {
+        let started = match self.started {
+            Some(started) => started,
+            None => {
+                // A first frame while paused holds still until resumed.
+                if self.paused_at.is_some() {
+                    self.paused_at = Some(now);
+                }
+                *self.started.insert(now)
+            }
+        };
+
+        self.paused_at
+            .unwrap_or(now)
+            .saturating_duration_since(started)
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        self.paused_at.get_or_insert(now);
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        let Some(paused_at) = self.paused_at.take() else {
+            return;
+        };
+
+        if let Some(started) = self.started.as_mut() {
+            *started += now.saturating_duration_since(paused_at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASS: Duration = Duration::from_secs(100);
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_progress_back_and_forth() {
+        assert_close(progress(Duration::ZERO, PASS), 0.0);
+        assert_close(progress(Duration::from_secs(50), PASS), 0.5);
+        assert_close(progress(PASS, PASS), 1.0);
+        // The second pass goes back the way it came.
+        assert_close(
+            progress(Duration::from_secs(125), PASS),
+            progress(Duration::from_secs(75), PASS),
+        );
+        assert_close(progress(Duration::from_secs(200), PASS), 0.0);
+        // Eased at the ends.
+        assert!(progress(Duration::from_secs(10), PASS) < 0.1);
+        assert_close(progress(Duration::from_secs(10), Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn test_motion() {
+        for seed in 0..8 {
+            let motion = Motion::new(seed, 1.5);
+            // Opposite corners, one end zoomed in.
+            assert_close((motion.from.x - motion.to.x).abs(), 1.0);
+            assert_close((motion.from.y - motion.to.y).abs(), 1.0);
+            assert_close(motion.from.zoom.max(motion.to.zoom), 1.5);
+            assert_close(motion.from.zoom.min(motion.to.zoom), 1.0);
+            assert_eq!(motion.at(0.0), motion.from);
+            assert_eq!(motion.at(1.0), motion.to);
+        }
+
+        let path = Path::new("/usr/share/backgrounds/a.jpg");
+        assert_eq!(Motion::for_path(path, 1.2), Motion::for_path(path, 1.2));
+    }
+
+    #[test]
+    fn test_crop() {
+        let centered = Viewport {
+            zoom: 1.0,
+            x: 0.5,
+            y: 0.5,
+        };
+
+        // A 4:3 image on a 16:9 layer loses the top and bottom.
+        let rect = crop((4000, 3000), (1920, 1080), centered);
+        assert_close(rect.width, 4000.0);
+        assert_close(rect.height, 2250.0);
+        assert_close(rect.y, 375.0);
+
+        // Zooming in halves the crop, which keeps the layer aspect ratio
+        // and stays inside the image at the far corner.
+        let zoomed = crop(
+            (4000, 3000),
+            (1920, 1080),
+            Viewport {
+                zoom: 2.0,
+                x: 1.0,
+                y: 1.0,
+            },
+        );
+        assert_close(zoomed.width, 2000.0);
+        assert_close(zoomed.width / zoomed.height, 1920.0 / 1080.0);
+        assert_close(zoomed.x + zoomed.width, 4000.0);
+        assert_close(zoomed.y + zoomed.height, 3000.0);
+    }
+
+    #[test]
+    fn test_clock_pauses() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut clock = Clock::default();
+
+        assert_eq!(clock.elapsed(at(0)), Duration::ZERO);
+        assert_eq!(clock.elapsed(at(10)), Duration::from_secs(10));
+
+        clock.pause(at(10));
+        assert_eq!(clock.elapsed(at(30)), Duration::from_secs(10));
+        clock.resume(at(30));
+        assert_eq!(clock.elapsed(at(35)), Duration::from_secs(15));
+
+        // A new image starts from zero, and holds still while paused.
+        clock.reset();
+        clock.pause(at(40));
+        assert_eq!(clock.elapsed(at(50)), Duration::ZERO);
+        clock.resume(at(60));
+        assert_eq!(clock.elapsed(at(65)), Duration::from_secs(5));
+    }
+}
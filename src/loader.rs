@@ -5,19 +5,29 @@
 //! This module provides background loading of images to prevent blocking the
 //! Wayland event loop during directory scanning and image decoding operations.
 //!
-//! # Integration Status
-//!
-//! Fully implemented and tested but currently NOT integrated into the main event loop.
-//! Integration pending - requires adding loader to CosmicBg state and polling results
-//! from the calloop event loop. See module docs for usage examples.
-
-#![allow(dead_code)]
+//! The loader is owned by `CosmicBg`; wallpapers queue work through a cloned
+//! [`LoaderHandle`] and results are dispatched back to them by [`loader_source`].
+//! Decoded images are kept in the shared [`ImageCache`].
 
 use image::DynamicImage;
+use sctk::reexports::calloop::{LoopHandle, channel};
 use std::{
-    path::PathBuf,
-    sync::mpsc,
+    collections::{HashMap, VecDeque},
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
     thread::{self, JoinHandle},
+    time::SystemTime,
+};
+
+use crate::{
+    CosmicBg,
+    cache::{CacheConfig, CacheKey, ImageCache},
+    error::WallpaperError,
+    media::{self, MediaKind},
+    scaler::ScaleSettings,
+    source::WallpaperSource,
+    theme::{self, ThemeMode},
 };
 
 /// Commands sent to the loader worker thread
@@ -28,12 +38,38 @@ pub enum LoaderCommand {
         output: String,
         path: PathBuf,
         recursive: bool,
+        filter_by_theme: bool,
     },
+    /// Change the theme mode used by `filter_by_theme` scans
+    SetTheme(ThemeMode),
     /// Decode a specific image file
     DecodeImage {
         output: String,
         path: PathBuf,
     },
+    /// Decode a slideshow entry, or open it for playback at `size` if it is
+    /// an animation or a video
+    LoadMedia {
+        output: String,
+        path: PathBuf,
+        size: Option<(u32, u32)>,
+    },
+    /// Decode an upcoming image and cache copies scaled to each of `sizes`
+    /// and adjusted
+    Prefetch {
+        output: String,
+        path: PathBuf,
+        sizes: Vec<(u32, u32)>,
+        settings: ScaleSettings,
+    },
+    /// Scale and adjust an image for a layer of `size`, reporting back once
+    /// the result is cached
+    Render {
+        output: String,
+        path: PathBuf,
+        size: (u32, u32),
+        settings: ScaleSettings,
+    },
     /// Shutdown the worker thread
     Shutdown,
 }
@@ -44,13 +80,29 @@ pub enum LoaderResult {
     /// Directory scan completed
     DirectoryScanned {
         output: String,
+        path: PathBuf,
+        paths: Vec<PathBuf>,
+    },
+    /// The images of a `filter_by_theme` scan were classified, leaving
+    /// `paths` suiting the theme
+    DirectoryRefined {
+        output: String,
+        path: PathBuf,
         paths: Vec<PathBuf>,
     },
     /// Image decoding completed
     ImageDecoded {
         output: String,
         path: PathBuf,
-        image: Box<DynamicImage>,
+        image: Arc<DynamicImage>,
+    },
+    /// A scaled image was cached under `key`
+    ImageRendered { output: String, key: CacheKey },
+    /// A slideshow animation or video is ready to play
+    MediaLoaded {
+        output: String,
+        path: PathBuf,
+        source: MediaSource,
     },
     /// Error occurred during loading
     LoadError {
@@ -60,13 +112,24 @@ pub enum LoaderResult {
     },
 }
 
+/// An animation or video opened by the loader thread
+pub struct MediaSource(pub Box<dyn WallpaperSource>);
+
+impl fmt::Debug for MediaSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MediaSource")
+            .field(&self.0.description())
+            .finish()
+    }
+}
+
 /// State tracking for async loading operations
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LoadingState {
     /// No loading in progress
     Idle,
     /// Scanning directory for images
-    ScanningDirectory,
+    ScanningDirectory(PathBuf),
     /// Loading/decoding an image
     LoadingImage(PathBuf),
     /// Loading completed successfully
@@ -81,46 +144,175 @@ impl Default for LoadingState {
     }
 }
 
+/// Average luminance of images classified by `filter_by_theme` scans, by
+/// path and modification time, or `None` for images that failed to decode
+type LuminanceMap = HashMap<(PathBuf, Option<SystemTime>), Option<f32>>;
+
+/// A `filter_by_theme` scan whose images are still being classified
+#[derive(Debug)]
+struct Classification {
+    output: String,
+    path: PathBuf,
+    theme: ThemeMode,
+    /// Every image in the directory
+    all: Vec<PathBuf>,
+    /// The images the scan reported so far
+    reported: Vec<PathBuf>,
+    /// Images left to classify
+    pending: Vec<PathBuf>,
+}
+
 /// Asynchronous image loader with worker thread
 ///
-/// # Implementation Status
-///
-/// This loader is **fully implemented and tested** with complete functionality for:
-/// - Directory scanning (recursive and non-recursive)
-/// - Image decoding (including JPEG XL support)
-/// - Background worker thread management
-/// - Clean shutdown handling
-///
-/// # Integration Status
-///
-/// **Currently NOT integrated into the main wallpaper loading path.**
-///
-/// The loader is used for background operations only. Integration into the main
-/// `calloop` event loop is pending. This will require:
-/// 1. Adding the loader to `CosmicBg` state
-/// 2. Integrating result polling into the event loop
-/// 3. Updating `wallpaper.rs` to use async loading instead of blocking calls
-///
-/// See tests at the end of this file for usage examples.
+/// Handles directory scanning (recursive and non-recursive) and image decoding
+/// (including JPEG XL) on a background thread, delivering each
+/// [`LoaderResult`] through a calloop channel.
+#[derive(Debug)]
 pub struct AsyncImageLoader {
     /// Sender for commands to worker thread
     command_tx: mpsc::Sender<LoaderCommand>,
-    /// Receiver for results from worker thread
-    result_rx: mpsc::Receiver<LoaderResult>,
+    /// Decoded and pre-scaled images shared with the worker thread
+    cache: Arc<ImageCache<CacheKey>>,
     /// Handle to the worker thread
     worker_handle: Option<JoinHandle<()>>,
 }
 
+/// Cloneable handle for queueing work on an [`AsyncImageLoader`].
+#[derive(Debug, Clone)]
+pub struct LoaderHandle {
+    command_tx: mpsc::Sender<LoaderCommand>,
+    cache: Arc<ImageCache<CacheKey>>,
+}
+
+impl LoaderHandle {
+    /// The image cache filled by the loader
+    pub fn cache(&self) -> &ImageCache<CacheKey> {
+        &self.cache
+    }
+
+    /// Request decoding of an upcoming image and scaling and adjusting it
+    /// for each of `sizes` ahead of time (async)
+    pub fn request_prefetch(
+        &self,
+        output: String,
+        path: PathBuf,
+        sizes: Vec<(u32, u32)>,
+        settings: ScaleSettings,
+    ) {
+        let _ = self.command_tx.send(LoaderCommand::Prefetch {
+            output,
+            path,
+            sizes,
+            settings,
+        });
+    }
+
+    /// Request scaling and adjusting an image for a layer of `size` (async)
+    pub fn request_render(
+        &self,
+        output: String,
+        path: PathBuf,
+        size: (u32, u32),
+        settings: ScaleSettings,
+    ) {
+        let _ = self.command_tx.send(LoaderCommand::Render {
+            output,
+            path,
+            size,
+            settings,
+        });
+    }
+
+    /// Request directory scanning (async)
+    ///
+    /// With `filter_by_theme`, only images matching the current theme mode
+    /// are returned.
+    pub fn request_scan_directory(
+        &self,
+        output: String,
+        path: PathBuf,
+        recursive: bool,
+        filter_by_theme: bool,
+    ) {
+        let _ = self.command_tx.send(LoaderCommand::ScanDirectory {
+            output,
+            path,
+            recursive,
+            filter_by_theme,
+        });
+    }
+
+    /// Request image decoding (async)
+    pub fn request_decode_image(&self, output: String, path: PathBuf) {
+        let _ = self.command_tx.send(LoaderCommand::DecodeImage { output, path });
+    }
+
+    /// Request decoding a slideshow entry, or opening it for playback at
+    /// `size` if it turns out to be an animation or a video (async)
+    pub fn request_load_media(&self, output: String, path: PathBuf, size: Option<(u32, u32)>) {
+        let _ = self
+            .command_tx
+            .send(LoaderCommand::LoadMedia { output, path, size });
+    }
+}
+
+/// Spawn the loader and insert its result channel into the event loop.
+///
+/// Results are routed to the wallpaper whose entry matches their `output`.
+pub fn loader_source(
+    handle: &LoopHandle<'static, CosmicBg>,
+    cache_config: CacheConfig,
+) -> Result<AsyncImageLoader, WallpaperError> {
+    let (result_tx, result_rx) = channel::channel();
+
+    handle
+        .insert_source(result_rx, |event, _, state| match event {
+            channel::Event::Msg(result) => {
+                let output = match &result {
+                    LoaderResult::DirectoryScanned { output, .. }
+                    | LoaderResult::DirectoryRefined { output, .. }
+                    | LoaderResult::ImageDecoded { output, .. }
+                    | LoaderResult::ImageRendered { output, .. }
+                    | LoaderResult::MediaLoaded { output, .. }
+                    | LoaderResult::LoadError { output, .. } => output.clone(),
+                };
+                let media = matches!(result, LoaderResult::MediaLoaded { .. });
+
+                if let Some(wallpaper) = state
+                    .wallpapers
+                    .iter_mut()
+                    .find(|w| w.entry.output == output)
+                {
+                    wallpaper.handle_loader_result(result);
+                }
+
+                // Start the animation or video that just became ready.
+                if media {
+                    state.schedule_animations();
+                }
+            }
+            channel::Event::Closed => {
+                tracing::warn!("image loader channel closed");
+            }
+        })
+        .map_err(|_| WallpaperError::EventLoopInsert {
+            source_type: "image loader",
+        })?;
+
+    Ok(AsyncImageLoader::new(result_tx, cache_config))
+}
+
 impl AsyncImageLoader {
     /// Create a new async image loader with background worker thread
-    pub fn new() -> Self {
+    pub fn new(result_tx: channel::Sender<LoaderResult>, cache_config: CacheConfig) -> Self {
         let (command_tx, command_rx) = mpsc::channel();
-        let (result_tx, result_rx) = mpsc::channel();
+        let cache = Arc::new(ImageCache::with_config(cache_config));
 
+        let worker_cache = Arc::clone(&cache);
         let worker_handle = thread::Builder::new()
             .name("cosmic-ext-bg-loader".to_string())
             .spawn(move || {
-                Self::worker_thread(command_rx, result_tx);
+                Self::worker_thread(command_rx, result_tx, &worker_cache);
             })
             .expect("Failed to spawn loader worker thread");
 
@@ -128,28 +320,166 @@ impl AsyncImageLoader {
 
         Self {
             command_tx,
-            result_rx,
+            cache,
             worker_handle: Some(worker_handle),
         }
     }
 
+    /// Get a handle for queueing work from wallpapers
+    pub fn handle(&self) -> LoaderHandle {
+        LoaderHandle {
+            command_tx: self.command_tx.clone(),
+            cache: Arc::clone(&self.cache),
+        }
+    }
+
+    /// The image cache filled by the loader
+    pub fn cache(&self) -> &ImageCache<CacheKey> {
+        &self.cache
+    }
+
+    /// Set the theme mode used by subsequent `filter_by_theme` scans
+    pub fn set_theme(&self, mode: ThemeMode) {
+        let _ = self.command_tx.send(LoaderCommand::SetTheme(mode));
+    }
+
     /// Worker thread main loop
     fn worker_thread(
         command_rx: mpsc::Receiver<LoaderCommand>,
-        result_tx: mpsc::Sender<LoaderResult>,
+        result_tx: channel::Sender<LoaderResult>,
+        cache: &ImageCache<CacheKey>,
     ) {
         tracing::debug!("Loader worker thread started");
 
-        while let Ok(command) = command_rx.recv() {
+        let mut theme = ThemeMode::default();
+        let mut luminance = LuminanceMap::new();
+        let mut classifying: VecDeque<Classification> = VecDeque::new();
+
+        loop {
+            // Images are classified one at a time while no commands wait, so
+            // decodes for the wallpapers on screen aren't held up by a
+            // whole directory.
+            let command = if let Some(scan) = classifying.front_mut() {
+                match command_rx.try_recv() {
+                    Ok(command) => command,
+                    Err(mpsc::TryRecvError::Empty) => {
+                        if let Some(result) = Self::classify_next(scan, &mut luminance) {
+                            let _ = result_tx.send(result);
+                        }
+                        if scan.pending.is_empty() {
+                            classifying.pop_front();
+                        }
+                        continue;
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => break,
+                }
+            } else {
+                match command_rx.recv() {
+                    Ok(command) => command,
+                    Err(_) => break,
+                }
+            };
+
             match command {
-                LoaderCommand::ScanDirectory { output, path, recursive } => {
+                LoaderCommand::ScanDirectory {
+                    output,
+                    path,
+                    recursive,
+                    filter_by_theme,
+                } => {
                     tracing::trace!(output = %output, path = ?path, "Scanning directory");
-                    let result = Self::scan_directory(&output, &path, recursive);
+                    // A new scan replaces the one the output had.
+                    classifying.retain(|scan| scan.output != output);
+
+                    let result = if filter_by_theme {
+                        let (result, scan) =
+                            Self::scan_themed_directory(&output, &path, recursive, theme, &luminance);
+                        classifying.extend(scan);
+                        result
+                    } else {
+                        Self::scan_directory(&output, &path, recursive)
+                    };
                     let _ = result_tx.send(result);
                 }
+                LoaderCommand::SetTheme(mode) => {
+                    tracing::debug!(?mode, "Theme mode changed");
+                    theme = mode;
+                    // Themed slideshows are scanned again for the new mode.
+                    classifying.clear();
+                }
                 LoaderCommand::DecodeImage { output, path } => {
                     tracing::trace!(output = %output, path = ?path, "Decoding image");
-                    let result = Self::decode_image(&output, &path);
+                    let _ = result_tx.send(Self::decode_result(cache, output, path));
+                }
+                LoaderCommand::LoadMedia { output, path, size } => {
+                    tracing::trace!(output = %output, path = ?path, "Loading media");
+                    let result = match media::open(&path, size) {
+                        Ok(Some(source)) => LoaderResult::MediaLoaded {
+                            output,
+                            path,
+                            source: MediaSource(source),
+                        },
+                        Ok(None) => Self::decode_result(cache, output, path),
+                        Err(why) => LoaderResult::LoadError {
+                            output,
+                            path: Some(path),
+                            error: why.to_string(),
+                        },
+                    };
+                    let _ = result_tx.send(result);
+                }
+                LoaderCommand::Prefetch {
+                    output,
+                    path,
+                    sizes,
+                    settings,
+                } => {
+                    // Animations and videos are opened once rotation reaches them.
+                    if MediaKind::of(&path) != MediaKind::Still {
+                        continue;
+                    }
+
+                    tracing::trace!(output = %output, path = ?path, "Prefetching image");
+                    let image = match Self::cached_original(cache, &path) {
+                        Ok(image) => image,
+                        Err(error) => {
+                            tracing::debug!(output = %output, path = ?path, error, "Prefetch failed");
+                            continue;
+                        }
+                    };
+
+                    let fingerprint = settings.fingerprint();
+                    for size in sizes {
+                        let key = CacheKey::scaled(&path, size, fingerprint);
+                        if !cache.contains(&key) {
+                            cache.insert(key, settings.apply(&path, &image, size.0, size.1));
+                        }
+                    }
+                }
+                LoaderCommand::Render {
+                    output,
+                    path,
+                    size,
+                    settings,
+                } => {
+                    tracing::trace!(output = %output, path = ?path, ?size, "Rendering image");
+                    let key = CacheKey::scaled(&path, size, settings.fingerprint());
+                    let result = if cache.contains(&key) {
+                        LoaderResult::ImageRendered { output, key }
+                    } else {
+                        match Self::cached_original(cache, &path) {
+                            Ok(image) => {
+                                let scaled = settings.apply(&path, &image, size.0, size.1);
+                                cache.insert(key.clone(), scaled);
+                                LoaderResult::ImageRendered { output, key }
+                            }
+                            Err(error) => LoaderResult::LoadError {
+                                output,
+                                path: Some(path),
+                                error,
+                            },
+                        }
+                    };
                     let _ = result_tx.send(result);
                 }
                 LoaderCommand::Shutdown => {
@@ -160,8 +490,160 @@ impl AsyncImageLoader {
         }
     }
 
+    /// Decode an image, reporting the outcome to the wallpaper of `output`
+    fn decode_result(cache: &ImageCache<CacheKey>, output: String, path: PathBuf) -> LoaderResult {
+        match Self::cached_original(cache, &path) {
+            Ok(image) => LoaderResult::ImageDecoded {
+                output,
+                path,
+                image,
+            },
+            Err(error) => LoaderResult::LoadError {
+                output,
+                path: Some(path),
+                error,
+            },
+        }
+    }
+
     /// Scan a directory for image files
     fn scan_directory(output: &str, path: &PathBuf, recursive: bool) -> LoaderResult {
+        let paths = Self::collect_images(path, recursive);
+
+        tracing::debug!(
+            output = %output,
+            count = paths.len(),
+            "Directory scan complete"
+        );
+
+        LoaderResult::DirectoryScanned {
+            output: output.to_string(),
+            path: path.clone(),
+            paths,
+        }
+    }
+
+    /// Scan a directory for image files suiting the theme mode
+    ///
+    /// Uses the `light/` or `dark/` subdirectory when present, otherwise
+    /// classifies every image by its average luminance. Images not yet
+    /// classified, and videos, count as suiting both modes, so a directory
+    /// seen for the first time is reported whole, along with the
+    /// classification left to refine it.
+    fn scan_themed_directory(
+        output: &str,
+        path: &Path,
+        recursive: bool,
+        theme: ThemeMode,
+        luminance: &LuminanceMap,
+    ) -> (LoaderResult, Option<Classification>) {
+        let subdir = path.join(theme.subdir());
+
+        let (paths, classification) = if subdir.is_dir() {
+            (Self::collect_images(&subdir, recursive), None)
+        } else {
+            let all = Self::collect_images(path, recursive);
+            let paths = Self::suiting_images(&all, theme, luminance);
+            let pending: Vec<PathBuf> = all
+                .iter()
+                .filter(|image| {
+                    !media::is_video_file(image)
+                        && !luminance.contains_key(&Self::luminance_key(image))
+                })
+                .cloned()
+                .collect();
+
+            let classification = (!pending.is_empty()).then(|| Classification {
+                output: output.to_string(),
+                path: path.to_path_buf(),
+                theme,
+                all,
+                reported: paths.clone(),
+                pending,
+            });
+            (paths, classification)
+        };
+
+        tracing::debug!(
+            output = %output,
+            ?theme,
+            count = paths.len(),
+            unclassified = classification.as_ref().map_or(0, |scan| scan.pending.len()),
+            "Themed directory scan complete"
+        );
+
+        let result = LoaderResult::DirectoryScanned {
+            output: output.to_string(),
+            path: path.to_path_buf(),
+            paths,
+        };
+
+        (result, classification)
+    }
+
+    /// Classify the next image of a themed scan, reporting the images that
+    /// suit its theme once the last one is done, if they changed
+    fn classify_next(scan: &mut Classification, luminance: &mut LuminanceMap) -> Option<LoaderResult> {
+        let image = scan.pending.pop()?;
+        let key = Self::luminance_key(&image);
+        if !luminance.contains_key(&key) {
+            luminance.insert(key, Self::image_luminance(&image));
+        }
+
+        if !scan.pending.is_empty() {
+            return None;
+        }
+
+        let paths = Self::suiting_images(&scan.all, scan.theme, luminance);
+        if paths == scan.reported {
+            return None;
+        }
+
+        tracing::debug!(
+            output = %scan.output,
+            theme = ?scan.theme,
+            count = paths.len(),
+            "Themed directory classified"
+        );
+
+        Some(LoaderResult::DirectoryRefined {
+            output: scan.output.clone(),
+            path: scan.path.clone(),
+            paths,
+        })
+    }
+
+    /// The images suiting the theme mode, counting those not classified for
+    /// both modes, or all of them when none suit it
+    fn suiting_images(all: &[PathBuf], theme: ThemeMode, luminance: &LuminanceMap) -> Vec<PathBuf> {
+        let suiting: Vec<PathBuf> = all
+            .iter()
+            .filter(|image| match luminance.get(&Self::luminance_key(image)) {
+                Some(Some(value)) => theme.suits_luminance(*value),
+                _ => true,
+            })
+            .cloned()
+            .collect();
+
+        if suiting.is_empty() {
+            all.to_vec()
+        } else {
+            suiting
+        }
+    }
+
+    /// Key of an image in the luminance map, so a replaced file is
+    /// classified again
+    fn luminance_key(path: &Path) -> (PathBuf, Option<SystemTime>) {
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        (path.to_path_buf(), modified)
+    }
+
+    /// Collect the image files in a directory
+    fn collect_images(path: &Path, recursive: bool) -> Vec<PathBuf> {
         use walkdir::WalkDir;
 
         let walker = if recursive {
@@ -178,118 +660,111 @@ impl AsyncImageLoader {
             }
         }
 
-        tracing::debug!(
-            output = %output,
-            count = paths.len(),
-            "Directory scan complete"
-        );
+        paths
+    }
 
-        LoaderResult::DirectoryScanned {
-            output: output.to_string(),
-            paths,
+    /// Average luminance of an image, from a reduced decode where the format
+    /// allows one
+    ///
+    /// Images are decoded outside of the image cache so that classifying a
+    /// large directory does not evict the images on screen.
+    fn image_luminance(path: &Path) -> Option<f32> {
+        let image = if crate::svg::is_svg(path) {
+            crate::svg::thumbnail(path, theme::LUMINANCE_SAMPLE_SIZE)
+                .map_err(|e| format!("SVG error: {}", e))
+        } else {
+            let size = theme::LUMINANCE_SAMPLE_SIZE as u16;
+            crate::decode::jpeg_preview(path, size)
+                .map_or_else(|| Self::decode_image(path), Ok)
+        };
+
+        match image {
+            Ok(image) => Some(theme::mean_luminance(&image)),
+            Err(error) => {
+                tracing::debug!(path = ?path, error, "Failed to classify image");
+                None
+            }
         }
     }
 
     /// Check if a path is a supported image file
+    ///
+    /// Videos count too, so they can take a slot in a slideshow.
     fn is_image_file(path: &std::path::Path) -> bool {
+        #[cfg(feature = "heif")]
+        if crate::heif::is_heif(path) {
+            return true;
+        }
+
+        if media::is_video_file(path) {
+            return true;
+        }
+
         path.extension()
             .and_then(|e| e.to_str())
             .map(|s| {
                 matches!(
                     s.to_lowercase().as_str(),
-                    "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "tiff" | "jxl"
+                    "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "tiff" | "jxl" | "svg"
+                        | "svgz"
                 )
             })
             .unwrap_or(false)
     }
 
-    /// Create a load error result
-    fn load_error(output: &str, path: &PathBuf, msg: String) -> LoaderResult {
-        LoaderResult::LoadError {
-            output: output.to_string(),
-            path: Some(path.clone()),
-            error: msg,
-        }
+    /// Get the decoded image from the cache, decoding and caching it on a miss
+    fn cached_original(
+        cache: &ImageCache<CacheKey>,
+        path: &Path,
+    ) -> Result<Arc<DynamicImage>, String> {
+        cache.get_or_insert(&CacheKey::original(path), || Self::decode_image(path))
     }
 
     /// Decode an image file
-    fn decode_image(output: &str, path: &PathBuf) -> LoaderResult {
+    fn decode_image(path: &Path) -> Result<DynamicImage, String> {
         // Handle JPEG XL specially
         if let Some(ext) = path.extension() {
             if ext == "jxl" {
-                return Self::decode_jxl(output, path);
+                return Self::decode_jxl(path);
             }
         }
 
-        // Standard image formats
-        match image::ImageReader::open(path) {
-            Ok(reader) => match reader.with_guessed_format() {
-                Ok(reader) => match reader.decode() {
-                    Ok(image) => LoaderResult::ImageDecoded {
-                        output: output.to_string(),
-                        path: path.clone(),
-                        image: Box::new(image),
-                    },
-                    Err(e) => Self::load_error(output, path, format!("Decode error: {}", e)),
-                },
-                Err(e) => Self::load_error(output, path, format!("Format detection error: {}", e)),
-            },
-            Err(e) => Self::load_error(output, path, format!("Open error: {}", e)),
+        if crate::svg::is_svg(path) {
+            return crate::svg::decode(path).map_err(|e| format!("SVG error: {}", e));
         }
-    }
 
-    /// Decode JPEG XL image
-    fn decode_jxl(output: &str, path: &PathBuf) -> LoaderResult {
-        use jxl_oxide::integration::JxlDecoder;
-        use std::fs::File;
-
-        let file = match File::open(path) {
-            Ok(f) => f,
-            Err(e) => return Self::load_error(output, path, format!("Failed to open JXL file: {}", e)),
-        };
+        #[cfg(feature = "heif")]
+        if crate::heif::is_heif(path) {
+            return crate::heif::decode(path).map_err(|e| format!("HEIF error: {}", e));
+        }
 
-        let decoder = match JxlDecoder::new(file) {
-            Ok(d) => d,
-            Err(e) => return Self::load_error(output, path, format!("Failed to create JXL decoder: {}", e)),
-        };
+        // Videos stand in with their first frame on outputs with distinct images.
+        if media::is_video_file(path) {
+            let mut frames =
+                crate::video::ClipFrames::open(path).map_err(|e| format!("Video error: {}", e))?;
 
-        match DynamicImage::from_decoder(decoder) {
-            Ok(image) => LoaderResult::ImageDecoded {
-                output: output.to_string(),
-                path: path.clone(),
-                image: Box::new(image),
-            },
-            Err(e) => Self::load_error(output, path, format!("JXL decode error: {}", e)),
+            return match frames.next() {
+                Some(Ok((frame, _))) => Ok(frame),
+                Some(Err(e)) => Err(format!("Video error: {}", e)),
+                None => Err("Video has no frames".to_string()),
+            };
         }
-    }
 
-    /// Request directory scanning (async)
-    pub fn request_scan_directory(&self, output: String, path: PathBuf, recursive: bool) {
-        let _ = self.command_tx.send(LoaderCommand::ScanDirectory {
-            output,
-            path,
-            recursive,
-        });
+        // Standard image formats
+        crate::decode::open(path).map_err(|e| format!("Decode error: {}", e))
     }
 
-    /// Request image decoding (async)
-    pub fn request_decode_image(&self, output: String, path: PathBuf) {
-        let _ = self.command_tx.send(LoaderCommand::DecodeImage { output, path });
-    }
+    /// Decode JPEG XL image
+    fn decode_jxl(path: &Path) -> Result<DynamicImage, String> {
+        use jxl_oxide::integration::JxlDecoder;
+        use std::fs::File;
 
-    /// Poll for completed results (non-blocking)
-    pub fn poll_results(&self) -> Vec<LoaderResult> {
-        let mut results = Vec::new();
-        while let Ok(result) = self.result_rx.try_recv() {
-            results.push(result);
-        }
-        results
-    }
-}
+        let file = File::open(path).map_err(|e| format!("Failed to open JXL file: {}", e))?;
 
-impl Default for AsyncImageLoader {
-    fn default() -> Self {
-        Self::new()
+        let decoder =
+            JxlDecoder::new(file).map_err(|e| format!("Failed to create JXL decoder: {}", e))?;
+
+        crate::decode::from_decoder(decoder).map_err(|e| format!("JXL decode error: {}", e))
     }
 }
 
@@ -310,6 +785,7 @@ impl Drop for AsyncImageLoader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cosmic_ext_bg_config::ScalingMode;
     use std::time::Duration;
 
     #[test]
@@ -323,21 +799,205 @@ mod tests {
         assert!(AsyncImageLoader::is_image_file(std::path::Path::new("test.jpg")));
         assert!(AsyncImageLoader::is_image_file(std::path::Path::new("test.PNG")));
         assert!(AsyncImageLoader::is_image_file(std::path::Path::new("test.jxl")));
+        assert!(AsyncImageLoader::is_image_file(std::path::Path::new("test.svgz")));
+        assert!(AsyncImageLoader::is_image_file(std::path::Path::new("test.mp4")));
         assert!(!AsyncImageLoader::is_image_file(std::path::Path::new("test.txt")));
         assert!(!AsyncImageLoader::is_image_file(std::path::Path::new("test")));
     }
 
     #[test]
     fn test_loader_creation_and_shutdown() {
-        let loader = AsyncImageLoader::new();
+        let (tx, _rx) = channel::channel();
+        let loader = AsyncImageLoader::new(tx, CacheConfig::default());
         // Loader should shut down cleanly on drop
         drop(loader);
     }
 
     #[test]
     fn test_poll_empty_results() {
-        let loader = AsyncImageLoader::new();
-        let results = loader.poll_results();
-        assert!(results.is_empty());
+        let (tx, rx) = channel::channel();
+        let _loader = AsyncImageLoader::new(tx, CacheConfig::default());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_scan_directory_result() {
+        let dir = std::env::temp_dir().join(format!("cosmic-bg-loader-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.png"), b"").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        let (tx, rx) = channel::channel();
+        let loader = AsyncImageLoader::new(tx, CacheConfig::default());
+        loader
+            .handle()
+            .request_scan_directory("DP-1".into(), dir.clone(), false, false);
+
+        let result = recv_result(&rx);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        match result.expect("no scan result within 5s") {
+            LoaderResult::DirectoryScanned { output, path, paths } => {
+                assert_eq!(output, "DP-1");
+                assert_eq!(path, dir);
+                assert_eq!(paths, vec![dir.join("a.png")]);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_scan_directory_by_theme() {
+        let dir = std::env::temp_dir().join(format!("cosmic-bg-theme-{}", std::process::id()));
+        let solid = |name: &str, value: u8| {
+            let path = dir.join(name);
+            image::RgbImage::from_pixel(4, 4, image::Rgb([value; 3]))
+                .save(&path)
+                .unwrap();
+            path
+        };
+        let sorted = |mut paths: Vec<PathBuf>| {
+            paths.sort();
+            paths
+        };
+        std::fs::create_dir_all(&dir).unwrap();
+        let night = solid("night.png", 20);
+        let day = solid("day.png", 230);
+        let clip = dir.join("clip.mp4");
+        std::fs::write(&clip, b"").unwrap();
+
+        let (tx, rx) = channel::channel();
+        let loader = AsyncImageLoader::new(tx, CacheConfig::default());
+        let handle = loader.handle();
+
+        // Unclassified images are reported at once, then refined.
+        loader.set_theme(ThemeMode::Light);
+        handle.request_scan_directory("DP-1".into(), dir.clone(), false, true);
+        let unclassified = recv_result(&rx);
+        let light = recv_result(&rx);
+
+        // A replaced image is classified again.
+        solid("day.png", 20);
+        std::fs::File::options()
+            .write(true)
+            .open(&day)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        handle.request_scan_directory("DP-1".into(), dir.clone(), false, true);
+        let replaced = recv_result(&rx);
+        let reclassified = recv_result(&rx);
+
+        // A `dark/` subdirectory takes precedence over luminance.
+        std::fs::create_dir_all(dir.join("dark")).unwrap();
+        let dark_only = solid("dark/stars.png", 230);
+        loader.set_theme(ThemeMode::Dark);
+        handle.request_scan_directory("DP-1".into(), dir.clone(), false, true);
+        let dark = recv_result(&rx);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        match unclassified.expect("no light scan result within 5s") {
+            LoaderResult::DirectoryScanned { paths, .. } => {
+                assert_eq!(sorted(paths), sorted(vec![night.clone(), day.clone(), clip.clone()]));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        // Videos aren't classified, so they suit both modes.
+        match light.expect("no light classification within 5s") {
+            LoaderResult::DirectoryRefined { path, paths, .. } => {
+                assert_eq!(path, dir);
+                assert_eq!(sorted(paths), sorted(vec![day.clone(), clip.clone()]));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        match replaced.expect("no rescan result within 5s") {
+            LoaderResult::DirectoryScanned { paths, .. } => {
+                assert_eq!(sorted(paths), sorted(vec![day, clip.clone()]));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        match reclassified.expect("no reclassification within 5s") {
+            LoaderResult::DirectoryRefined { paths, .. } => assert_eq!(paths, vec![clip]),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        match dark.expect("no dark scan result within 5s") {
+            LoaderResult::DirectoryScanned { path, paths, .. } => {
+                assert_eq!(path, dir);
+                assert_eq!(paths, vec![dark_only]);
+                assert!(!paths.contains(&night));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_prefetch_fills_cache() {
+        let path = std::env::temp_dir().join(format!("cosmic-bg-prefetch-{}.png", std::process::id()));
+        image::RgbaImage::from_pixel(8, 4, image::Rgba([0, 128, 255, 255]))
+            .save(&path)
+            .unwrap();
+
+        let (tx, _rx) = channel::channel();
+        let loader = AsyncImageLoader::new(tx, CacheConfig::default());
+        let settings = ScaleSettings {
+            mode: ScalingMode::Stretch,
+            ..Default::default()
+        };
+        let fingerprint = settings.fingerprint();
+        loader
+            .handle()
+            .request_prefetch("all".into(), path.clone(), vec![(4, 2)], settings);
+
+        // The worker handles commands in order, so the prefetch is done on drop.
+        let cache = Arc::clone(&loader.cache);
+        drop(loader);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(cache.get(&CacheKey::original(&path)).unwrap().width(), 8);
+        let scaled = cache.get(&CacheKey::scaled(&path, (4, 2), fingerprint)).unwrap();
+        assert_eq!((scaled.width(), scaled.height()), (4, 2));
+    }
+
+    #[test]
+    fn test_render_reports_back() {
+        let path = std::env::temp_dir().join(format!("cosmic-bg-render-{}.png", std::process::id()));
+        image::RgbaImage::from_pixel(8, 4, image::Rgba([0, 128, 255, 255]))
+            .save(&path)
+            .unwrap();
+
+        let (tx, rx) = channel::channel();
+        let loader = AsyncImageLoader::new(tx, CacheConfig::default());
+        let settings = ScaleSettings::default();
+        let expected = CacheKey::scaled(&path, (4, 2), settings.fingerprint());
+        loader
+            .handle()
+            .request_render("DP-1".into(), path.clone(), (4, 2), settings);
+
+        let result = recv_result(&rx);
+        std::fs::remove_file(&path).unwrap();
+
+        match result.expect("no render result within 5s") {
+            LoaderResult::ImageRendered { output, key } => {
+                assert_eq!(output, "DP-1");
+                assert_eq!(key, expected);
+                assert_eq!(loader.cache().get(&key).unwrap().width(), 4);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    /// Wait up to five seconds for the next loader result
+    fn recv_result(rx: &channel::Channel<LoaderResult>) -> Option<LoaderResult> {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            match rx.try_recv() {
+                Ok(result) => return Some(result),
+                Err(_) if std::time::Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(_) => return None,
+            }
+        }
     }
 }
@@ -1,16 +1,31 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod adjust;
 mod animated;
 mod cache;
 mod colored;
+mod decode;
 mod draw;
 mod error;
+mod gnome_xml;
+#[cfg(feature = "heif")]
+mod heif;
+mod icc;
 mod img_source;
+mod ipc;
+mod ken_burns;
 mod loader;
+mod media;
 mod scaler;
+mod schedule;
 mod scheduler;
 mod shader;
+mod solar;
 mod source;
+mod span;
+mod svg;
+mod theme;
+mod transition;
 mod video;
 mod wallpaper;
 
@@ -45,7 +60,7 @@ mod malloc {
     }
 }
 
-use cosmic_ext_bg_config::{Config, state::State};
+use cosmic_ext_bg_config::{Config, Source, state::State};
 use cosmic_config::{CosmicConfigEntry, calloop::ConfigWatchSource};
 use eyre::Context;
 use sctk::{
@@ -53,14 +68,17 @@ use sctk::{
     delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm,
     output::{OutputHandler, OutputInfo, OutputState},
     reexports::{
-        calloop,
+        calloop::{
+            self,
+            timer::{TimeoutAction, Timer},
+        },
         calloop_wayland_source::WaylandSource,
         client::{
             Connection, Dispatch, Proxy, QueueHandle, Weak, delegate_noop,
             globals::registry_queue_init,
             protocol::{
                 wl_output::{self, WlOutput},
-                wl_surface,
+                wl_shm, wl_surface,
             },
         },
         protocols::wp::{
@@ -82,6 +100,8 @@ use sctk::{
     shm::{Shm, ShmHandler, slot::SlotPool},
 };
 
+use scheduler::FrameScheduler;
+use std::time::Instant;
 use tracing::error;
 use tracing_subscriber::prelude::*;
 use wallpaper::Wallpaper;
@@ -97,9 +117,15 @@ pub struct CosmicBgLayer {
     output_info: OutputInfo,
     pool: Option<SlotPool>,
     needs_redraw: bool,
+    // A frame callback was requested and the compositor has not signalled it yet
+    frame_pending: bool,
+    // An animation frame came due while `frame_pending` was set
+    frame_deferred: bool,
     size: Option<(u32, u32)>,
     fractional_scale: Option<u32>,
     transform: wl_output::Transform,
+    // The compositor accepts 10-bit `Xrgb2101010` buffers
+    ten_bit: bool,
 }
 
 /// Helper function to determine if a transform represents a 90° or 270° rotation
@@ -201,6 +227,15 @@ fn main() -> color_eyre::Result<()> {
                                 changes_applied = true;
                             }
 
+                            cosmic_ext_bg_config::CACHE => {
+                                tracing::debug!("updating cache limits");
+                                state.config.cache = conf_context.cache();
+                                // Start over rather than keep images cached
+                                // under the old limits.
+                                state.loader.cache().clear();
+                                state.loader.cache().set_config(state.config.cache.clone());
+                            }
+
                             _ => {
                                 tracing::debug!(key, "key modified");
                                 if let Some(output) = key.strip_prefix("output.") {
@@ -244,6 +279,16 @@ fn main() -> color_eyre::Result<()> {
     };
 
     let source_tx = img_source::img_source(&event_loop.handle());
+    let loader = loader::loader_source(&event_loop.handle(), config.cache.clone())?;
+    loader.set_theme(theme::theme_source(&event_loop.handle()));
+
+    let control_socket = match ipc::ipc_source(&event_loop.handle()) {
+        Ok(()) => true,
+        Err(why) => {
+            tracing::error!(?why, "failed to start control socket");
+            false
+        }
+    };
 
     // initial setup with all images
     let wallpapers = {
@@ -256,6 +301,7 @@ fn main() -> color_eyre::Result<()> {
                     qh.clone(),
                     event_loop.handle(),
                     source_tx.clone(),
+                    loader.handle(),
                 )
             })
         });
@@ -267,6 +313,7 @@ fn main() -> color_eyre::Result<()> {
             qh.clone(),
             event_loop.handle(),
             source_tx.clone(),
+            loader.handle(),
         ));
 
         wallpapers
@@ -282,13 +329,18 @@ fn main() -> color_eyre::Result<()> {
         fractional_scale_manager: globals.bind(&qh, 1..=1, ()).ok(),
         qh,
         source_tx,
+        loader,
         loop_handle: event_loop.handle(),
+        frame_scheduler: FrameScheduler::new(),
+        frame_timer: None,
         exit: false,
         wallpapers,
         config,
         active_outputs: Vec::new(),
     };
 
+    bg_state.schedule_animations();
+
     loop {
         event_loop.dispatch(None, &mut bg_state)?;
 
@@ -297,6 +349,10 @@ fn main() -> color_eyre::Result<()> {
         }
     }
 
+    if control_socket {
+        let _ = std::fs::remove_file(cosmic_ext_bg_config::ipc::socket_path());
+    }
+
     Ok(())
 }
 
@@ -311,7 +367,11 @@ pub struct CosmicBg {
     fractional_scale_manager: Option<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>,
     qh: QueueHandle<CosmicBg>,
     source_tx: calloop::channel::SyncSender<(String, notify::Event)>,
+    loader: loader::AsyncImageLoader,
     loop_handle: calloop::LoopHandle<'static, CosmicBg>,
+    frame_scheduler: FrameScheduler,
+    // The shared animation timer and the deadline it is armed for
+    frame_timer: Option<(calloop::RegistrationToken, Instant)>,
     exit: bool,
     wallpapers: Vec<Wallpaper>,
     config: Config,
@@ -319,51 +379,214 @@ pub struct CosmicBg {
 }
 
 impl CosmicBg {
+    /// Reconcile the wallpapers with the current config.
+    ///
+    /// Wallpapers are matched to entries by output name and updated in place,
+    /// and layer surfaces are handed over to whichever wallpaper now owns their
+    /// output, so editing one output leaves the others untouched.
     fn apply_backgrounds(&mut self) {
-        self.wallpapers.clear();
-
-        let mut all_wallpaper = Wallpaper::new(
-            self.config.default_background.clone(),
-            self.qh.clone(),
-            self.loop_handle.clone(),
-            self.source_tx.clone(),
-        );
+        let mut old_wallpapers = std::mem::take(&mut self.wallpapers);
 
         let mut backgrounds = self.config.backgrounds.clone();
         backgrounds.sort_by(|a, b| a.output.cmp(&b.output));
 
-        'outer: for output in &self.active_outputs {
-            let Some(output_info) = self.output_state.info(output) else {
-                continue;
+        let outputs: Vec<(WlOutput, OutputInfo)> = self
+            .active_outputs
+            .iter()
+            .filter_map(|output| Some((output.clone(), self.output_state.info(output)?)))
+            .collect();
+
+        // Name of the entry each output should show.
+        let owner = |info: &OutputInfo| -> String {
+            let o_name = info.name.clone().unwrap_or_default();
+            if backgrounds.iter().any(|bg| bg.output == o_name) {
+                o_name
+            } else {
+                String::from("all")
+            }
+        };
+
+        let entries = backgrounds
+            .iter()
+            .filter(|bg| outputs.iter().any(|(_, info)| owner(info) == bg.output))
+            .chain(std::iter::once(&self.config.default_background));
+
+        let mut wallpapers = Vec::with_capacity(backgrounds.len() + 1);
+        for entry in entries {
+            let wallpaper = match old_wallpapers
+                .iter()
+                .position(|w| w.entry.output == entry.output)
+            {
+                Some(pos) => {
+                    let mut wallpaper = old_wallpapers.swap_remove(pos);
+                    wallpaper.update_config(entry.clone(), self.source_tx.clone());
+                    wallpaper
+                }
+                None => Wallpaper::new(
+                    entry.clone(),
+                    self.qh.clone(),
+                    self.loop_handle.clone(),
+                    self.source_tx.clone(),
+                    self.loader.handle(),
+                ),
             };
+            wallpapers.push(wallpaper);
+        }
 
-            let o_name = output_info.name.clone().unwrap_or_default();
-            for background in &backgrounds {
-                if background.output == o_name {
-                    let mut new_wallpaper = Wallpaper::new(
-                        background.clone(),
-                        self.qh.clone(),
-                        self.loop_handle.clone(),
-                        self.source_tx.clone(),
-                    );
+        // Detach layers whose output now belongs to another wallpaper.
+        let mut spare_layers: Vec<CosmicBgLayer> = old_wallpapers
+            .iter_mut()
+            .flat_map(|w| w.layers.drain(..))
+            .collect();
+        for wallpaper in &mut wallpapers {
+            let (keep, moved): (Vec<_>, Vec<_>) =
+                wallpaper.layers.drain(..).partition(|layer| {
+                    outputs.iter().any(|(output, info)| {
+                        *output == layer.wl_output && owner(info) == wallpaper.entry.output
+                    })
+                });
+            wallpaper.layers = keep;
+            spare_layers.extend(moved);
+        }
+        for wallpaper in &old_wallpapers {
+            self.frame_scheduler.remove_output(&wallpaper.entry.output);
+        }
+        drop(old_wallpapers);
 
-                    new_wallpaper
-                        .layers
-                        .push(self.new_layer(output.clone(), output_info));
-                    _ = new_wallpaper.save_state();
-                    self.wallpapers.push(new_wallpaper);
+        let mut changed = vec![false; wallpapers.len()];
+        for (output, output_info) in outputs {
+            let name = owner(&output_info);
+            let Some(idx) = wallpapers.iter().position(|w| w.entry.output == name) else {
+                continue;
+            };
+
+            if wallpapers[idx].layers.iter().any(|l| l.wl_output == output) {
+                continue;
+            }
 
-                    continue 'outer;
+            let layer = match spare_layers.iter().position(|l| l.wl_output == output) {
+                Some(pos) => {
+                    let mut layer = spare_layers.swap_remove(pos);
+                    layer.needs_redraw = true;
+                    layer
                 }
+                None => self.new_layer(output, output_info),
+            };
+
+            wallpapers[idx].layers.push(layer);
+            changed[idx] = true;
+        }
+
+        for (wallpaper, changed) in wallpapers.iter_mut().zip(changed) {
+            if changed {
+                _ = wallpaper.save_state();
+                wallpaper.draw();
             }
+        }
 
-            all_wallpaper
-                .layers
-                .push(self.new_layer(output.clone(), output_info));
+        self.wallpapers = wallpapers;
+        self.schedule_animations();
+    }
+
+    /// Reload the slideshows filtered by theme after a light / dark switch.
+    pub fn set_theme(&mut self, mode: theme::ThemeMode) {
+        self.loader.set_theme(mode);
+
+        for wallpaper in &mut self.wallpapers {
+            if wallpaper.entry.filter_by_theme
+                && matches!(wallpaper.entry.source, Source::Path(_))
+            {
+                wallpaper.load_images();
+            }
+        }
+    }
+
+    /// Schedule a frame for every animating wallpaper that has none pending.
+    ///
+    /// Frames are rescheduled as they are drawn, so this only needs calling
+    /// when animations start or resume.
+    pub fn schedule_animations(&mut self) {
+        for wallpaper in &self.wallpapers {
+            let Some(frame_duration) = wallpaper.animation_frame_duration() else {
+                continue;
+            };
+
+            if !self.frame_scheduler.is_scheduled(&wallpaper.entry.output) {
+                self.frame_scheduler
+                    .schedule(wallpaper.entry.output.clone(), frame_duration);
+            }
         }
 
-        _ = all_wallpaper.save_state();
-        self.wallpapers.push(all_wallpaper);
+        self.arm_frame_timer();
+    }
+
+    /// Make sure the shared frame timer fires by the earliest deadline.
+    fn arm_frame_timer(&mut self) {
+        let Some(deadline) = self.frame_scheduler.next_deadline_instant() else {
+            return;
+        };
+
+        if let Some((token, armed)) = self.frame_timer {
+            if armed <= deadline {
+                return;
+            }
+            self.loop_handle.remove(token);
+        }
+
+        self.frame_timer = self
+            .loop_handle
+            .insert_source(Timer::from_deadline(deadline), |_, _, state| {
+                state.dispatch_frames()
+            })
+            .map(|token| (token, deadline))
+            .map_err(|why| tracing::error!(?why, "failed to insert frame timer"))
+            .ok();
+    }
+
+    /// Draw every animation frame that is due and re-arm for the next one.
+    fn dispatch_frames(&mut self) -> TimeoutAction {
+        let now = Instant::now();
+
+        let ready = self
+            .frame_scheduler
+            .pop_ready_within(scheduler::COALESCE_WINDOW);
+
+        for (output, deadline) in ready {
+            let Some(wallpaper) = self.wallpapers.iter_mut().find(|w| w.entry.output == output)
+            else {
+                continue; // Wallpaper was removed
+            };
+
+            wallpaper.draw_animation_frame();
+
+            // Paused or no longer animated wallpapers drop out of the schedule,
+            // as do ones where every layer waits on a frame callback; `frame`
+            // reschedules those once the compositor is ready.
+            let Some(frame_duration) = wallpaper.animation_frame_duration() else {
+                continue;
+            };
+            if wallpaper.awaiting_frame_callbacks() {
+                continue;
+            }
+
+            // Keep the cadence of the previous deadline unless we fell behind.
+            let next = deadline + frame_duration;
+            let next = if next > now { next } else { now + frame_duration };
+            self.frame_scheduler.schedule_at(output, next);
+        }
+
+        match self.frame_scheduler.next_deadline_instant() {
+            Some(deadline) => {
+                if let Some((_, armed)) = self.frame_timer.as_mut() {
+                    *armed = deadline;
+                }
+                TimeoutAction::ToInstant(deadline)
+            }
+            None => {
+                self.frame_timer = None;
+                TimeoutAction::Drop
+            }
+        }
     }
 
     #[must_use]
@@ -402,7 +625,13 @@ impl CosmicBg {
             size: None,
             fractional_scale,
             needs_redraw: false,
+            frame_pending: false,
+            frame_deferred: false,
             pool: None,
+            ten_bit: self
+                .shm_state
+                .formats()
+                .contains(&wl_shm::Format::Xrgb2101010),
         }
     }
 }
@@ -434,9 +663,27 @@ impl CompositorHandler for CosmicBg {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
+        surface: &wl_surface::WlSurface,
         _time: u32,
     ) {
+        for wallpaper in &mut self.wallpapers {
+            if let Some(layer) = wallpaper
+                .layers
+                .iter_mut()
+                .find(|layer| layer.layer.wl_surface() == surface)
+            {
+                layer.frame_pending = false;
+
+                // Render the frame that came due while waiting on the compositor.
+                if std::mem::take(&mut layer.frame_deferred) {
+                    layer.needs_redraw = true;
+                    wallpaper.draw();
+                }
+                break;
+            }
+        }
+
+        self.schedule_animations();
     }
 
     fn transform_changed(
@@ -532,22 +779,30 @@ impl OutputHandler for CosmicBg {
         _qh: &QueueHandle<Self>,
         output: wl_output::WlOutput,
     ) {
-        if self.fractional_scale_manager.is_none()
-            && self.compositor_state.wl_compositor().version() < 6
-        {
-            let Some(output_info) = self.output_state.info(&output) else {
-                return;
-            };
-            for wallpaper in &mut self.wallpapers {
-                if let Some(layer) = wallpaper
-                    .layers
-                    .iter_mut()
-                    .find(|layer| layer.wl_output == output)
-                {
+        let Some(output_info) = self.output_state.info(&output) else {
+            return;
+        };
+
+        let legacy_scale = self.fractional_scale_manager.is_none()
+            && self.compositor_state.wl_compositor().version() < 6;
+
+        for wallpaper in &mut self.wallpapers {
+            if let Some(layer) = wallpaper
+                .layers
+                .iter_mut()
+                .find(|layer| layer.wl_output == output)
+            {
+                if legacy_scale {
                     layer.fractional_scale = Some(output_info.scale_factor as u32 * FRACTIONAL_SCALE_MULTIPLIER);
+                }
+
+                // Spanned wallpapers re-crop every output when one moves.
+                layer.output_info = output_info;
+
+                if legacy_scale || wallpaper.entry.span.is_some() {
                     wallpaper.draw();
-                    break;
                 }
+                break;
             }
         }
     }
@@ -559,6 +814,10 @@ impl OutputHandler for CosmicBg {
         output: wl_output::WlOutput,
     ) {
         self.active_outputs.retain(|o| o != &output);
+        // Nothing is left to animate without outputs.
+        if self.active_outputs.is_empty() {
+            self.frame_scheduler.clear();
+        }
         let Some(output_info) = self.output_state.info(&output) else {
             return;
         };
@@ -594,6 +853,9 @@ impl OutputHandler for CosmicBg {
         };
 
         output_wallpaper.layers.remove(layer_position);
+        if output_wallpaper.layers.is_empty() {
+            self.frame_scheduler.remove_output(&output_wallpaper.entry.output);
+        }
     }
 }
 
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Tells the stills of a slideshow directory apart from its animations and
+//! videos.
+//!
+//! Directories are scanned by file extension, which says little about a GIF
+//! or PNG, so the kind of an image is sniffed from its contents once rotation
+//! reaches it, on the loader thread. The header tells most formats apart;
+//! only GIF and JPEG XL files are read further, without decoding, to tell
+//! whether they animate.
+
+use std::path::Path;
+
+use cosmic_ext_bg_config::{AnimatedConfig, VideoConfig};
+
+use crate::animated::{self, AnimatedSource};
+use crate::source::{SourceError, WallpaperSource};
+use crate::video::VideoSource;
+
+/// Extensions of the video files picked up by directory scans.
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "mkv", "webm", "mov", "avi"];
+
+/// `ftyp` brands of ISO base media files holding images rather than video.
+const IMAGE_BRANDS: &[&[u8]] = &[
+    b"avif", b"avis", b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1",
+];
+
+/// What a slideshow entry is played as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Still,
+    Animated,
+    Video,
+}
+
+impl MediaKind {
+    /// Sniff the kind of the file at `path`, falling back to its extension
+    /// for videos whose container has no signature.
+    pub fn of(path: &Path) -> Self {
+        let Ok(header) = animated::read_header(path) else {
+            return Self::Still;
+        };
+
+        if animated::is_animated(path, &header) {
+            return Self::Animated;
+        }
+
+        if is_video(&header) || (image::guess_format(&header).is_err() && is_video_file(path)) {
+            Self::Video
+        } else {
+            Self::Still
+        }
+    }
+}
+
+/// Open the file at `path` for playback if it is an animation or a video,
+/// preparing it for `size` when that is known.
+///
+/// Returns `None` for stills, which are decoded like any other image.
+pub fn open(
+    path: &Path,
+    size: Option<(u32, u32)>,
+) -> Result<Option<Box<dyn WallpaperSource>>, SourceError> {
+    let mut source: Box<dyn WallpaperSource> = match MediaKind::of(path) {
+        MediaKind::Still => return Ok(None),
+        MediaKind::Animated => Box::new(AnimatedSource::new(AnimatedConfig {
+            path: path.to_path_buf(),
+            ..Default::default()
+        })?),
+        MediaKind::Video => Box::new(VideoSource::new(VideoConfig {
+            path: path.to_path_buf(),
+            ..Default::default()
+        })?),
+    };
+
+    if let Some((width, height)) = size {
+        source.prepare(width, height)?;
+    }
+
+    Ok(Some(source))
+}
+
+/// Whether `path` names a video file.
+pub fn is_video_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        VIDEO_EXTENSIONS
+            .iter()
+            .any(|known| ext.eq_ignore_ascii_case(known))
+    })
+}
+
+/// Whether a file header is that of a Matroska, WebM, AVI, MP4 or
+/// QuickTime video.
+fn is_video(header: &[u8]) -> bool {
+    const EBML: &[u8] = &[0x1a, 0x45, 0xdf, 0xa3];
+
+    if header.starts_with(EBML) {
+        return true;
+    }
+
+    if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"AVI ") {
+        return true;
+    }
+
+    let mut brands = animated::ftyp_brands(header).peekable();
+    brands.peek().is_some() && !brands.any(|brand| IMAGE_BRANDS.contains(&brand))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_video_file() {
+        assert!(is_video_file(Path::new("/home/user/Videos/waves.mp4")));
+        assert!(is_video_file(Path::new("clip.WebM")));
+        assert!(!is_video_file(Path::new("photo.jpg")));
+        assert!(!is_video_file(Path::new("mkv")));
+    }
+
+    #[test]
+    fn test_video_sniffing() {
+        assert!(is_video(b"\0\0\0\x18ftypisom\0\0\x02\0isomiso2"));
+        assert!(is_video(b"\0\0\0\x14ftypqt  \0\0\x02\0qt  "));
+        assert!(is_video(&[0x1a, 0x45, 0xdf, 0xa3, 0x9f, 0x42, 0x86, 0x81]));
+        assert!(is_video(b"RIFF\0\0\0\0AVI LIST"));
+
+        // Stills and sequences in the same container are images.
+        assert!(!is_video(b"\0\0\0\x1cftypheic\0\0\0\0mif1heicmiaf"));
+        assert!(!is_video(b"\0\0\0\x18ftypavis\0\0\0\0avismsf1"));
+        assert!(!is_video(b"RIFF\0\0\0\0WEBPVP8 "));
+        assert!(!is_video(b"GIF89a"));
+    }
+
+    #[test]
+    fn test_media_kind() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+        assert_eq!(MediaKind::of(&fixtures.join("animated.gif")), MediaKind::Animated);
+        assert_eq!(MediaKind::of(&fixtures.join("still.gif")), MediaKind::Still);
+        assert_eq!(MediaKind::of(&fixtures.join("missing.mp4")), MediaKind::Still);
+    }
+
+    #[test]
+    fn test_open() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+        let animated = open(&fixtures.join("animated.gif"), Some((4, 4))).unwrap();
+        assert!(animated.is_some_and(|source| source.is_animated()));
+        assert!(open(&fixtures.join("still.gif"), Some((4, 4))).unwrap().is_none());
+    }
+}
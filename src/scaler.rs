@@ -2,19 +2,153 @@
 
 //! Background scaling methods such as fit, stretch, and zoom.
 
+use std::path::Path;
+
+use cosmic_ext_bg_config::{Adjustments, Entry, FilterMethod, ScalingMode};
+use fast_image_resize::ResizeAlg;
 use image::imageops::FilterType;
 use image::{DynamicImage, Pixel};
 
+use crate::ken_burns::Crop;
+
+/// The blurred fill of `FitBlur` is drawn at this fraction of the layer size
+/// and scaled up, which is far cheaper than blurring at full size.
+const BLUR_DOWNSCALE: u32 = 16;
+
+/// Blur radius of the `FitBlur` fill, in pixels of the downscaled copy.
+const BLUR_SIGMA: f32 = 2.5;
+
+/// How many times the output size `FilterMethod::SuperSampling` samples
+/// the source at.
+const SUPERSAMPLING: u8 = 4;
+
+/// Everything a still image is scaled and adjusted with for a layer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScaleSettings {
+    pub mode: ScalingMode,
+    pub filter: FilterMethod,
+    pub adjustments: Option<Adjustments>,
+}
+
+impl ScaleSettings {
+    pub fn new(entry: &Entry) -> Self {
+        Self {
+            mode: entry.scaling_mode.clone(),
+            filter: entry.filter_method,
+            adjustments: entry.adjustments.clone(),
+        }
+    }
+
+    /// Identifies the settings in the keys of pre-scaled cache entries.
+    pub fn fingerprint(&self) -> u64 {
+        crate::cache::fingerprint(self)
+    }
+
+    /// Scale and adjust `img`, decoded from `path`, to the layer size.
+    pub fn apply(
+        &self,
+        path: &Path,
+        img: &DynamicImage,
+        layer_width: u32,
+        layer_height: u32,
+    ) -> DynamicImage {
+        let scaled = scale_path(path, img, &self.mode, self.filter, layer_width, layer_height);
+        match &self.adjustments {
+            Some(adjustments) if !adjustments.is_identity() => {
+                crate::adjust::apply(scaled, adjustments)
+            }
+            _ => scaled,
+        }
+    }
+}
+
+/// Scale `img`, decoded from `path`, to the layer size.
+///
+/// Vector images are rendered afresh at the layer size instead of
+/// resampling `img`.
+pub fn scale_path(
+    path: &Path,
+    img: &DynamicImage,
+    mode: &ScalingMode,
+    filter: FilterMethod,
+    layer_width: u32,
+    layer_height: u32,
+) -> DynamicImage {
+    if crate::svg::is_svg(path) {
+        match crate::svg::render(path, mode, filter, layer_width, layer_height) {
+            Ok(image) => return image,
+            Err(why) => tracing::warn!(?path, %why, "failed to render SVG, scaling its raster"),
+        }
+    }
+
+    scale(img, mode, filter, layer_width, layer_height)
+}
+
+/// Scale `img` to the layer size according to `mode`, resampling with
+/// `filter`.
+pub fn scale(
+    img: &image::DynamicImage,
+    mode: &ScalingMode,
+    filter: FilterMethod,
+    layer_width: u32,
+    layer_height: u32,
+) -> image::DynamicImage {
+    match mode {
+        ScalingMode::Fit(color) => fit(img, color, filter, layer_width, layer_height),
+        // Still frames, such as prescaled ones, start from a plain zoom.
+        ScalingMode::Zoom | ScalingMode::KenBurns(_) => {
+            zoom(img, filter, layer_width, layer_height)
+        }
+        ScalingMode::Stretch => stretch(img, filter, layer_width, layer_height),
+        ScalingMode::Center(color) => center(img, color, layer_width, layer_height),
+        ScalingMode::Tile(factor) => tile(img, *factor, filter, layer_width, layer_height),
+        ScalingMode::FitBlur => fit_blur(img, filter, layer_width, layer_height),
+    }
+}
+
 pub fn fit(
     img: &image::DynamicImage,
     color: &[f32; 3],
+    filter: FilterMethod,
     layer_width: u32,
     layer_height: u32,
 ) -> image::DynamicImage {
     // TODO: convert color to the same format as the input image.
-    let mut filled_image =
+    let filled_image =
         image::ImageBuffer::from_pixel(layer_width, layer_height, *image::Rgb::from_slice(color));
 
+    letterbox(filled_image, img, filter)
+}
+
+/// Fit `img` over a blurred copy of itself zoomed to fill the layer.
+pub fn fit_blur(
+    img: &image::DynamicImage,
+    filter: FilterMethod,
+    layer_width: u32,
+    layer_height: u32,
+) -> image::DynamicImage {
+    let small = zoom(
+        img,
+        filter,
+        (layer_width / BLUR_DOWNSCALE).max(1),
+        (layer_height / BLUR_DOWNSCALE).max(1),
+    );
+
+    let background = small
+        .blur(BLUR_SIGMA)
+        .resize_exact(layer_width, layer_height, FilterType::Triangle)
+        .into_rgb32f();
+
+    letterbox(background, img, filter)
+}
+
+/// Scale `img` to fit `filled_image` and draw it in the middle.
+fn letterbox(
+    mut filled_image: image::Rgb32FImage,
+    img: &image::DynamicImage,
+    filter: FilterMethod,
+) -> image::DynamicImage {
+    let (layer_width, layer_height) = filled_image.dimensions();
     let (w, h) = (img.width(), img.height());
 
     let ratio = (layer_width as f64 / w as f64).min(layer_height as f64 / h as f64);
@@ -24,7 +158,7 @@ pub fn fit(
         (h as f64 * ratio).round() as u32,
     );
 
-    let resized_image = resize(img, new_width, new_height);
+    let resized_image = resize(img, filter, new_width, new_height);
 
     image::imageops::replace(
         &mut filled_image,
@@ -36,15 +170,107 @@ pub fn fit(
     DynamicImage::from(filled_image)
 }
 
+/// Show `img` unscaled in the middle of the layer, cropping whatever doesn't
+/// fit and filling the rest with `color`.
+pub fn center(
+    img: &image::DynamicImage,
+    color: &[f32; 3],
+    layer_width: u32,
+    layer_height: u32,
+) -> image::DynamicImage {
+    let mut filled_image =
+        image::ImageBuffer::from_pixel(layer_width, layer_height, *image::Rgb::from_slice(color));
+
+    let (w, h) = (img.width(), img.height());
+
+    // Only convert the part that ends up on the layer.
+    let visible = img.crop_imm(
+        w.saturating_sub(layer_width) / 2,
+        h.saturating_sub(layer_height) / 2,
+        w.min(layer_width),
+        h.min(layer_height),
+    );
+
+    image::imageops::replace(
+        &mut filled_image,
+        &visible.to_rgb32f(),
+        (layer_width.saturating_sub(w) / 2).into(),
+        (layer_height.saturating_sub(h) / 2).into(),
+    );
+
+    DynamicImage::from(filled_image)
+}
+
+/// Repeat `img` across the layer from its top left corner, scaled by
+/// `factor`.
+pub fn tile(
+    img: &image::DynamicImage,
+    factor: f32,
+    filter: FilterMethod,
+    layer_width: u32,
+    layer_height: u32,
+) -> image::DynamicImage {
+    let factor = if factor.is_finite() {
+        f64::from(factor).clamp(0.05, 20.0)
+    } else {
+        1.0
+    };
+
+    // Only the top left of a tile larger than the layer ever shows, so the
+    // rest of the source is not scaled at all.
+    let (visible_width, visible_height) =
+        visible_tile_source((img.width(), img.height()), factor, (layer_width, layer_height));
+    let cropped;
+    let img = if (visible_width, visible_height) == (img.width(), img.height()) {
+        img
+    } else {
+        cropped = img.crop_imm(0, 0, visible_width, visible_height);
+        &cropped
+    };
+
+    let (w, h) = (img.width(), img.height());
+    let (tile_width, tile_height) = (
+        ((w as f64 * factor).round() as u32).max(1),
+        ((h as f64 * factor).round() as u32).max(1),
+    );
+
+    let scaled;
+    let tile = if (tile_width, tile_height) == (w, h) {
+        img
+    } else {
+        scaled = resize(img, filter, tile_width, tile_height);
+        &scaled
+    };
+
+    let mut tiled = image::DynamicImage::new(layer_width, layer_height, tile.color());
+    image::imageops::tile(&mut tiled, tile);
+    tiled
+}
+
+/// Part of a `size` source that a tile scaled by `factor` shows of on a
+/// `layer` sized buffer, from its top left corner.
+fn visible_tile_source(size: (u32, u32), factor: f64, layer: (u32, u32)) -> (u32, u32) {
+    let visible = |source: u32, layer: u32| {
+        source.min((f64::from(layer) / factor).ceil() as u32).max(1)
+    };
+    (visible(size.0, layer.0), visible(size.1, layer.1))
+}
+
 pub fn stretch(
     img: &image::DynamicImage,
+    filter: FilterMethod,
     layer_width: u32,
     layer_height: u32,
 ) -> image::DynamicImage {
-    resize(img, layer_width, layer_height)
+    resize(img, filter, layer_width, layer_height)
 }
 
-pub fn zoom(img: &image::DynamicImage, layer_width: u32, layer_height: u32) -> image::DynamicImage {
+pub fn zoom(
+    img: &image::DynamicImage,
+    filter: FilterMethod,
+    layer_width: u32,
+    layer_height: u32,
+) -> image::DynamicImage {
     let (w, h) = (img.width(), img.height());
 
     let ratio = (layer_width as f64 / w as f64).max(layer_height as f64 / h as f64);
@@ -54,7 +280,7 @@ pub fn zoom(img: &image::DynamicImage, layer_width: u32, layer_height: u32) -> i
         (h as f64 * ratio).round() as u32,
     );
 
-    let mut new_image = resize(img, new_width, new_height);
+    let mut new_image = resize(img, filter, new_width, new_height);
 
     image::imageops::crop(
         &mut new_image,
@@ -67,19 +293,143 @@ pub fn zoom(img: &image::DynamicImage, layer_width: u32, layer_height: u32) -> i
     .into()
 }
 
-fn resize(img: &image::DynamicImage, new_width: u32, new_height: u32) -> image::DynamicImage {
+/// Scale the `rect` of `img` to the layer size.
+///
+/// The crop is sampled with subpixel precision, so a slowly moving rectangle
+/// doesn't jump from pixel to pixel.
+pub fn crop(
+    img: &image::DynamicImage,
+    rect: Crop,
+    filter: FilterMethod,
+    layer_width: u32,
+    layer_height: u32,
+) -> image::DynamicImage {
+    let mut resizer = fast_image_resize::Resizer::new();
+    let options = fast_image_resize::ResizeOptions::new()
+        .resize_alg(resize_alg(filter))
+        .crop(rect.x, rect.y, rect.width, rect.height);
+    let mut new_image = image::DynamicImage::new(layer_width, layer_height, img.color());
+    if let Err(err) = resizer.resize(img, &mut new_image, &options) {
+        tracing::warn!(?err, "Failed to use `fast_image_resize`. Falling back.");
+        let cropped = img.crop_imm(
+            rect.x.round() as u32,
+            rect.y.round() as u32,
+            rect.width.round() as u32,
+            rect.height.round() as u32,
+        );
+        new_image =
+            image::imageops::resize(&cropped, layer_width, layer_height, filter.into()).into();
+    }
+    new_image
+}
+
+fn resize(
+    img: &image::DynamicImage,
+    filter: FilterMethod,
+    new_width: u32,
+    new_height: u32,
+) -> image::DynamicImage {
     let mut resizer = fast_image_resize::Resizer::new();
     let options = fast_image_resize::ResizeOptions {
-        algorithm: fast_image_resize::ResizeAlg::Convolution(
-            fast_image_resize::FilterType::Lanczos3,
-        ),
+        algorithm: resize_alg(filter),
         ..Default::default()
     };
     let mut new_image = image::DynamicImage::new(new_width, new_height, img.color());
     if let Err(err) = resizer.resize(img, &mut new_image, &options) {
         tracing::warn!(?err, "Failed to use `fast_image_resize`. Falling back.");
-        new_image =
-            image::imageops::resize(img, new_width, new_height, FilterType::Lanczos3).into();
+        new_image = image::imageops::resize(img, new_width, new_height, filter.into()).into();
     }
     new_image
 }
+
+/// The `fast_image_resize` algorithm for a filter.
+fn resize_alg(filter: FilterMethod) -> ResizeAlg {
+    use fast_image_resize::FilterType as Fir;
+
+    match filter {
+        FilterMethod::Nearest => ResizeAlg::Nearest,
+        FilterMethod::Linear => ResizeAlg::Convolution(Fir::Bilinear),
+        FilterMethod::Lanczos => ResizeAlg::Convolution(Fir::Lanczos3),
+        FilterMethod::Box => ResizeAlg::Convolution(Fir::Box),
+        FilterMethod::Mitchell => ResizeAlg::Convolution(Fir::Mitchell),
+        FilterMethod::CatmullRom => ResizeAlg::Convolution(Fir::CatmullRom),
+        FilterMethod::SuperSampling => ResizeAlg::SuperSampling(Fir::Bilinear, SUPERSAMPLING),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_tile_source() {
+        // Small tiles repeat, so all of the source is needed.
+        assert_eq!(visible_tile_source((400, 300), 0.5, (1920, 1080)), (400, 300));
+        // A 20x tile of a photo covers the layer many times over.
+        assert_eq!(visible_tile_source((4000, 3000), 20.0, (1920, 1080)), (96, 54));
+        assert_eq!(visible_tile_source((4000, 3000), 0.05, (10, 10)), (200, 200));
+        assert_eq!(visible_tile_source((1, 1), 20.0, (1920, 1080)), (1, 1));
+    }
+
+    #[test]
+    fn test_tile_large_factor() {
+        let img = DynamicImage::from(image::RgbImage::from_fn(4000, 3000, |x, y| {
+            image::Rgb([(x * 10) as u8, (y * 10) as u8, 0])
+        }));
+
+        let tiled = tile(&img, 20.0, FilterMethod::Nearest, 320, 160);
+        assert_eq!((tiled.width(), tiled.height()), (320, 160));
+        // Each source pixel covers 20x20 layer pixels.
+        assert_eq!(tiled.to_rgb8().get_pixel(319, 159).0, [150, 70, 0]);
+    }
+
+    #[test]
+    fn test_center_crops_larger_image() {
+        let img = DynamicImage::from(image::RgbImage::from_fn(8, 6, |x, y| {
+            image::Rgb([(x * 10) as u8, (y * 10) as u8, 0])
+        }));
+
+        let centered = center(&img, &[0.0, 0.0, 1.0], 4, 2).to_rgb8();
+        assert_eq!(centered.dimensions(), (4, 2));
+        // The middle 4x2 of the image, unscaled.
+        assert_eq!(centered.get_pixel(0, 0).0, [20, 20, 0]);
+        assert_eq!(centered.get_pixel(3, 1).0, [50, 30, 0]);
+    }
+
+    #[test]
+    fn test_center_pads_smaller_image() {
+        let img = DynamicImage::from(image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0])));
+
+        let centered = center(&img, &[0.0, 0.0, 1.0], 6, 4).to_rgb8();
+        assert_eq!(centered.dimensions(), (6, 4));
+
+        for (x, y, pixel) in centered.enumerate_pixels() {
+            let inside = (2..4).contains(&x) && (1..3).contains(&y);
+            let expected = if inside { [255, 0, 0] } else { [0, 0, 255] };
+            assert_eq!(pixel.0, expected, "pixel at {x}x{y}");
+        }
+    }
+
+    #[test]
+    fn test_fit_blur() {
+        // Red on the left half, blue on the right.
+        let img = DynamicImage::from(image::RgbImage::from_fn(160, 80, |x, _| {
+            if x < 80 { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) }
+        }));
+
+        let fitted = fit_blur(&img, FilterMethod::Nearest, 320, 320).to_rgb8();
+        assert_eq!(fitted.dimensions(), (320, 320));
+
+        // The image is fitted to 320x160 in the middle.
+        assert_eq!(fitted.get_pixel(10, 80).0, [255, 0, 0]);
+        assert_eq!(fitted.get_pixel(310, 239).0, [0, 0, 255]);
+
+        // The bars above and below follow the blurred image, not a flat color.
+        for y in [10, 310] {
+            let [left_red, _, left_blue] = fitted.get_pixel(10, y).0;
+            let [right_red, _, right_blue] = fitted.get_pixel(310, y).0;
+            assert!(left_red > left_blue, "left bar at row {y} isn't red");
+            assert!(right_blue > right_red, "right bar at row {y} isn't blue");
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Sources that change with the time of day.
+//!
+//! A `Source::Schedule` or `Source::Solar` entry is resolved to the source
+//! active now before the wallpaper sees it, and a timer swaps in the next
+//! source when its time comes. The timer runs on the monotonic clock, which
+//! stops during suspend, so it never waits longer than [`RECHECK_INTERVAL`]
+//! before checking the wall clock again.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Timelike, Utc};
+use cosmic_ext_bg_config::{Entry, Schedule, SolarConfig, Source};
+
+use crate::solar::{self, Phase};
+
+/// Longest wait before the wall clock is checked again.
+pub const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A source picked by the time of day.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimedSource {
+    Schedule(Schedule),
+    Solar(SolarConfig),
+}
+
+/// The source a [`TimedSource`] shows at some time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Active {
+    pub source: Source,
+    /// Crossfade into `source` in progress
+    pub fade: Option<Fade>,
+}
+
+/// A crossfade spread over a stretch of the day, such as twilight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fade {
+    pub from: Source,
+    pub elapsed: Duration,
+    pub duration: Duration,
+}
+
+impl TimedSource {
+    /// The source to show at `now`.
+    pub fn active(&self, now: DateTime<Local>) -> Active {
+        match self {
+            TimedSource::Schedule(schedule) => Active {
+                source: schedule
+                    .active_slot(now.num_seconds_from_midnight())
+                    .map_or_else(|| Entry::fallback().source, |slot| slot.source.clone()),
+                fade: None,
+            },
+
+            TimedSource::Solar(solar) => {
+                let (phase, _) = solar::phase(solar.latitude, solar.longitude, now.to_utc());
+                let day = || Source::clone(&solar.day);
+                let night = || Source::clone(&solar.night);
+
+                match phase {
+                    Phase::Day => Active {
+                        source: day(),
+                        fade: None,
+                    },
+                    Phase::Dusk { elapsed, duration } if solar.twilight_blend => Active {
+                        source: night(),
+                        fade: Some(Fade {
+                            from: day(),
+                            elapsed,
+                            duration,
+                        }),
+                    },
+                    Phase::Dawn { elapsed, duration } if solar.twilight_blend => Active {
+                        source: day(),
+                        fade: Some(Fade {
+                            from: night(),
+                            elapsed,
+                            duration,
+                        }),
+                    },
+                    Phase::Night | Phase::Dusk { .. } | Phase::Dawn { .. } => Active {
+                        source: night(),
+                        fade: None,
+                    },
+                }
+            }
+        }
+    }
+
+    /// How long to wait before checking again at `now`.
+    pub fn next_check(&self, now: DateTime<Local>) -> Duration {
+        let until_next = match self {
+            TimedSource::Schedule(schedule) => schedule
+                .seconds_until_next(now.num_seconds_from_midnight())
+                .map(|secs| Duration::from_secs(u64::from(secs))),
+            TimedSource::Solar(solar) => {
+                let now = now.to_utc();
+                solar::phase(solar.latitude, solar.longitude, now)
+                    .1
+                    .and_then(|next: DateTime<Utc>| (next - now).to_std().ok())
+            }
+        };
+
+        until_next.map_or(RECHECK_INTERVAL, |until| until.min(RECHECK_INTERVAL))
+    }
+
+    /// Whether the source crossfades over stretches of the day.
+    pub fn fades(&self) -> bool {
+        matches!(self, TimedSource::Solar(solar) if solar.twilight_blend)
+    }
+}
+
+/// Split a timed entry into the entry for its source at `now` and the
+/// timed source.
+///
+/// Partway through a fade, the entry gets the source being faded from, so
+/// it can be drawn before the fade picks up. Other entries are returned as
+/// they are. An invalid timed source resolves to the fallback source.
+pub fn resolve(mut entry: Entry, now: DateTime<Local>) -> (Entry, Option<TimedSource>) {
+    let timed = match entry.source {
+        Source::Schedule(schedule) if schedule.is_valid() => TimedSource::Schedule(schedule),
+        Source::Solar(solar) if solar.is_valid() => TimedSource::Solar(solar),
+        Source::Schedule(_) | Source::Solar(_) => {
+            tracing::error!(
+                output = %entry.output,
+                "schedules need at least one slot, solar sources a valid location, \
+                 and neither can hold another timed source"
+            );
+            entry.source = Entry::fallback().source;
+            return (entry, None);
+        }
+        source => {
+            entry.source = source;
+            return (entry, None);
+        }
+    };
+
+    let active = timed.active(now);
+    entry.source = active.fade.map_or(active.source, |fade| fade.from);
+    (entry, Some(timed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use cosmic_ext_bg_config::{ScheduleSlot, TimeOfDay};
+    use std::path::PathBuf;
+
+    fn path(path: &str) -> Source {
+        Source::Path(PathBuf::from(path))
+    }
+
+    fn scheduled(slots: Vec<ScheduleSlot>) -> Entry {
+        Entry::new("all".to_string(), Source::Schedule(Schedule { slots }))
+    }
+
+    fn slot(hour: u8, source: &str) -> ScheduleSlot {
+        ScheduleSlot {
+            start: TimeOfDay::new(hour, 0).unwrap(),
+            source: path(source),
+        }
+    }
+
+    fn local(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2024, 3, 20, hour, minute, second)
+            .unwrap()
+    }
+
+    fn solar(twilight_blend: bool) -> SolarConfig {
+        // Greenwich, where UTC is the local solar time.
+        SolarConfig {
+            latitude: 51.4769,
+            longitude: 0.0,
+            day: Box::new(path("/day.jpg")),
+            night: Box::new(path("/night.jpg")),
+            twilight_blend,
+        }
+    }
+
+    fn utc(hour: u32, minute: u32) -> DateTime<Local> {
+        Utc.with_ymd_and_hms(2024, 3, 20, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    #[test]
+    fn test_resolve_active_slot() {
+        let entry = scheduled(vec![slot(8, "/day.jpg"), slot(20, "/night.jpg")]);
+
+        let (resolved, timed) = resolve(entry.clone(), local(12, 0, 0));
+        assert_eq!(resolved.source, path("/day.jpg"));
+        assert_eq!(resolved.output, "all");
+        assert!(matches!(timed, Some(TimedSource::Schedule(s)) if s.slots.len() == 2));
+
+        let (resolved, _) = resolve(entry, local(2, 0, 0));
+        assert_eq!(resolved.source, path("/night.jpg"));
+    }
+
+    #[test]
+    fn test_resolve_invalid_and_plain() {
+        let (resolved, timed) = resolve(scheduled(Vec::new()), local(0, 0, 0));
+        assert_eq!(resolved.source, Entry::fallback().source);
+        assert!(timed.is_none());
+
+        let mut invalid = solar(false);
+        invalid.latitude = 100.0;
+        let entry = Entry::new("all".to_string(), Source::Solar(invalid));
+        assert_eq!(
+            resolve(entry, local(0, 0, 0)).0.source,
+            Entry::fallback().source
+        );
+
+        let plain = Entry::new("all".to_string(), path("/a.jpg"));
+        assert_eq!(resolve(plain.clone(), local(0, 0, 0)), (plain, None));
+    }
+
+    #[test]
+    fn test_schedule_next_check() {
+        let schedule = TimedSource::Schedule(Schedule {
+            slots: vec![slot(8, "/day.jpg")],
+        });
+
+        assert_eq!(
+            schedule.next_check(local(7, 59, 55)),
+            Duration::from_secs(5)
+        );
+        assert_eq!(schedule.next_check(local(9, 0, 0)), RECHECK_INTERVAL);
+    }
+
+    #[test]
+    fn test_solar_switch() {
+        let timed = TimedSource::Solar(solar(false));
+
+        assert_eq!(timed.active(utc(12, 0)).source, path("/day.jpg"));
+        assert_eq!(timed.active(utc(22, 0)).source, path("/night.jpg"));
+        // Without a blend, the night starts at sunset.
+        assert_eq!(
+            timed.active(utc(18, 25)),
+            Active {
+                source: path("/night.jpg"),
+                fade: None,
+            }
+        );
+        assert!(!timed.fades());
+    }
+
+    #[test]
+    fn test_solar_twilight_blend() {
+        let timed = TimedSource::Solar(solar(true));
+        assert!(timed.fades());
+
+        let dusk = timed.active(utc(18, 25));
+        assert_eq!(dusk.source, path("/night.jpg"));
+        let fade = dusk.fade.unwrap();
+        assert_eq!(fade.from, path("/day.jpg"));
+        assert!(fade.elapsed < fade.duration);
+
+        // Resolving mid-fade starts from the source being faded from.
+        let entry = Entry::new("all".to_string(), Source::Solar(solar(true)));
+        assert_eq!(resolve(entry, utc(18, 25)).0.source, path("/day.jpg"));
+
+        let dawn = timed.active(utc(5, 50));
+        assert_eq!(dawn.source, path("/day.jpg"));
+        assert_eq!(dawn.fade.unwrap().from, path("/night.jpg"));
+    }
+}
@@ -5,12 +5,8 @@
 //! This module provides timing coordination for animated wallpaper sources,
 //! ensuring smooth frame delivery synchronized with Wayland frame callbacks.
 //!
-//! # Integration Status
-//!
-//! Fully implemented and tested but not yet used. Will be integrated when animated
-//! wallpaper support is added to coordinate frame rendering across multiple outputs.
-
-#![allow(dead_code)]
+//! A single scheduler owned by `CosmicBg` drives every animated wallpaper from
+//! one calloop timer, so outputs with close deadlines share a wakeup.
 
 use std::{
     cmp::Ordering,
@@ -18,6 +14,10 @@ use std::{
     time::{Duration, Instant},
 };
 
+/// Frames due within this window of the earliest one are rendered in the
+/// same wakeup.
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(2);
+
 /// A scheduled frame for a specific output.
 #[derive(Debug, Clone)]
 struct ScheduledFrame {
@@ -113,6 +113,7 @@ impl FrameScheduler {
     ///
     /// Returns `None` if no frames are scheduled.
     /// Returns `Duration::ZERO` if a frame is already overdue.
+    #[allow(dead_code)]
     pub fn next_deadline(&self) -> Option<Duration> {
         self.queue.peek().map(|frame| {
             let now = Instant::now();
@@ -132,6 +133,7 @@ impl FrameScheduler {
     /// Pop all frames that are ready to render (deadline has passed).
     ///
     /// Returns a vector of output names for frames that should be rendered now.
+    #[allow(dead_code)]
     pub fn pop_ready(&mut self) -> Vec<String> {
         let mut ready = Vec::new();
         while let Some(output) = self.pop_next_ready() {
@@ -143,6 +145,7 @@ impl FrameScheduler {
     /// Pop the next ready frame, if any.
     ///
     /// Returns `Some(output_name)` if a frame is ready, `None` otherwise.
+    #[allow(dead_code)]
     pub fn pop_next_ready(&mut self) -> Option<String> {
         let now = Instant::now();
 
@@ -158,12 +161,37 @@ impl FrameScheduler {
         None
     }
 
+    /// Pop all frames due within `window` from now, with their deadlines.
+    ///
+    /// Rendering slightly early frames together coalesces wakeups of
+    /// outputs whose deadlines are close.
+    pub fn pop_ready_within(&mut self, window: Duration) -> Vec<(String, Instant)> {
+        let horizon = Instant::now() + window;
+        let mut ready = Vec::new();
+
+        while self.queue.peek().is_some_and(|frame| frame.deadline <= horizon) {
+            if let Some(frame) = self.queue.pop() {
+                tracing::trace!(output = %frame.output, "frame ready");
+                ready.push((frame.output, frame.deadline));
+            }
+        }
+
+        ready
+    }
+
+    /// Returns true if a frame is scheduled for the given output.
+    pub fn is_scheduled(&self, output: &str) -> bool {
+        self.queue.iter().any(|frame| frame.output == output)
+    }
+
     /// Returns the number of scheduled frames.
+    #[allow(dead_code)]
     pub fn len(&self) -> usize {
         self.queue.len()
     }
 
     /// Returns true if no frames are scheduled.
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
@@ -260,6 +288,23 @@ mod tests {
         assert_eq!(scheduler.len(), 1); // "not_ready" still scheduled
     }
 
+    #[test]
+    fn test_pop_ready_within_coalesces() {
+        let mut scheduler = FrameScheduler::new();
+
+        scheduler.schedule("now", Duration::ZERO);
+        scheduler.schedule("soon", Duration::from_millis(50));
+        scheduler.schedule("later", Duration::from_secs(10));
+
+        let ready = scheduler.pop_ready_within(Duration::from_millis(500));
+        let outputs: Vec<_> = ready.iter().map(|(output, _)| output.as_str()).collect();
+        assert_eq!(outputs, ["now", "soon"]);
+        assert!(ready[0].1 <= ready[1].1);
+
+        assert!(scheduler.is_scheduled("later"));
+        assert!(!scheduler.is_scheduled("soon"));
+    }
+
     #[test]
     fn test_clear() {
         let mut scheduler = FrameScheduler::new();
@@ -46,6 +46,8 @@ pub struct ShaderSource {
     output_buffer: Option<wgpu::Buffer>,
     target_size: Option<(u32, u32)>,
     start_time: Instant,
+    // Set while paused so shader time stands still
+    paused_at: Option<Instant>,
     shader_source: String,
     is_prepared: bool,
 }
@@ -84,6 +86,7 @@ impl ShaderSource {
             output_buffer: None,
             target_size: None,
             start_time: Instant::now(),
+            paused_at: None,
             shader_source,
             is_prepared: false,
         })
@@ -311,7 +314,8 @@ impl ShaderSource {
         let (width, height) = self.target_size.ok_or_else(not_init)?;
 
         // Update uniforms
-        let elapsed = self.start_time.elapsed().as_secs_f32();
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        let elapsed = now.duration_since(self.start_time).as_secs_f32();
         let uniforms = Uniforms {
             resolution: [width as f32, height as f32],
             time: elapsed,
@@ -461,6 +465,16 @@ impl WallpaperSource for ShaderSource {
         tracing::debug!("Shader source released");
     }
 
+    fn pause(&mut self) {
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
+
+    fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.start_time += paused_at.elapsed();
+        }
+    }
+
     fn description(&self) -> String {
         let name = match &self.config.preset {
             Some(p) => format!("{:?}", p),
@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Offline sunrise and sunset times.
+//!
+//! Uses the NOAA solar position equations, which are accurate to about a
+//! minute for latitudes within the polar circles. No network or location
+//! service is involved; the location comes from the config.
+
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Timelike, Utc};
+
+/// Sun altitude at sunrise and sunset, allowing for refraction and the
+/// size of the solar disc.
+pub const SUNRISE_ALTITUDE: f64 = -0.833;
+
+/// Sun altitude at the start of civil dawn and the end of civil dusk.
+pub const CIVIL_TWILIGHT_ALTITUDE: f64 = -6.0;
+
+/// A crossing of the sunrise or civil twilight altitude.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolarEvent {
+    Dawn,
+    Sunrise,
+    Sunset,
+    Dusk,
+}
+
+/// Where the day is at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// Between sunrise and sunset
+    Day,
+    /// Between the end of dusk and the start of dawn, or between sunset and
+    /// sunrise when the sun doesn't get far enough below the horizon
+    Night,
+    /// Civil twilight before sunrise
+    Dawn {
+        elapsed: Duration,
+        duration: Duration,
+    },
+    /// Civil twilight after sunset
+    Dusk {
+        elapsed: Duration,
+        duration: Duration,
+    },
+}
+
+/// The phase of the day at `now`, and when the next event changes it.
+///
+/// When the sun neither rises nor sets for days, as in polar summer and
+/// winter, the phase follows the sun's altitude and there is no next event.
+pub fn phase(latitude: f64, longitude: f64, now: DateTime<Utc>) -> (Phase, Option<DateTime<Utc>>) {
+    let today = now.date_naive();
+    let mut events: Vec<(DateTime<Utc>, SolarEvent)> =
+        [today.pred_opt(), Some(today), today.succ_opt()]
+            .into_iter()
+            .flatten()
+            .flat_map(|date| events(date, latitude, longitude))
+            .collect();
+    events.sort_by_key(|(time, _)| *time);
+
+    let split = events.partition_point(|(time, _)| *time <= now);
+    let last = split.checked_sub(1).map(|i| events[i]);
+    let next = events.get(split).copied();
+
+    let twilight = |(start, _): (DateTime<Utc>, SolarEvent),
+                    (end, _): (DateTime<Utc>, SolarEvent)| {
+        let elapsed = (now - start).to_std().unwrap_or_default();
+        let duration = (end - start).to_std().unwrap_or_default();
+        (elapsed, duration)
+    };
+
+    let phase = match (last, next) {
+        (Some((_, SolarEvent::Sunrise)), _) => Phase::Day,
+        (Some(last @ (_, SolarEvent::Sunset)), Some(next @ (_, SolarEvent::Dusk))) => {
+            let (elapsed, duration) = twilight(last, next);
+            Phase::Dusk { elapsed, duration }
+        }
+        (Some(last @ (_, SolarEvent::Dawn)), Some(next @ (_, SolarEvent::Sunrise))) => {
+            let (elapsed, duration) = twilight(last, next);
+            Phase::Dawn { elapsed, duration }
+        }
+        (Some(_), _) => Phase::Night,
+        (None, _) if altitude(latitude, longitude, now) >= SUNRISE_ALTITUDE => Phase::Day,
+        (None, _) => Phase::Night,
+    };
+
+    (phase, next.map(|(time, _)| time))
+}
+
+/// Dawn, sunrise, sunset and dusk on a UTC date, leaving out those that
+/// don't happen that day.
+pub fn events(date: NaiveDate, latitude: f64, longitude: f64) -> Vec<(DateTime<Utc>, SolarEvent)> {
+    let (noon, declination) = solar_noon(date, longitude);
+
+    let mut events = Vec::with_capacity(4);
+    for (altitude, rising, setting) in [
+        (CIVIL_TWILIGHT_ALTITUDE, SolarEvent::Dawn, SolarEvent::Dusk),
+        (SUNRISE_ALTITUDE, SolarEvent::Sunrise, SolarEvent::Sunset),
+    ] {
+        if let Some(hour_angle) = hour_angle(latitude, declination, altitude) {
+            // Four minutes of time per degree of rotation.
+            let offset = chrono::Duration::milliseconds((hour_angle * 4.0 * 60_000.0) as i64);
+            events.push((noon - offset, rising));
+            events.push((noon + offset, setting));
+        }
+    }
+    events
+}
+
+/// Altitude of the sun above the horizon in degrees, without refraction.
+pub fn altitude(latitude: f64, longitude: f64, time: DateTime<Utc>) -> f64 {
+    let (declination, equation_of_time) = sun_position(julian_century(time));
+
+    let minutes = f64::from(time.num_seconds_from_midnight()) / 60.0;
+    let true_solar_time = (minutes + equation_of_time + 4.0 * longitude).rem_euclid(1440.0);
+    let hour_angle = (true_solar_time / 4.0 - 180.0).to_radians();
+
+    let latitude = latitude.to_radians();
+    let sin_altitude =
+        latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    sin_altitude.clamp(-1.0, 1.0).asin().to_degrees()
+}
+
+/// Time of solar noon on a UTC date, and the sun's declination then.
+fn solar_noon(date: NaiveDate, longitude: f64) -> (DateTime<Utc>, f64) {
+    let midnight = date.and_time(NaiveTime::MIN).and_utc();
+
+    // Solve at the approximate noon, then once more at the result.
+    let mut noon = midnight + chrono::Duration::minutes((720.0 - 4.0 * longitude) as i64);
+    let mut declination = 0.0;
+    for _ in 0..2 {
+        let (decl, equation_of_time) = sun_position(julian_century(noon));
+        let minutes = 720.0 - 4.0 * longitude - equation_of_time;
+        noon = midnight + chrono::Duration::milliseconds((minutes * 60_000.0) as i64);
+        declination = decl;
+    }
+
+    (noon, declination)
+}
+
+/// Hour angle in degrees at which the sun reaches `altitude`, or `None` if
+/// it stays above or below it all day.
+fn hour_angle(latitude: f64, declination: f64, altitude: f64) -> Option<f64> {
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = (altitude.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+
+    (-1.0..=1.0)
+        .contains(&cos_hour_angle)
+        .then(|| cos_hour_angle.acos().to_degrees())
+}
+
+/// Julian centuries since J2000.0.
+fn julian_century(time: DateTime<Utc>) -> f64 {
+    let julian_day = time.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5;
+    (julian_day - 2_451_545.0) / 36_525.0
+}
+
+/// Declination of the sun in radians and the equation of time in minutes.
+fn sun_position(t: f64) -> (f64, f64) {
+    let mean_longitude = (280.46646 + t * (36000.76983 + t * 0.0003032)).rem_euclid(360.0);
+    let mean_anomaly = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+    let eccentricity = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+
+    let m = mean_anomaly.to_radians();
+    let center = m.sin() * (1.914602 - t * (0.004817 + 0.000014 * t))
+        + (2.0 * m).sin() * (0.019993 - 0.000101 * t)
+        + (3.0 * m).sin() * 0.000289;
+
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let apparent_longitude =
+        (mean_longitude + center - 0.00569 - 0.00478 * omega.sin()).to_radians();
+
+    let mean_obliquity =
+        23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+    let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+
+    let declination = (obliquity.sin() * apparent_longitude.sin()).asin();
+
+    let y = (obliquity / 2.0).tan().powi(2);
+    let l = mean_longitude.to_radians();
+    let equation_of_time = 4.0
+        * (y * (2.0 * l).sin() - 2.0 * eccentricity * m.sin()
+            + 4.0 * eccentricity * y * m.sin() * (2.0 * l).cos()
+            - 0.5 * y * y * (4.0 * l).sin()
+            - 1.25 * eccentricity * eccentricity * (2.0 * m).sin())
+        * 180.0
+        / PI;
+
+    (declination, equation_of_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn event(date: NaiveDate, lat: f64, lon: f64, kind: SolarEvent) -> DateTime<Utc> {
+        events(date, lat, lon)
+            .into_iter()
+            .find(|(_, event)| *event == kind)
+            .unwrap()
+            .0
+    }
+
+    fn assert_near(actual: DateTime<Utc>, expected: DateTime<Utc>) {
+        let error = (actual - expected).num_seconds().abs();
+        assert!(error <= 180, "{actual} is {error}s from {expected}");
+    }
+
+    #[test]
+    fn test_london_equinox() {
+        // NOAA: sunrise 06:04 and sunset 18:14 UTC at Greenwich.
+        let date = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        assert_near(
+            event(date, 51.4769, 0.0, SolarEvent::Sunrise),
+            utc(2024, 3, 20, 6, 4),
+        );
+        assert_near(
+            event(date, 51.4769, 0.0, SolarEvent::Sunset),
+            utc(2024, 3, 20, 18, 14),
+        );
+    }
+
+    #[test]
+    fn test_new_york_summer() {
+        // NOAA: sunrise 05:25 EDT (09:25 UTC), sunset 20:31 EDT (00:31 UTC).
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        assert_near(
+            event(date, 40.7128, -74.006, SolarEvent::Sunrise),
+            utc(2024, 6, 21, 9, 25),
+        );
+        assert_near(
+            event(date, 40.7128, -74.006, SolarEvent::Sunset),
+            utc(2024, 6, 22, 0, 31),
+        );
+    }
+
+    #[test]
+    fn test_phase() {
+        let (lat, lon) = (51.4769, 0.0);
+
+        assert_eq!(phase(lat, lon, utc(2024, 3, 20, 12, 0)).0, Phase::Day);
+        assert_eq!(phase(lat, lon, utc(2024, 3, 20, 23, 0)).0, Phase::Night);
+
+        let now = utc(2024, 3, 20, 18, 25);
+        let (dusk, next) = phase(lat, lon, now);
+        let Phase::Dusk { elapsed, duration } = dusk else {
+            panic!("expected dusk, got {dusk:?}");
+        };
+        assert!(elapsed < duration);
+        assert!((25..40).contains(&(duration.as_secs() / 60)));
+        // Dusk ends at the next event.
+        let remaining = chrono::Duration::from_std(duration - elapsed).unwrap();
+        assert_near(next.unwrap(), now + remaining);
+
+        let (dawn, _) = phase(lat, lon, utc(2024, 3, 20, 5, 50));
+        assert!(matches!(dawn, Phase::Dawn { .. }), "{dawn:?}");
+    }
+
+    #[test]
+    fn test_polar() {
+        // Tromsø: midnight sun in June, polar night in December.
+        let (lat, lon) = (69.65, 18.96);
+
+        assert_eq!(phase(lat, lon, utc(2024, 6, 21, 22, 0)), (Phase::Day, None));
+        assert_eq!(phase(lat, lon, utc(2024, 12, 21, 11, 0)).0, Phase::Night);
+    }
+}
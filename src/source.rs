@@ -41,6 +41,9 @@ pub enum SourceError {
     Gradient(String),
     #[error("JPEG XL decode error: {0}")]
     JpegXl(#[from] eyre::Report),
+    #[cfg(feature = "heif")]
+    #[error("HEIF decode error: {0}")]
+    Heif(#[from] crate::heif::HeifError),
 }
 
 impl SourceError {
@@ -62,6 +65,17 @@ pub trait WallpaperSource: Send + Sync {
     /// Whether this source requires continuous rendering
     fn is_animated(&self) -> bool;
 
+    /// Whether frames have more than 8 bits per channel
+    fn is_high_depth(&self) -> bool {
+        false
+    }
+
+    /// Whether playback has reached the end at least once
+    /// Sources without an end count as played through
+    fn played_through(&self) -> bool {
+        true
+    }
+
     /// Prepare source for rendering at given dimensions
     /// This is called when the output size changes
     fn prepare(&mut self, width: u32, height: u32) -> Result<(), SourceError>;
@@ -69,6 +83,12 @@ pub trait WallpaperSource: Send + Sync {
     /// Release resources when no longer needed
     fn release(&mut self);
 
+    /// Suspend playback, keeping the current frame
+    fn pause(&mut self) {}
+
+    /// Continue playback from where it was paused
+    fn resume(&mut self) {}
+
     /// Get a description of this source for debugging
     fn description(&self) -> String;
 }
@@ -100,9 +120,13 @@ impl StaticSource {
             }
         }
 
+        #[cfg(feature = "heif")]
+        if crate::heif::is_heif(&self.path) {
+            return Ok(crate::heif::decode(&self.path)?);
+        }
+
         // Use standard image decoder for other formats
-        let reader = image::ImageReader::open(&self.path)?;
-        let image = reader.with_guessed_format()?.decode()?;
+        let image = crate::decode::open(&self.path)?;
         Ok(image)
     }
 }
@@ -234,7 +258,7 @@ fn decode_jpegxl(path: &std::path::Path) -> Result<DynamicImage, SourceError> {
     let decoder = JxlDecoder::new(file)
         .map_err(|why| eyre!("failed to read jxl image header: {why}"))?;
 
-    let image = DynamicImage::from_decoder(decoder)
+    let image = crate::decode::from_decoder(decoder)
         .map_err(|why| eyre!("failed to decode jxl image: {why}"))?;
 
     Ok(image)
@@ -0,0 +1,323 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Span mode: one wallpaper mapped across the combined layout of outputs.
+//!
+//! The wallpaper is rendered once to a canvas covering the bounding box of
+//! every output's logical rectangle, and each layer shows its own crop of it.
+//! Logical rectangles are already rotated by the compositor, so portrait
+//! outputs crop a portrait region. The canvas resolution follows the densest
+//! output, and lower density outputs downsample their crop.
+
+use cosmic_ext_bg_config::FilterMethod;
+use image::DynamicImage;
+use sctk::output::OutputInfo;
+
+/// Upper bound for either side of the canvas, in pixels.
+const MAX_CANVAS_SIDE: f64 = 16384.0;
+
+/// Placement of an output in global compositor space, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanOutput {
+    pub position: (i32, i32),
+    pub size: (i32, i32),
+}
+
+impl SpanOutput {
+    /// Placement of an output, from its xdg-output geometry or, failing that,
+    /// from its current mode and integer scale.
+    pub fn from_info(info: &OutputInfo) -> Option<Self> {
+        let size = info.logical_size.or_else(|| {
+            let mode = info.modes.iter().find(|mode| mode.current)?;
+            let scale = info.scale_factor.max(1);
+            let (w, h) = (mode.dimensions.0 / scale, mode.dimensions.1 / scale);
+
+            Some(if crate::is_rotated_90_or_270(info.transform) {
+                (h, w)
+            } else {
+                (w, h)
+            })
+        })?;
+
+        if size.0 <= 0 || size.1 <= 0 {
+            return None;
+        }
+
+        Some(Self {
+            position: info.logical_position.unwrap_or(info.location),
+            size,
+        })
+    }
+}
+
+/// Region of the canvas shown by one output, in canvas pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Canvas size and the crop of each output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanLayout {
+    /// Size of the canvas the wallpaper is rendered to, in pixels
+    pub size: (u32, u32),
+    /// Crop shown by each output, in the order they were given
+    pub crops: Vec<Option<Crop>>,
+}
+
+impl SpanLayout {
+    /// Lay out outputs given with their buffer size in pixels.
+    ///
+    /// Outputs are pushed apart by `bezel_gap` logical pixels for every
+    /// output edge to their left or above them, so the image continues
+    /// behind the bezels. Outputs given as `None` get no crop. Returns `None`
+    /// if no output can be placed.
+    pub fn new(outputs: &[Option<(SpanOutput, (u32, u32))>], bezel_gap: u32) -> Option<Self> {
+        let placed: Vec<(SpanOutput, (u32, u32))> = outputs.iter().flatten().copied().collect();
+        if placed.is_empty() {
+            return None;
+        }
+
+        let mut columns: Vec<i32> = placed.iter().map(|(o, _)| o.position.0).collect();
+        let mut rows: Vec<i32> = placed.iter().map(|(o, _)| o.position.1).collect();
+        columns.sort_unstable();
+        columns.dedup();
+        rows.sort_unstable();
+        rows.dedup();
+
+        let gap = i64::from(bezel_gap);
+        let shifted = |output: &SpanOutput| -> (i64, i64) {
+            let column = columns.iter().filter(|x| **x < output.position.0).count() as i64;
+            let row = rows.iter().filter(|y| **y < output.position.1).count() as i64;
+            (
+                i64::from(output.position.0) + column * gap,
+                i64::from(output.position.1) + row * gap,
+            )
+        };
+
+        let (mut min_x, mut min_y) = (i64::MAX, i64::MAX);
+        let (mut max_x, mut max_y) = (i64::MIN, i64::MIN);
+        for (output, _) in &placed {
+            let (x, y) = shifted(output);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x + i64::from(output.size.0));
+            max_y = max_y.max(y + i64::from(output.size.1));
+        }
+
+        let (total_width, total_height) = ((max_x - min_x) as f64, (max_y - min_y) as f64);
+
+        // Pixels per logical pixel of the densest output. Area based, so
+        // outputs whose buffer is rotated relative to their logical size
+        // count the same as any other.
+        let density = placed
+            .iter()
+            .map(|(output, (width, height))| {
+                let logical = f64::from(output.size.0) * f64::from(output.size.1);
+                (f64::from(*width) * f64::from(*height) / logical).sqrt()
+            })
+            .fold(f64::MIN_POSITIVE, f64::max)
+            .min(MAX_CANVAS_SIDE / total_width.max(total_height));
+
+        let size = (
+            ((total_width * density).round() as u32).max(1),
+            ((total_height * density).round() as u32).max(1),
+        );
+
+        let crops = outputs
+            .iter()
+            .map(|output| {
+                let (output, _) = output.as_ref()?;
+                let (x, y) = shifted(output);
+
+                let x = (((x - min_x) as f64 * density).round() as u32).min(size.0 - 1);
+                let y = (((y - min_y) as f64 * density).round() as u32).min(size.1 - 1);
+                let width = (f64::from(output.size.0) * density).round() as u32;
+                let height = (f64::from(output.size.1) * density).round() as u32;
+
+                Some(Crop {
+                    x,
+                    y,
+                    width: width.clamp(1, size.0 - x),
+                    height: height.clamp(1, size.1 - y),
+                })
+            })
+            .collect();
+
+        Some(Self { size, crops })
+    }
+}
+
+/// Cut an output's region out of the canvas and fit it to its buffer.
+///
+/// Buffers of 90° and 270° outputs are sized with their sides swapped, so
+/// the buffer is turned to the crop's orientation rather than stretching a
+/// portrait crop into a landscape buffer. The result's size is the one to
+/// present.
+pub fn crop(
+    canvas: &DynamicImage,
+    crop: Crop,
+    width: u32,
+    height: u32,
+    filter: FilterMethod,
+) -> DynamicImage {
+    let region = canvas.crop_imm(crop.x, crop.y, crop.width, crop.height);
+
+    let (width, height) = if (crop.width > crop.height) != (width > height)
+        && crop.width != crop.height
+        && width != height
+    {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    if (crop.width, crop.height) == (width, height) {
+        region
+    } else {
+        crate::scaler::stretch(&region, filter, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(x: i32, y: i32, w: i32, h: i32, buffer: (u32, u32)) -> Option<(SpanOutput, (u32, u32))> {
+        Some((
+            SpanOutput {
+                position: (x, y),
+                size: (w, h),
+            },
+            buffer,
+        ))
+    }
+
+    fn crop_at(x: u32, y: u32, width: u32, height: u32) -> Option<Crop> {
+        Some(Crop { x, y, width, height })
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let layout = SpanLayout::new(
+            &[
+                output(0, 0, 1920, 1080, (1920, 1080)),
+                output(1920, 0, 1920, 1080, (1920, 1080)),
+                output(3840, 0, 1920, 1080, (1920, 1080)),
+            ],
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(layout.size, (5760, 1080));
+        assert_eq!(layout.crops[1], crop_at(1920, 0, 1920, 1080));
+        assert_eq!(layout.crops[2], crop_at(3840, 0, 1920, 1080));
+    }
+
+    #[test]
+    fn test_bezel_gap() {
+        let layout = SpanLayout::new(
+            &[
+                output(1920, 0, 1920, 1080, (1920, 1080)),
+                output(0, 0, 1920, 1080, (1920, 1080)),
+            ],
+            40,
+        )
+        .unwrap();
+
+        // The gap is left out of both crops.
+        assert_eq!(layout.size, (3880, 1080));
+        assert_eq!(layout.crops[0], crop_at(1960, 0, 1920, 1080));
+        assert_eq!(layout.crops[1], crop_at(0, 0, 1920, 1080));
+    }
+
+    #[test]
+    fn test_mixed_scale() {
+        // A 4K output at scale 2 next to a 1080p output at scale 1.
+        let layout = SpanLayout::new(
+            &[
+                output(0, 0, 1920, 1080, (3840, 2160)),
+                output(1920, 0, 1920, 1080, (1920, 1080)),
+                None,
+            ],
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(layout.size, (7680, 2160));
+        assert_eq!(layout.crops[0], crop_at(0, 0, 3840, 2160));
+        assert_eq!(layout.crops[1], crop_at(3840, 0, 3840, 2160));
+        assert_eq!(layout.crops[2], None);
+
+        let canvas = DynamicImage::new_rgb8(layout.size.0, layout.size.1);
+        let shown = crop(
+            &canvas,
+            layout.crops[1].unwrap(),
+            1920,
+            1080,
+            FilterMethod::Lanczos,
+        );
+        assert_eq!((shown.width(), shown.height()), (1920, 1080));
+    }
+
+    #[test]
+    fn test_rotated_output() {
+        // A portrait output right of a landscape one, its buffer swapped.
+        let layout = SpanLayout::new(
+            &[
+                output(0, 420, 1920, 1080, (1920, 1080)),
+                output(1920, 0, 1080, 1920, (1920, 1080)),
+            ],
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(layout.size, (3000, 1920));
+        assert_eq!(layout.crops[0], crop_at(0, 420, 1920, 1080));
+        assert_eq!(layout.crops[1], crop_at(1920, 0, 1080, 1920));
+
+        // The portrait crop is drawn upright, not squashed into the swapped buffer.
+        let canvas = DynamicImage::new_rgb8(layout.size.0, layout.size.1);
+        let shown = crop(
+            &canvas,
+            layout.crops[1].unwrap(),
+            1920,
+            1080,
+            FilterMethod::Lanczos,
+        );
+        assert_eq!((shown.width(), shown.height()), (1080, 1920));
+    }
+
+    #[test]
+    fn test_rotated_output_downsampled() {
+        // A portrait output at scale 1 next to a 4K landscape one at scale 2.
+        let layout = SpanLayout::new(
+            &[
+                output(0, 0, 1920, 1080, (3840, 2160)),
+                output(1920, 0, 1080, 1920, (1920, 1080)),
+            ],
+            0,
+        )
+        .unwrap();
+
+        let region = layout.crops[1].unwrap();
+        assert_eq!(region, crop_at(3840, 0, 2160, 3840));
+
+        let canvas = DynamicImage::new_rgb8(layout.size.0, layout.size.1);
+        let shown = crop(&canvas, region, 1920, 1080, FilterMethod::Lanczos);
+
+        // Same aspect ratio as the crop, at the buffer's resolution.
+        assert_eq!((shown.width(), shown.height()), (1080, 1920));
+        assert_eq!(
+            u64::from(shown.width()) * u64::from(region.height),
+            u64::from(shown.height()) * u64::from(region.width)
+        );
+    }
+
+    #[test]
+    fn test_empty_layout() {
+        assert_eq!(SpanLayout::new(&[None, None], 0), None);
+    }
+}
@@ -0,0 +1,310 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! SVG and SVGZ wallpapers, rasterized with `resvg`.
+//!
+//! Vector images are rendered straight at each layer's pixel size, with the
+//! scaling mode applied as a transform, so they stay sharp at any fractional
+//! scale. The decoded "original" cached for them is only used where a raster
+//! is needed regardless, such as Ken Burns crops and theme classification.
+//!
+//! Rendering happens on the loader thread, which keeps the drawings it parsed
+//! last so that each layer size doesn't read and parse the file again.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+    time::SystemTime,
+};
+
+use cosmic_ext_bg_config::{FilterMethod, ScalingMode};
+use image::{DynamicImage, RgbaImage};
+use resvg::{
+    tiny_skia::{Color, Pixmap, Transform},
+    usvg::{self, fontdb},
+};
+
+/// Size the longer side of a rasterized original is brought up to.
+const ORIGINAL_SIZE: f32 = 3840.0;
+
+/// How many parsed drawings are kept for further renders.
+const PARSED_TREES: usize = 4;
+
+/// A parsed drawing and the modification time of the file it came from.
+type Parsed = (PathBuf, Option<SystemTime>, Arc<usvg::Tree>);
+
+/// Drawings parsed last, most recently used first.
+static TREES: Mutex<VecDeque<Parsed>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, thiserror::Error)]
+pub enum SvgError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("SVG error: {0}")]
+    Parse(#[from] usvg::Error),
+    #[error("cannot rasterize at {0}x{1}")]
+    Size(u32, u32),
+}
+
+/// Whether `path` names an SVG or gzip-compressed SVGZ file.
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg") || ext.eq_ignore_ascii_case("svgz"))
+}
+
+/// Rasterize the image at `path` at its own aspect ratio, large enough to be
+/// cropped and scaled like a photo.
+pub fn decode(path: &Path) -> Result<DynamicImage, SvgError> {
+    let tree = load(path)?;
+    let (width, height) = (tree.size().width(), tree.size().height());
+    let scale = (ORIGINAL_SIZE / width.max(height)).max(1.0);
+
+    rasterize(
+        &tree,
+        (width * scale).round() as u32,
+        (height * scale).round() as u32,
+        Placement::stretch((width, height), (width * scale, height * scale)),
+        None,
+    )
+}
+
+/// Rasterize the image at `path` with its longer side `size` pixels long,
+/// for a quick look at its colors.
+pub fn thumbnail(path: &Path, size: u32) -> Result<DynamicImage, SvgError> {
+    let tree = load(path)?;
+    let (width, height) = (tree.size().width(), tree.size().height());
+    let scale = size as f32 / width.max(height);
+    let thumbnail = (width * scale, height * scale);
+
+    rasterize(
+        &tree,
+        thumbnail.0.round().max(1.0) as u32,
+        thumbnail.1.round().max(1.0) as u32,
+        Placement::stretch((width, height), thumbnail),
+        None,
+    )
+}
+
+/// Render the image at `path` for a layer, applying `mode` in vector space.
+///
+/// Tiles and the blurred fill of `FitBlur` are rasterized at the size they
+/// are shown at and finished by the raster scaler.
+pub fn render(
+    path: &Path,
+    mode: &ScalingMode,
+    filter: FilterMethod,
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage, SvgError> {
+    let tree = load(path)?;
+    let size = (tree.size().width(), tree.size().height());
+    let layer = (width as f32, height as f32);
+
+    match mode {
+        ScalingMode::Zoom | ScalingMode::KenBurns(_) => {
+            rasterize(&tree, width, height, Placement::zoom(size, layer), None)
+        }
+        ScalingMode::Stretch => {
+            rasterize(&tree, width, height, Placement::stretch(size, layer), None)
+        }
+        ScalingMode::Fit(color) => rasterize(
+            &tree,
+            width,
+            height,
+            Placement::fit(size, layer),
+            Some(color),
+        ),
+        ScalingMode::Center(color) => rasterize(
+            &tree,
+            width,
+            height,
+            Placement::center(size, layer),
+            Some(color),
+        ),
+        ScalingMode::Tile(factor) => {
+            let factor = if factor.is_finite() {
+                factor.clamp(0.05, 20.0)
+            } else {
+                1.0
+            };
+            let tile = (size.0 * factor, size.1 * factor);
+            // Only the top left of a tile larger than the layer ever shows.
+            let image = rasterize(
+                &tree,
+                (tile.0.round().max(1.0) as u32).min(width),
+                (tile.1.round().max(1.0) as u32).min(height),
+                Placement::stretch(size, tile),
+                None,
+            )?;
+            Ok(crate::scaler::tile(&image, 1.0, filter, width, height))
+        }
+        ScalingMode::FitBlur => {
+            let fitted = Placement::fit(size, layer);
+            let image = rasterize(
+                &tree,
+                (size.0 * fitted.scale.0).round().max(1.0) as u32,
+                (size.1 * fitted.scale.1).round().max(1.0) as u32,
+                Placement::stretch(size, (size.0 * fitted.scale.0, size.1 * fitted.scale.1)),
+                None,
+            )?;
+            Ok(crate::scaler::fit_blur(&image, filter, width, height))
+        }
+    }
+}
+
+/// Where the image lands on the layer: its scale on each axis and the
+/// position of its top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Placement {
+    scale: (f32, f32),
+    offset: (f32, f32),
+}
+
+impl Placement {
+    /// Scaled uniformly by `scale` and centered.
+    fn centered(size: (f32, f32), layer: (f32, f32), scale: f32) -> Self {
+        Self {
+            scale: (scale, scale),
+            offset: (
+                (layer.0 - size.0 * scale) / 2.0,
+                (layer.1 - size.1 * scale) / 2.0,
+            ),
+        }
+    }
+
+    fn zoom(size: (f32, f32), layer: (f32, f32)) -> Self {
+        Self::centered(size, layer, (layer.0 / size.0).max(layer.1 / size.1))
+    }
+
+    fn fit(size: (f32, f32), layer: (f32, f32)) -> Self {
+        Self::centered(size, layer, (layer.0 / size.0).min(layer.1 / size.1))
+    }
+
+    fn center(size: (f32, f32), layer: (f32, f32)) -> Self {
+        Self::centered(size, layer, 1.0)
+    }
+
+    fn stretch(size: (f32, f32), layer: (f32, f32)) -> Self {
+        Self {
+            scale: (layer.0 / size.0, layer.1 / size.1),
+            offset: (0.0, 0.0),
+        }
+    }
+
+    fn transform(self) -> Transform {
+        Transform::from_row(
+            self.scale.0,
+            0.0,
+            0.0,
+            self.scale.1,
+            self.offset.0,
+            self.offset.1,
+        )
+    }
+}
+
+/// Parse the drawing at `path`, or reuse it if it was parsed recently and
+/// the file hasn't changed since.
+fn load(path: &Path) -> Result<Arc<usvg::Tree>, SvgError> {
+    let modified = std::fs::metadata(path)?.modified().ok();
+
+    {
+        let mut trees = TREES.lock().unwrap_or_else(PoisonError::into_inner);
+        let position = trees
+            .iter()
+            .position(|(parsed, time, _)| parsed == path && *time == modified);
+        if let Some(parsed) = position.and_then(|position| trees.remove(position)) {
+            let tree = Arc::clone(&parsed.2);
+            trees.push_front(parsed);
+            return Ok(tree);
+        }
+    }
+
+    let tree = Arc::new(parse(path)?);
+
+    let mut trees = TREES.lock().unwrap_or_else(PoisonError::into_inner);
+    trees.retain(|(parsed, ..)| parsed != path);
+    trees.push_front((path.to_path_buf(), modified, Arc::clone(&tree)));
+    trees.truncate(PARSED_TREES);
+
+    Ok(tree)
+}
+
+fn parse(path: &Path) -> Result<usvg::Tree, SvgError> {
+    // System fonts take a while to load, so they are shared by every render.
+    static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+
+    let mut options = usvg::Options {
+        resources_dir: path.parent().map(Path::to_path_buf),
+        ..Default::default()
+    };
+    options.fontdb = Arc::clone(FONTS.get_or_init(|| {
+        let mut fonts = fontdb::Database::new();
+        fonts.load_system_fonts();
+        Arc::new(fonts)
+    }));
+
+    // Compressed SVGZ data is detected and inflated by `usvg`.
+    let data = std::fs::read(path)?;
+    Ok(usvg::Tree::from_data(&data, &options)?)
+}
+
+fn rasterize(
+    tree: &usvg::Tree,
+    width: u32,
+    height: u32,
+    placement: Placement,
+    background: Option<&[f32; 3]>,
+) -> Result<DynamicImage, SvgError> {
+    let mut pixmap = Pixmap::new(width, height).ok_or(SvgError::Size(width, height))?;
+
+    if let Some(&[r, g, b]) = background {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        pixmap.fill(Color::from_rgba8(channel(r), channel(g), channel(b), 255));
+    }
+
+    resvg::render(tree, placement.transform(), &mut pixmap.as_mut());
+
+    let image = RgbaImage::from_fn(width, height, |x, y| {
+        let color = pixmap.pixels()[(y * width + x) as usize].demultiply();
+        image::Rgba([color.red(), color.green(), color.blue(), color.alpha()])
+    });
+
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_svg() {
+        assert!(is_svg(Path::new("/usr/share/backgrounds/waves.svg")));
+        assert!(is_svg(Path::new("art.SVGZ")));
+        assert!(!is_svg(Path::new("photo.jpg")));
+        assert!(!is_svg(Path::new("svg")));
+    }
+
+    #[test]
+    fn test_placement() {
+        // A 100x50 drawing on a 1000x1000 layer.
+        let size = (100.0, 50.0);
+        let layer = (1000.0, 1000.0);
+
+        let zoom = Placement::zoom(size, layer);
+        assert_eq!(zoom.scale, (20.0, 20.0));
+        assert_eq!(zoom.offset, (-500.0, 0.0));
+
+        let fit = Placement::fit(size, layer);
+        assert_eq!(fit.scale, (10.0, 10.0));
+        assert_eq!(fit.offset, (0.0, 250.0));
+
+        let center = Placement::center(size, layer);
+        assert_eq!(center.scale, (1.0, 1.0));
+        assert_eq!(center.offset, (450.0, 475.0));
+
+        let stretch = Placement::stretch(size, layer);
+        assert_eq!(stretch.scale, (10.0, 20.0));
+        assert_eq!(stretch.offset, (0.0, 0.0));
+    }
+}
@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Light / dark theme tracking for `filter_by_theme` slideshows.
+//!
+//! The daemon follows the COSMIC theme's `is_dark` setting. Slideshows with
+//! `filter_by_theme` enabled only show images from a `light/` or `dark/`
+//! subdirectory of their source, or, when the source has no such
+//! subdirectories, images whose average luminance suits the theme.
+
+use cosmic_config::{ConfigGet, calloop::ConfigWatchSource};
+use image::DynamicImage;
+use sctk::reexports::calloop::LoopHandle;
+
+use crate::CosmicBg;
+
+/// Config of the COSMIC theme mode.
+const THEME_MODE_NAME: &str = "com.system76.CosmicTheme.Mode";
+const THEME_MODE_VERSION: u64 = 1;
+const IS_DARK: &str = "is_dark";
+
+/// Images darker than this average luminance suit a dark theme.
+const DARK_LUMINANCE_THRESHOLD: f32 = 0.5;
+
+/// Size of the thumbnail luminance is sampled from.
+pub const LUMINANCE_SAMPLE_SIZE: u32 = 32;
+
+/// Light or dark mode of the desktop theme.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThemeMode {
+    Light,
+    #[default]
+    Dark,
+}
+
+impl ThemeMode {
+    pub fn from_is_dark(is_dark: bool) -> Self {
+        if is_dark { Self::Dark } else { Self::Light }
+    }
+
+    /// Name of the subdirectory holding images for this mode.
+    pub fn subdir(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+
+    /// Whether an image with the given average luminance suits this mode.
+    pub fn suits_luminance(self, luminance: f32) -> bool {
+        match self {
+            Self::Light => luminance >= DARK_LUMINANCE_THRESHOLD,
+            Self::Dark => luminance < DARK_LUMINANCE_THRESHOLD,
+        }
+    }
+}
+
+/// Average relative luminance of an image, from 0.0 (black) to 1.0 (white).
+pub fn mean_luminance(image: &DynamicImage) -> f32 {
+    let thumbnail = image
+        .thumbnail(LUMINANCE_SAMPLE_SIZE, LUMINANCE_SAMPLE_SIZE)
+        .to_rgb32f();
+
+    let pixels = thumbnail.pixels().len();
+    if pixels == 0 {
+        return 0.0;
+    }
+
+    let sum: f32 = thumbnail
+        .pixels()
+        .map(|p| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2])
+        .sum();
+
+    sum / pixels as f32
+}
+
+/// Watch the COSMIC theme mode and return the current one.
+///
+/// Falls back to [`ThemeMode::Dark`] if the theme config is unavailable.
+pub fn theme_source(handle: &LoopHandle<'static, CosmicBg>) -> ThemeMode {
+    let config = match cosmic_config::Config::new(THEME_MODE_NAME, THEME_MODE_VERSION) {
+        Ok(config) => config,
+        Err(why) => {
+            tracing::error!(?why, "failed to open theme mode config");
+            return ThemeMode::default();
+        }
+    };
+
+    let current = config
+        .get::<bool>(IS_DARK)
+        .map(ThemeMode::from_is_dark)
+        .unwrap_or_default();
+
+    match ConfigWatchSource::new(&config) {
+        Ok(source) => {
+            let result = handle.insert_source(source, |(config, keys), (), state| {
+                if !keys.iter().any(|key| key == IS_DARK) {
+                    return;
+                }
+
+                match config.get::<bool>(IS_DARK) {
+                    Ok(is_dark) => state.set_theme(ThemeMode::from_is_dark(is_dark)),
+                    Err(why) => tracing::error!(?why, "failed to read theme mode"),
+                }
+            });
+
+            if let Err(why) = result {
+                tracing::error!(?why, "failed to insert theme mode watcher");
+            }
+        }
+        Err(why) => tracing::error!(?why, "failed to watch theme mode config"),
+    }
+
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn solid(value: u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 48, Rgb([value; 3])))
+    }
+
+    #[test]
+    fn test_mean_luminance_extremes() {
+        assert!(mean_luminance(&solid(0)) < 0.01);
+        assert!(mean_luminance(&solid(255)) > 0.99);
+    }
+
+    #[test]
+    fn test_mode_suits_luminance() {
+        let night = mean_luminance(&solid(30));
+        let day = mean_luminance(&solid(220));
+
+        assert!(ThemeMode::Dark.suits_luminance(night));
+        assert!(!ThemeMode::Dark.suits_luminance(day));
+        assert!(ThemeMode::Light.suits_luminance(day));
+        assert!(!ThemeMode::Light.suits_luminance(night));
+    }
+
+    #[test]
+    fn test_from_is_dark() {
+        assert_eq!(ThemeMode::from_is_dark(true), ThemeMode::Dark);
+        assert_eq!(ThemeMode::from_is_dark(false).subdir(), "light");
+    }
+}
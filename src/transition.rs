@@ -0,0 +1,417 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Transition effects between two wallpapers.
+//!
+//! A transition blends the frames that were on screen before a change with
+//! the scaled frames of the new wallpaper. While it runs, the wallpaper is
+//! animated by the `FrameScheduler` like any other animated source.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use cosmic_ext_bg_config::{Transition, TransitionKind};
+use image::{DynamicImage, ImageBuffer, Pixel, Rgba, RgbaImage};
+
+/// Interval between transition frames.
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Distinct blend weights of a crossfade. Long crossfades only need a new
+/// frame when the weight changes.
+const CROSSFADE_STEPS: u32 = 256;
+
+/// How far `ZoomIn` magnifies the previous wallpaper by the end.
+const ZOOM_AMOUNT: f32 = 0.25;
+
+/// Identifies one rendered frame of a wallpaper: its buffer size, and the
+/// output it was rendered for when outputs show different images.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FrameKey {
+    pub output: Option<String>,
+    pub size: (u32, u32),
+}
+
+impl FrameKey {
+    pub fn new(output: Option<&str>, size: (u32, u32)) -> Self {
+        Self {
+            output: output.map(str::to_owned),
+            size,
+        }
+    }
+}
+
+/// Channel types frames are blended in.
+pub trait Channel: image::Primitive {
+    /// Blend weight that shows only the new frame.
+    const FULL: u32;
+
+    /// Mix two samples, `weight` parts of `FULL` towards `to`.
+    fn mix(from: Self, to: Self, weight: u32) -> Self;
+}
+
+impl Channel for u8 {
+    const FULL: u32 = 1 << 8;
+
+    fn mix(from: Self, to: Self, weight: u32) -> Self {
+        ((u32::from(from) * (Self::FULL - weight) + u32::from(to) * weight) >> 8) as u8
+    }
+}
+
+impl Channel for u16 {
+    const FULL: u32 = 1 << 16;
+
+    fn mix(from: Self, to: Self, weight: u32) -> Self {
+        let mixed = u64::from(from) * u64::from(Self::FULL - weight)
+            + u64::from(to) * u64::from(weight);
+        (mixed >> 16) as u16
+    }
+}
+
+type Buffer<S> = ImageBuffer<Rgba<S>, Vec<S>>;
+type Rgba16Image = Buffer<u16>;
+
+/// A frame kept for blending, at 16 bits per channel when its source has
+/// more than 8, so fades from and to deep images keep their precision.
+#[derive(Debug)]
+enum Frame {
+    Eight(RgbaImage),
+    Sixteen(Rgba16Image),
+}
+
+impl Frame {
+    fn new(image: DynamicImage, high_depth: bool) -> Self {
+        if high_depth {
+            Self::Sixteen(image.into_rgba16())
+        } else {
+            Self::Eight(image.into_rgba8())
+        }
+    }
+
+    fn to_sixteen(&self) -> Cow<'_, Rgba16Image> {
+        match self {
+            Self::Eight(frame) => Cow::Owned(DynamicImage::ImageRgba8(frame.clone()).into_rgba16()),
+            Self::Sixteen(frame) => Cow::Borrowed(frame),
+        }
+    }
+}
+
+/// A running transition from the frames previously on screen.
+#[derive(Debug)]
+pub struct TransitionState {
+    kind: TransitionKind,
+    duration: Duration,
+    /// Progress made before the transition was picked up
+    offset: Duration,
+    /// Set once the new wallpaper is ready and the first frame is drawn
+    start: Option<Instant>,
+    /// Frames on screen when the transition began
+    from: HashMap<FrameKey, Frame>,
+    /// Frames of a static target
+    to: HashMap<FrameKey, Frame>,
+}
+
+impl TransitionState {
+    /// Begin a transition from the given frames, or `None` if `config`
+    /// disables transitions or nothing was on screen.
+    ///
+    /// Frames with more than 8 bits per channel are blended at 16.
+    pub fn new(config: &Transition, from: HashMap<FrameKey, DynamicImage>) -> Option<Self> {
+        if !config.is_enabled() {
+            return None;
+        }
+
+        Self::resume(config.kind, config.clamped_duration(), Duration::ZERO, from)
+    }
+
+    /// Continue a transition of `duration` that is already `elapsed` in, as
+    /// when a timed slideshow is picked up partway through a crossfade.
+    pub fn resume(
+        kind: TransitionKind,
+        duration: Duration,
+        elapsed: Duration,
+        from: HashMap<FrameKey, DynamicImage>,
+    ) -> Option<Self> {
+        if from.is_empty() || duration.is_zero() {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            duration,
+            offset: elapsed,
+            start: None,
+            from: from
+                .into_iter()
+                .map(|(key, frame)| {
+                    let high_depth = crate::draw::is_high_depth(&frame);
+                    (key, Frame::new(frame, high_depth))
+                })
+                .collect(),
+            to: HashMap::new(),
+        })
+    }
+
+    /// Whether the first frame has been drawn.
+    pub fn is_started(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Whether the transition has run its full duration.
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// Interval between frames. Crossfades longer than a few seconds are
+    /// drawn less often, as their blend weight changes more slowly.
+    pub fn frame_interval(&self) -> Duration {
+        match self.kind {
+            TransitionKind::Crossfade => (self.duration / CROSSFADE_STEPS).max(FRAME_INTERVAL),
+            _ => FRAME_INTERVAL,
+        }
+    }
+
+    /// Linear progress from 0.0 to 1.0.
+    fn progress(&self, now: Instant) -> f32 {
+        let Some(start) = self.start else {
+            return (self.offset.as_secs_f32() / self.duration.as_secs_f32()).min(1.0);
+        };
+
+        let elapsed = now.saturating_duration_since(start) + self.offset;
+        (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    /// Blend the previous frame for `output` into `target`, starting the
+    /// clock on the first call.
+    ///
+    /// `target` is kept across frames when `is_static`, so it only needs
+    /// converting once, at 16 bits per channel when `high_depth`. Returns
+    /// `target` unchanged if there is no previous frame of its size.
+    pub fn render(
+        &mut self,
+        output: Option<&str>,
+        target: DynamicImage,
+        is_static: bool,
+        high_depth: bool,
+        now: Instant,
+    ) -> DynamicImage {
+        let key = FrameKey::new(output, (target.width(), target.height()));
+        if !self.from.contains_key(&key) {
+            return target;
+        }
+
+        let start = *self.start.get_or_insert(now);
+        let t = ease(self.progress(now.max(start)));
+
+        let from = &self.from[&key];
+        let high_depth = high_depth || matches!(from, Frame::Sixteen(_));
+
+        let converted;
+        let to = if is_static {
+            self.to
+                .entry(key.clone())
+                .or_insert_with(|| Frame::new(target, high_depth))
+        } else {
+            converted = Frame::new(target, high_depth);
+            &converted
+        };
+
+        match (from, to) {
+            (Frame::Eight(from), Frame::Eight(to)) => {
+                DynamicImage::ImageRgba8(blend(self.kind, from, to, t))
+            }
+            (from, to) => {
+                DynamicImage::ImageRgba16(blend(self.kind, &from.to_sixteen(), &to.to_sixteen(), t))
+            }
+        }
+    }
+}
+
+/// Smoothstep easing, so effects start and end gently.
+fn ease(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Render `kind` at progress `t` between two frames of the same size.
+pub fn blend<S: Channel>(
+    kind: TransitionKind,
+    from: &Buffer<S>,
+    to: &Buffer<S>,
+    t: f32,
+) -> Buffer<S>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    let (width, height) = to.dimensions();
+
+    match kind {
+        TransitionKind::None => to.clone(),
+        TransitionKind::Crossfade => crossfade(from, to, t),
+        TransitionKind::Wipe => {
+            let edge = (t * width as f32).round() as u32;
+            Buffer::from_fn(width, height, |x, y| {
+                if x < edge { *to.get_pixel(x, y) } else { *from.get_pixel(x, y) }
+            })
+        }
+        TransitionKind::Slide => {
+            let offset = (t * width as f32).round() as u32;
+            Buffer::from_fn(width, height, |x, y| {
+                if x + offset < width {
+                    *from.get_pixel(x + offset, y)
+                } else {
+                    *to.get_pixel(x + offset - width, y)
+                }
+            })
+        }
+        TransitionKind::ZoomIn => {
+            let scale = 1.0 + ZOOM_AMOUNT * t;
+            let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+            let zoomed = Buffer::from_fn(width, height, |x, y| {
+                let sx = (cx + (x as f32 + 0.5 - cx) / scale) as u32;
+                let sy = (cy + (y as f32 + 0.5 - cy) / scale) as u32;
+                *from.get_pixel(sx.min(width - 1), sy.min(height - 1))
+            });
+            crossfade(&zoomed, to, t)
+        }
+    }
+}
+
+fn crossfade<S: Channel>(from: &Buffer<S>, to: &Buffer<S>, t: f32) -> Buffer<S>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    let weight = (t * S::FULL as f32) as u32;
+
+    let mut out = to.clone();
+    for (out, from) in out.iter_mut().zip(from.iter()) {
+        *out = S::mix(*from, *out, weight);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn solid(value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(8, 2, Rgba([value, value, value, 255]))
+    }
+
+    #[test]
+    fn test_crossfade_midpoint() {
+        let frame = blend(TransitionKind::Crossfade, &solid(0), &solid(200), 0.5);
+        assert_eq!(frame.get_pixel(3, 1).0, [100, 100, 100, 255]);
+    }
+
+    #[test]
+    fn test_endpoints_match_frames() {
+        for kind in [
+            TransitionKind::Crossfade,
+            TransitionKind::Wipe,
+            TransitionKind::Slide,
+            TransitionKind::ZoomIn,
+        ] {
+            assert_eq!(blend(kind, &solid(10), &solid(250), 0.0), solid(10), "{kind:?}");
+            assert_eq!(blend(kind, &solid(10), &solid(250), 1.0), solid(250), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn test_wipe_and_slide_split() {
+        let wipe = blend(TransitionKind::Wipe, &solid(0), &solid(255), 0.25);
+        assert_eq!(wipe.get_pixel(1, 0).0[0], 255);
+        assert_eq!(wipe.get_pixel(2, 0).0[0], 0);
+
+        let mut from = solid(0);
+        from.put_pixel(7, 0, Rgba([9, 9, 9, 255]));
+        let slide = blend(TransitionKind::Slide, &from, &solid(255), 0.5);
+        // The right edge of the previous frame moved to the middle.
+        assert_eq!(slide.get_pixel(3, 0).0[0], 9);
+        assert_eq!(slide.get_pixel(4, 0).0[0], 255);
+    }
+
+    #[test]
+    fn test_state_lifecycle() {
+        let config = Transition {
+            kind: TransitionKind::Crossfade,
+            duration_ms: 100,
+        };
+        let from = HashMap::from([(
+            FrameKey::new(None, (8, 2)),
+            DynamicImage::ImageRgba8(solid(0)),
+        )]);
+
+        assert!(TransitionState::new(&Transition::default(), from.clone()).is_none());
+        assert!(TransitionState::new(&config, HashMap::new()).is_none());
+
+        let mut state = TransitionState::new(&config, from).unwrap();
+        assert!(!state.is_started());
+
+        let start = Instant::now();
+        let first = state.render(None, DynamicImage::ImageRgba8(solid(200)), true, false, start);
+        assert!(state.is_started());
+        assert_eq!(first.to_rgba8(), solid(0));
+        assert!(!state.is_finished(start + Duration::from_millis(50)));
+        assert!(state.is_finished(start + Duration::from_millis(100)));
+
+        // Frames of other sizes or outputs are drawn as they are.
+        let other = RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 255]));
+        let drawn =
+            state.render(None, DynamicImage::ImageRgba8(other.clone()), false, false, start);
+        assert_eq!(drawn.to_rgba8(), other);
+        let drawn =
+            state.render(Some("DP-2"), DynamicImage::ImageRgba8(solid(7)), false, false, start);
+        assert_eq!(drawn.to_rgba8(), solid(7));
+    }
+
+    #[test]
+    fn test_keeps_sixteen_bits() {
+        let config = Transition {
+            kind: TransitionKind::Crossfade,
+            duration_ms: 100,
+        };
+        let deep = |value| {
+            let pixel = Rgba([value, value, value, u16::MAX]);
+            DynamicImage::ImageRgba16(Rgba16Image::from_pixel(8, 2, pixel))
+        };
+
+        let from = HashMap::from([(FrameKey::new(None, (8, 2)), deep(1000))]);
+        let mut state = TransitionState::new(&config, from).unwrap();
+        let start = Instant::now();
+        state.render(None, deep(3000), true, true, start);
+
+        let DynamicImage::ImageRgba16(frame) =
+            state.render(None, deep(3000), true, true, start + Duration::from_millis(50))
+        else {
+            panic!("expected a 16-bit frame");
+        };
+        assert_eq!(frame.get_pixel(0, 0).0[0], 2000);
+
+        // An 8-bit target of a deep source is blended at 16 bits too.
+        let from = HashMap::from([(FrameKey::new(None, (8, 2)), deep(1000))]);
+        let mut state = TransitionState::new(&config, from).unwrap();
+        let drawn = state.render(None, DynamicImage::ImageRgba8(solid(0)), false, false, start);
+        assert!(matches!(drawn, DynamicImage::ImageRgba16(_)));
+    }
+
+    #[test]
+    fn test_resume_partway() {
+        let from = HashMap::from([(
+            FrameKey::new(None, (8, 2)),
+            DynamicImage::ImageRgba8(solid(0)),
+        )]);
+        let duration = Duration::from_secs(512);
+
+        let mut state =
+            TransitionState::resume(TransitionKind::Crossfade, duration, duration / 2, from)
+                .unwrap();
+        assert_eq!(state.frame_interval(), Duration::from_secs(2));
+
+        let start = Instant::now();
+        let first = state.render(None, DynamicImage::ImageRgba8(solid(200)), true, false, start);
+        assert_eq!(first.to_rgba8(), solid(100));
+        assert!(state.is_finished(start + duration / 2));
+    }
+}
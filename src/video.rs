@@ -7,6 +7,7 @@ use gstreamer::prelude::*;
 use gstreamer_app as gst_app;
 use image::{DynamicImage, ImageBuffer, Rgba};
 use std::{
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
@@ -29,6 +30,169 @@ fn link_elements(elements: &[&gst::Element]) -> Result<(), SourceError> {
         .map_err(|e| gst_error(format!("Failed to link elements: {}", e)))
 }
 
+/// Link the video pad `decodebin` adds once it knows the stream to
+/// `videoconvert`.
+fn link_video_pad(decodebin: &gst::Element, videoconvert: &gst::Element) {
+    let videoconvert_weak = videoconvert.downgrade();
+    decodebin.connect_pad_added(move |_src, src_pad| {
+        let Some(videoconvert) = videoconvert_weak.upgrade() else {
+            return;
+        };
+
+        let sink_pad = videoconvert
+            .static_pad("sink")
+            .expect("videoconvert has no sink pad");
+
+        if sink_pad.is_linked() {
+            return;
+        }
+
+        let Some(caps) = src_pad.current_caps() else {
+            return;
+        };
+        let Some(structure) = caps.structure(0) else {
+            return;
+        };
+        let name = structure.name();
+
+        if name.starts_with("video/") {
+            if let Err(e) = src_pad.link(&sink_pad) {
+                tracing::error!("Failed to link decodebin pad: {}", e);
+            }
+        }
+    });
+}
+
+/// Copy an RGBA sample from an appsink into an image.
+fn sample_image(sample: &gst::SampleRef) -> Option<DynamicImage> {
+    let buffer = sample.buffer()?;
+    let map = buffer.map_readable().ok()?;
+    let structure = sample.caps()?.structure(0)?;
+
+    let width = structure.get::<i32>("width").ok()? as u32;
+    let height = structure.get::<i32>("height").ok()? as u32;
+
+    ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, map.as_slice().to_vec())
+        .map(DynamicImage::ImageRgba8)
+}
+
+/// Frames of a short clip, such as an AVIF image sequence, with the time
+/// each is shown for, decoded as they are taken.
+pub struct ClipFrames {
+    path: PathBuf,
+    pipeline: gst::Pipeline,
+    appsink: gst_app::AppSink,
+    decoded: usize,
+    done: bool,
+}
+
+impl ClipFrames {
+    /// Start decoding the clip at `path`.
+    pub fn open(path: &Path) -> Result<Self, SourceError> {
+        gst::init().map_err(|e| gst_error(format!("GStreamer initialization failed: {}", e)))?;
+
+        let location = path.to_str().ok_or_else(|| gst_error("Invalid video path"))?;
+
+        let pipeline = gst::Pipeline::new();
+
+        let filesrc = gst::ElementFactory::make("filesrc")
+            .property("location", location)
+            .build()
+            .map_err(|e| gst_error(format!("Failed to create filesrc: {}", e)))?;
+
+        let decodebin = create_element("decodebin")?;
+        let videoconvert = create_element("videoconvert")?;
+
+        // Without clock sync, frames arrive as fast as they decode, and
+        // holding a single sample keeps decoding from running ahead of them
+        // being taken.
+        let appsink = gst_app::AppSink::builder()
+            .caps(&gst::Caps::builder("video/x-raw").field("format", "RGBA").build())
+            .sync(false)
+            .max_buffers(1)
+            .build();
+
+        pipeline
+            .add_many([&filesrc, &decodebin, &videoconvert, appsink.upcast_ref()])
+            .map_err(|e| gst_error(format!("Failed to add elements to pipeline: {}", e)))?;
+
+        link_elements(&[&filesrc, &decodebin])?;
+        link_elements(&[&videoconvert, appsink.upcast_ref()])?;
+        link_video_pad(&decodebin, &videoconvert);
+
+        pipeline
+            .set_state(gst::State::Playing)
+            .map_err(|e| gst_error(format!("Failed to start decoding: {}", e)))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            pipeline,
+            appsink,
+            decoded: 0,
+            done: false,
+        })
+    }
+
+    /// Why no frames came out of the clip
+    fn failure(&self) -> SourceError {
+        let error = self
+            .pipeline
+            .bus()
+            .and_then(|bus| bus.pop_filtered(&[gst::MessageType::Error]));
+
+        match error.as_ref().map(|msg| msg.view()) {
+            Some(gst::MessageView::Error(err)) => gst_error(format!(
+                "Failed to decode {}: {}",
+                self.path.display(),
+                err.error()
+            )),
+            _ => gst_error(format!("No frames decoded from {}", self.path.display())),
+        }
+    }
+}
+
+impl Iterator for ClipFrames {
+    type Item = Result<(DynamicImage, Duration), SourceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            // A pipeline that fails never reaches the end of the stream, so
+            // waiting for a sample gives up after a while.
+            let Some(sample) = self
+                .appsink
+                .try_pull_sample(gst::ClockTime::from_seconds(10))
+            else {
+                self.done = true;
+                if self.decoded == 0 {
+                    return Some(Err(self.failure()));
+                }
+                break;
+            };
+            let Some(image) = sample_image(&sample) else {
+                continue;
+            };
+
+            let duration = sample
+                .buffer()
+                .and_then(|buffer| buffer.duration())
+                .map_or(crate::source::DEFAULT_FRAME_DURATION, |duration| {
+                    Duration::from_nanos(duration.nseconds())
+                });
+
+            self.decoded += 1;
+            return Some(Ok((image, duration)));
+        }
+
+        None
+    }
+}
+
+impl Drop for ClipFrames {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gst::State::Null);
+    }
+}
+
 /// Video wallpaper source with GStreamer backend
 #[derive(Debug)]
 pub struct VideoSource {
@@ -40,6 +204,8 @@ pub struct VideoSource {
     frame_duration: Duration,
     is_playing: bool,
     is_prepared: bool,
+    /// Set once playback reaches the end of the file
+    reached_end: bool,
 }
 
 impl VideoSource {
@@ -57,6 +223,7 @@ impl VideoSource {
             frame_duration: crate::source::DEFAULT_FRAME_DURATION,
             is_playing: false,
             is_prepared: false,
+            reached_end: false,
         })
     }
 
@@ -121,34 +288,7 @@ impl VideoSource {
         link_elements(&[&videoconvert, &videoscale, appsink.upcast_ref()])?;
 
         // Handle dynamic pad linking from decodebin
-        let videoconvert_weak = videoconvert.downgrade();
-        decodebin.connect_pad_added(move |_src, src_pad| {
-            let Some(videoconvert) = videoconvert_weak.upgrade() else {
-                return;
-            };
-
-            let sink_pad = videoconvert
-                .static_pad("sink")
-                .expect("videoconvert has no sink pad");
-
-            if sink_pad.is_linked() {
-                return;
-            }
-
-            let Some(caps) = src_pad.current_caps() else {
-                return;
-            };
-            let Some(structure) = caps.structure(0) else {
-                return;
-            };
-            let name = structure.name();
-
-            if name.starts_with("video/") {
-                if let Err(e) = src_pad.link(&sink_pad) {
-                    tracing::error!("Failed to link decodebin pad: {}", e);
-                }
-            }
-        });
+        link_video_pad(&decodebin, &videoconvert);
 
         // Setup appsink callbacks
         let current_frame = Arc::clone(&self.current_frame);
@@ -157,27 +297,9 @@ impl VideoSource {
                 .new_sample(move |appsink| {
                     let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Error)?;
 
-                    if let Some(buffer) = sample.buffer() {
-                        let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
-                        let caps = sample.caps().ok_or(gst::FlowError::Error)?;
-                        let structure = caps.structure(0).ok_or(gst::FlowError::Error)?;
-
-                        let width = structure
-                            .get::<i32>("width")
-                            .map_err(|_| gst::FlowError::Error)? as u32;
-                        let height = structure
-                            .get::<i32>("height")
-                            .map_err(|_| gst::FlowError::Error)? as u32;
-
-                        if let Some(img_buffer) = ImageBuffer::<Rgba<u8>, _>::from_raw(
-                            width,
-                            height,
-                            map.as_slice().to_vec(),
-                        ) {
-                            let image = DynamicImage::ImageRgba8(img_buffer);
-                            if let Ok(mut frame) = current_frame.lock() {
-                                *frame = Some(image);
-                            }
+                    if let Some(image) = sample_image(&sample) {
+                        if let Ok(mut frame) = current_frame.lock() {
+                            *frame = Some(image);
                         }
                     }
 
@@ -239,11 +361,7 @@ impl VideoSource {
     }
 
     /// Pause video playback
-    ///
-    /// Part of the video control API. Not yet exposed to config but available
-    /// for future interactive controls or power management integration.
-    #[allow(dead_code)]
-    fn pause(&mut self) -> Result<(), SourceError> {
+    fn pause_playback(&mut self) -> Result<(), SourceError> {
         if let Some(ref pipeline) = self.pipeline {
             pipeline
                 .set_state(gst::State::Paused)
@@ -256,10 +374,6 @@ impl VideoSource {
 
     /// Check if video has reached end and loop if configured
     fn check_eos(&mut self) -> Result<(), SourceError> {
-        if !self.config.loop_playback {
-            return Ok(());
-        }
-
         let Some(ref pipeline) = self.pipeline else {
             return Ok(());
         };
@@ -274,6 +388,11 @@ impl VideoSource {
         };
 
         if let gst::MessageView::Eos(_) = msg.view() {
+            self.reached_end = true;
+            if !self.config.loop_playback {
+                return Ok(());
+            }
+
             tracing::debug!("Video reached end, looping");
             // Seek back to start
             pipeline
@@ -332,6 +451,11 @@ impl WallpaperSource for VideoSource {
         true
     }
 
+    fn played_through(&self) -> bool {
+        // A video that failed to start has nothing left to play.
+        self.reached_end || self.pipeline.is_none()
+    }
+
     fn prepare(&mut self, width: u32, height: u32) -> Result<(), SourceError> {
         self.target_size = Some((width, height));
 
@@ -355,10 +479,23 @@ impl WallpaperSource for VideoSource {
         self.current_frame = Arc::new(Mutex::new(None));
         self.is_playing = false;
         self.is_prepared = false;
+        self.reached_end = false;
 
         tracing::debug!("Video source released");
     }
 
+    fn pause(&mut self) {
+        if let Err(why) = self.pause_playback() {
+            tracing::warn!(?why, "failed to pause video");
+        }
+    }
+
+    fn resume(&mut self) {
+        if let Err(why) = self.play() {
+            tracing::warn!(?why, "failed to resume video");
+        }
+    }
+
     fn description(&self) -> String {
         format!(
             "Video: {} (loop: {}, hw_accel: {})",
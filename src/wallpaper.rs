@@ -2,22 +2,34 @@
 
 use crate::{CosmicBg, CosmicBgLayer};
 use crate::animated::AnimatedSource;
+use crate::cache::CacheKey;
+use crate::gnome_xml::{self, GnomeSlideshow, SlideshowFrame};
+use crate::icc;
+use crate::ken_burns::{self, Motion};
+use crate::loader::{LoaderHandle, LoaderResult, LoadingState};
+use crate::scaler::ScaleSettings;
+use crate::schedule::{self, TimedSource};
 use crate::shader::ShaderSource;
 use crate::source::WallpaperSource;
+use crate::span::{self, SpanLayout, SpanOutput};
+use crate::transition::{FrameKey, TransitionState};
 use crate::video::VideoSource;
 
 use std::{
-    collections::VecDeque,
-    fs::{self, File},
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
     path::{Path, PathBuf},
+    sync::Arc,
     time::{Duration, Instant},
 };
 
-use cosmic_ext_bg_config::{Color, Entry, SamplingMethod, ScalingMode, Source, state::State};
+use cosmic_ext_bg_config::{
+    Color, Entry, FilterMethod, KenBurns, SamplingMethod, ScalingMode, Source, TransitionKind,
+    ipc::OutputStatus, state::State,
+};
+use chrono::Local;
 use cosmic_config::CosmicConfigEntry;
-use eyre::eyre;
-use image::{DynamicImage, ImageReader};
-use jxl_oxide::integration::JxlDecoder;
+use image::DynamicImage;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use rand::{rng, seq::SliceRandom};
 use sctk::{
@@ -32,17 +44,21 @@ use sctk::{
 };
 use thiserror::Error;
 use tracing::error;
-use walkdir::WalkDir;
 
-// TODO filter images by whether they seem to match dark / light mode
-// Alternatively only load from light / dark subdirectories given a directory source when this is active
+/// How soon a crossfade picked up partway through, in a GNOME slideshow or
+/// across twilight, checks again for frames to fade from.
+const FADE_RETRY: Duration = Duration::from_secs(1);
+
+/// How often a rotation held by `hold_animations` checks whether the
+/// animation or video on screen has played through.
+const HOLD_RETRY: Duration = Duration::from_millis(250);
 
 #[derive(Debug, Error)]
 pub enum DrawError {
     #[error("no source configured for wallpaper")]
     NoSource,
-    #[error("failed to decode JPEG XL image: {0}")]
-    JpegXlDecode(#[from] eyre::Report),
+    #[error("image is still loading")]
+    Loading,
     #[error("failed to decode image from {path}: {reason}")]
     ImageDecode { path: PathBuf, reason: String },
     #[error("invalid color gradient in config")]
@@ -59,12 +75,49 @@ pub struct Wallpaper {
     queue_handle: QueueHandle<CosmicBg>,
     current_source: Option<Source>,
     // Cache of source image, if `current_source` is a `Source::Path`
-    current_image: Option<image::DynamicImage>,
+    current_image: Option<Arc<DynamicImage>>,
+    // Progress of the background scan or decode for `current_source`
+    loading_state: LoadingState,
+    // How long the Ken Burns motion has been running on the images on screen
+    ken_burns_clock: ken_burns::Clock,
+    loader: LoaderHandle,
     timer_token: Option<RegistrationToken>,
-    // Persistent animated source for videos/GIFs/shaders
+    // Schedule or solar source `entry.source` was resolved from, if any
+    schedule: Option<TimedSource>,
+    // Timer switching to the next source of `schedule`
+    schedule_token: Option<RegistrationToken>,
+    // Filesystem events of `watch_source`, kept for schedule slot changes
+    source_tx: calloop::channel::SyncSender<(String, notify::Event)>,
+    // GNOME background XML slideshow `entry.source` points at, if any
+    slideshow: Option<GnomeSlideshow>,
+    // Timer moving to the next item of `slideshow`
+    slideshow_token: Option<RegistrationToken>,
+    // Persistent animated source for videos/GIFs/shaders, whose frames are
+    // driven by the `FrameScheduler` in `CosmicBg`. Slideshows use it for the
+    // animation or video on screen.
     animated_source: Option<Box<dyn WallpaperSource>>,
-    // Timer for animation frames
-    animation_timer_token: Option<RegistrationToken>,
+    // When the rotation timer fires next, if one is registered
+    next_rotation: Option<Instant>,
+    // Set through the control socket to halt rotation and playback
+    paused: bool,
+    // Image shown on each output, when `distinct_per_output` is set
+    output_images: HashMap<String, PathBuf>,
+    // Decoded sources of `output_images`
+    output_originals: HashMap<PathBuf, Arc<DynamicImage>>,
+    // Images of `output_images` being decoded by the loader
+    pending_decodes: HashSet<PathBuf>,
+    // Vector images being rasterized for a layer size by the loader
+    pending_renders: HashSet<CacheKey>,
+    // Restore the images outputs showed last time on the next assignment
+    resume_outputs: bool,
+    // Last frame drawn for each buffer size, kept while transitions are enabled
+    shown_frames: HashMap<FrameKey, DynamicImage>,
+    // Transition from the previous wallpaper, while one is running
+    transition: Option<TransitionState>,
+    // Crops of the last span mode frame
+    span_layout: Option<SpanLayout>,
+    // Conversions to `entry.color_profiles`, by output name
+    display_profiles: HashMap<String, icc::Conversion>,
     // Filesystem watcher for live wallpaper directory updates.
     // Must be stored here to keep the watcher alive for the lifetime of this wallpaper.
     _watcher: Option<RecommendedWatcher>,
@@ -78,9 +131,13 @@ impl std::fmt::Debug for Wallpaper {
             .field("image_queue", &self.image_queue)
             .field("current_source", &self.current_source)
             .field("current_image", &self.current_image.as_ref().map(|_| "<DynamicImage>"))
+            .field("loading_state", &self.loading_state)
             .field("timer_token", &self.timer_token)
+            .field("schedule", &self.schedule)
+            .field("slideshow", &self.slideshow.is_some())
             .field("animated_source", &self.animated_source.as_ref().map(|s| s.description()))
-            .field("animation_timer_token", &self.animation_timer_token)
+            .field("paused", &self.paused)
+            .field("transition", &self.transition)
             .finish_non_exhaustive()
     }
 }
@@ -90,7 +147,10 @@ impl Drop for Wallpaper {
         if let Some(token) = self.timer_token.take() {
             self.loop_handle.remove(token);
         }
-        if let Some(token) = self.animation_timer_token.take() {
+        if let Some(token) = self.schedule_token.take() {
+            self.loop_handle.remove(token);
+        }
+        if let Some(token) = self.slideshow_token.take() {
             self.loop_handle.remove(token);
         }
     }
@@ -102,16 +162,38 @@ impl Wallpaper {
         queue_handle: QueueHandle<CosmicBg>,
         loop_handle: calloop::LoopHandle<'static, CosmicBg>,
         source_tx: calloop::channel::SyncSender<(String, notify::Event)>,
+        loader: LoaderHandle,
     ) -> Self {
+        let (entry, schedule) = schedule::resolve(entry, Local::now());
+        let display_profiles = load_display_profiles(&entry);
+
         let mut wallpaper = Wallpaper {
             entry,
             layers: Vec::new(),
             current_source: None,
             current_image: None,
+            loading_state: LoadingState::Idle,
+            ken_burns_clock: ken_burns::Clock::default(),
+            loader,
             image_queue: VecDeque::default(),
             timer_token: None,
+            schedule,
+            schedule_token: None,
+            source_tx: source_tx.clone(),
+            slideshow: None,
+            slideshow_token: None,
             animated_source: None,
-            animation_timer_token: None,
+            next_rotation: None,
+            paused: false,
+            output_images: HashMap::new(),
+            output_originals: HashMap::new(),
+            pending_decodes: HashSet::new(),
+            pending_renders: HashSet::new(),
+            resume_outputs: false,
+            shown_frames: HashMap::new(),
+            transition: None,
+            span_layout: None,
+            display_profiles,
             _watcher: None,
             loop_handle,
             queue_handle,
@@ -119,105 +201,912 @@ impl Wallpaper {
 
         wallpaper.load_images();
         wallpaper.register_timer();
+        wallpaper.register_schedule_timer();
         wallpaper.watch_source(source_tx);
         wallpaper
     }
 
     /// Update the wallpaper configuration without full recreation.
     ///
-    /// This preserves the image cache and only updates changed settings,
-    /// avoiding unnecessary file I/O and memory allocations.
+    /// Only what the change affects is reloaded: layer surfaces and their
+    /// pools are always kept, and an animated source survives anything but a
+    /// change of `source`, so video position and shader time are preserved.
     ///
-    /// Currently unused - wallpapers are recreated on config changes. This method
-    /// provides an optimization path for hot-reloading config without full teardown.
-    #[allow(dead_code)]
-    pub fn update_config(&mut self, new_entry: Entry) {
+    /// A schedule or solar entry is applied with the source active now.
+    ///
+    /// Returns `false` if the entry was unchanged.
+    pub fn update_config(
+        &mut self,
+        new_entry: Entry,
+        source_tx: calloop::channel::SyncSender<(String, notify::Event)>,
+    ) -> bool {
+        let (new_entry, schedule) = schedule::resolve(new_entry, Local::now());
+        self.source_tx = source_tx;
+
+        let schedule_changed = self.schedule != schedule;
+        if schedule_changed {
+            self.schedule = schedule;
+            self.register_schedule_timer();
+        }
+
+        self.apply_entry(new_entry) || schedule_changed
+    }
+
+    /// Switch to the source the schedule has active now, crossfading from
+    /// the frames on screen if it is partway through a fade.
+    fn apply_schedule(&mut self) {
+        let Some(schedule) = self.schedule.as_ref() else {
+            return;
+        };
+
+        let active = schedule.active(Local::now());
+        if active.source == self.entry.source {
+            return;
+        }
+
+        if let Some(fade) = active.fade {
+            // Retried once the source being faded from has been drawn.
+            if self.shown_frames.is_empty() {
+                return;
+            }
+
+            let shown = std::mem::take(&mut self.shown_frames);
+            self.transition =
+                TransitionState::resume(TransitionKind::Crossfade, fade.duration, fade.elapsed, shown);
+        }
+
+        tracing::info!(output = %self.entry.output, "switching to the next scheduled source");
+
+        let mut entry = self.entry.clone();
+        entry.source = active.source;
+        self.apply_entry(entry);
+    }
+
+    /// How long to wait before checking the schedule again.
+    fn schedule_delay(&self) -> Option<Duration> {
+        let schedule = self.schedule.as_ref()?;
+        let now = Local::now();
+
+        if schedule.active(now).source != self.entry.source {
+            // A fade is waiting for a frame to start from.
+            return Some(FADE_RETRY);
+        }
+
+        Some(schedule.next_check(now))
+    }
+
+    /// Whether drawn frames are kept for transitions to start from.
+    fn records_frames(&self) -> bool {
+        self.entry.transition.is_enabled()
+            || self.slideshow.is_some()
+            || self.schedule.as_ref().is_some_and(TimedSource::fades)
+    }
+
+    fn apply_entry(&mut self, new_entry: Entry) -> bool {
+        if self.entry == new_entry {
+            return false;
+        }
+
+        let was_distinct = self.distinct_outputs();
         let rotation_changed = self.entry.rotation_frequency != new_entry.rotation_frequency;
-        let scaling_changed = self.entry.scaling_mode != new_entry.scaling_mode;
         let source_changed = self.entry.source != new_entry.source;
+        let images_changed = source_changed
+            || self.entry.sampling_method != new_entry.sampling_method
+            || self.entry.filter_by_theme != new_entry.filter_by_theme;
+        let redraw_needed = images_changed
+            || self.entry.scaling_mode != new_entry.scaling_mode
+            || self.entry.span != new_entry.span
+            || self.entry.distinct_per_output != new_entry.distinct_per_output
+            || self.entry.filter_method != new_entry.filter_method
+            || self.entry.adjustments != new_entry.adjustments
+            || self.entry.color_depth != new_entry.color_depth
+            || self.entry.color_profiles != new_entry.color_profiles;
 
         tracing::debug!(
             output = %self.entry.output,
             rotation_changed,
-            scaling_changed,
             source_changed,
+            images_changed,
+            redraw_needed,
             "Updating wallpaper config"
         );
 
+        if self.entry.distinct_per_output != new_entry.distinct_per_output {
+            self.output_images.clear();
+            self.resume_outputs = true;
+        }
+
+        if self.entry.color_profiles != new_entry.color_profiles {
+            self.display_profiles = load_display_profiles(&new_entry);
+        }
+
         // Update the entry
         self.entry = new_entry;
 
-        // If source changed, reload images (this will be called from apply_backgrounds)
         if source_changed {
-            self.current_image = None;
-            // Clear animated source and timer
-            if let Some(token) = self.animation_timer_token.take() {
-                self.loop_handle.remove(token);
-            }
+            self.begin_transition();
+            self.forget_cached_images();
+
+            // A new animated source gets scheduled by `CosmicBg`.
             self.animated_source = None;
+            self.current_source = None;
+            self.watch_source(self.source_tx.clone());
+        }
+
+        // Animated sources are only rebuilt when the source itself changed.
+        if images_changed && (source_changed || matches!(self.entry.source, Source::Path(_))) {
+            self.current_image = None;
             self.load_images();
+        } else if self.distinct_outputs() != was_distinct {
+            // Outputs with their own images only show stills, and a shared
+            // one may be an animation again.
+            self.animated_source = None;
+            self.clear_image();
         }
 
-        // Re-register timer if rotation frequency changed
-        if rotation_changed {
-            if let Some(token) = self.timer_token.take() {
-                self.loop_handle.remove(token);
-            }
-            self.register_timer();
+        // Re-register timer if rotation frequency changed, or the source
+        // changed to or from a timed slideshow
+        if rotation_changed || source_changed {
+            self.restart_timer();
         }
 
-        // Trigger redraw if scaling mode changed
-        if scaling_changed {
+        if redraw_needed {
             for layer in &mut self.layers {
                 layer.needs_redraw = true;
             }
+            self.draw();
+            self.prefetch_next();
+        }
+
+        true
+    }
+
+    /// Step the slideshow forward or backward by one image.
+    ///
+    /// The back of `image_queue` is the image on screen, so stepping forward
+    /// moves the front to the back and stepping backward does the reverse.
+    /// Returns `false` if there is nothing to rotate to.
+    pub fn rotate(&mut self, forward: bool) -> bool {
+        let rotated = self.step(forward);
+
+        // A manual step starts a fresh rotation interval.
+        if rotated && self.timer_token.is_some() {
+            self.restart_timer();
+        }
+
+        rotated
+    }
+
+    fn step(&mut self, forward: bool) -> bool {
+        // GNOME slideshows follow the clock.
+        if self.slideshow.is_some() {
+            return false;
+        }
+
+        if self.distinct_outputs() {
+            return self.step_outputs(forward);
+        }
+
+        // Skip rotation when there's only one image — it would
+        // re-decode and re-draw the same wallpaper.
+        if self.image_queue.len() <= 1 {
+            return false;
+        }
+
+        let next = if forward {
+            let Some(next) = self.image_queue.pop_front() else {
+                return false;
+            };
+            self.image_queue.push_back(next.clone());
+            next
+        } else {
+            let Some(current) = self.image_queue.pop_back() else {
+                return false;
+            };
+            self.image_queue.push_front(current);
+            let Some(previous) = self.image_queue.back().cloned() else {
+                return false;
+            };
+            previous
+        };
+
+        self.begin_transition();
+        self.show_image(next);
+
+        true
+    }
+
+    /// Make `path` the image on screen.
+    fn show_image(&mut self, path: PathBuf) {
+        self.clear_image();
+        self.current_source = Some(Source::Path(path.clone()));
+        self.animated_source = None;
+
+        // Prefetched ahead of time, so this is usually just a buffer swap.
+        // Animations and videos are opened by the loader on the next draw.
+        if let Some(image) = self.loader.cache().get(&CacheKey::original(&path)) {
+            self.current_image = Some(image);
+            self.loading_state = LoadingState::Ready;
         }
+
+        if let Err(err) = self.save_state() {
+            error!("{err}");
+        }
+
+        self.draw();
+
+        // Otherwise prefetching resumes once the pending decode arrives.
+        if !matches!(self.loading_state, LoadingState::LoadingImage(_)) {
+            self.prefetch_next();
+        }
+    }
+
+    /// Whether rotation waits for the animation or video on screen to play
+    /// through once, as `hold_animations` asks.
+    fn holds_rotation(&self) -> bool {
+        self.entry.hold_animations
+            && matches!(self.entry.source, Source::Path(_))
+            && self
+                .animated_source
+                .as_ref()
+                .is_some_and(|source| source.is_animated() && !source.played_through())
+    }
+
+    /// Show what the GNOME slideshow has on screen now, crossfading during
+    /// its transitions. Returns the time until it changes again.
+    fn advance_slideshow(&mut self) -> Option<Duration> {
+        let frame = self.slideshow.as_ref()?.frame_now();
+        let remaining = frame.remaining();
+
+        let (file, next) = match frame {
+            SlideshowFrame::Static { file, next, .. } => (file, next),
+            SlideshowFrame::Transition {
+                from,
+                to,
+                elapsed,
+                duration,
+            } => {
+                if self.current_source.as_ref() == Some(&Source::Path(to.clone())) {
+                    return Some(remaining);
+                }
+
+                // Fade from the frames on screen, which need drawing first
+                // when the slideshow starts partway through a transition.
+                if self.shown_frames.is_empty() {
+                    self.show_slideshow_image(from, to);
+                    return Some(remaining.min(FADE_RETRY));
+                }
+
+                let shown = std::mem::take(&mut self.shown_frames);
+                self.transition =
+                    TransitionState::resume(TransitionKind::Crossfade, duration, elapsed, shown);
+                (to.clone(), to)
+            }
+        };
+
+        self.show_slideshow_image(file, next);
+        Some(remaining)
+    }
+
+    /// Show `file` of the GNOME slideshow, with `next` prefetched.
+    fn show_slideshow_image(&mut self, file: PathBuf, next: PathBuf) {
+        self.image_queue = if next == file {
+            VecDeque::from([file.clone()])
+        } else {
+            VecDeque::from([next, file.clone()])
+        };
+
+        if self.current_source.as_ref() != Some(&Source::Path(file.clone())) {
+            self.show_image(file);
+        }
+    }
+
+    /// Step every output to its next image at once.
+    ///
+    /// The back of `image_queue` holds one image per output, so the queue
+    /// rotates by that many images.
+    fn step_outputs(&mut self, forward: bool) -> bool {
+        let slots = self.output_images.len().min(self.image_queue.len());
+        if slots == 0 || self.image_queue.len() <= slots {
+            return false;
+        }
+
+        if forward {
+            self.image_queue.rotate_left(slots);
+        } else {
+            self.image_queue.rotate_right(slots);
+        }
+
+        self.begin_transition();
+
+        // Reassigned on the next draw.
+        self.output_images.clear();
+        self.ken_burns_clock.reset();
+        for layer in &mut self.layers {
+            layer.needs_redraw = true;
+        }
+
+        self.draw();
+        self.prefetch_next();
+
+        true
+    }
+
+    /// Whether each output shows its own image of the slideshow.
+    ///
+    /// Span mode takes precedence, as it shows a single image.
+    fn distinct_outputs(&self) -> bool {
+        self.entry.distinct_per_output
+            && self.entry.span.is_none()
+            && self.slideshow.is_none()
+            && matches!(self.entry.source, Source::Path(_))
+    }
+
+    /// Give each output its own image from the back of the queue, which holds
+    /// the images on screen. Outputs only share an image when there are more
+    /// outputs than images.
+    fn assign_output_images(&mut self) {
+        let mut outputs: Vec<String> = self
+            .layers
+            .iter()
+            .filter_map(|layer| layer.output_info.name.clone())
+            .collect();
+        outputs.sort_unstable();
+        outputs.dedup();
+
+        let assigned = !self.resume_outputs
+            && outputs.len() == self.output_images.len()
+            && outputs.iter().all(|output| self.output_images.contains_key(output));
+        if assigned {
+            return;
+        }
+
+        let slots = outputs.len().min(self.image_queue.len());
+        if slots == 0 {
+            self.output_images.clear();
+            return;
+        }
+
+        if std::mem::take(&mut self.resume_outputs) {
+            self.restore_output_images(&outputs[..slots]);
+        }
+
+        let on_screen = self.image_queue.len() - slots;
+        self.output_images = outputs
+            .iter()
+            .enumerate()
+            .map(|(i, output)| (output.clone(), self.image_queue[on_screen + i % slots].clone()))
+            .collect();
+
+        self.output_originals
+            .retain(|path, _| self.output_images.values().any(|p| p == path));
+        self.current_source = outputs
+            .first()
+            .and_then(|output| self.output_images.get(output))
+            .cloned()
+            .map(Source::Path);
+
+        for layer in &mut self.layers {
+            layer.needs_redraw = true;
+        }
+
+        if let Err(err) = self.save_state() {
+            error!("{err}");
+        }
+    }
+
+    /// Move the image each output showed last time to the back of the queue,
+    /// in output order, filling in for outputs without one.
+    fn restore_output_images(&mut self, outputs: &[String]) {
+        let mut restored: Vec<Option<PathBuf>> = Vec::with_capacity(outputs.len());
+        for output in outputs {
+            let saved = match current_image(output) {
+                Some(Source::Path(path)) => Some(path),
+                _ => None,
+            };
+            let saved = saved.filter(|path| {
+                self.image_queue.contains(path) && !restored.iter().flatten().any(|p| p == path)
+            });
+            restored.push(saved);
+        }
+
+        self.image_queue
+            .retain(|path| !restored.iter().flatten().any(|p| p == path));
+
+        let missing = restored.iter().filter(|path| path.is_none()).count();
+        let mut fill = self
+            .image_queue
+            .split_off(self.image_queue.len().saturating_sub(missing));
+
+        for path in restored {
+            if let Some(path) = path.or_else(|| fill.pop_front()) {
+                self.image_queue.push_back(path);
+            }
+        }
+    }
+
+    /// Decode the upcoming slideshow images and scale them for every layer
+    /// in the background, so the next rotation can use them straight from
+    /// the cache.
+    fn prefetch_next(&self) {
+        let upcoming = if self.distinct_outputs() {
+            self.output_images.len().max(1)
+        } else {
+            1
+        };
+
+        if self.image_queue.len() <= upcoming {
+            return;
+        }
+
+        // Ken Burns frames are cropped from the original, so only decode it.
+        let sizes = if self.ken_burns().is_some() {
+            Vec::new()
+        } else {
+            self.render_sizes()
+        };
+
+        for next in self.image_queue.iter().take(upcoming) {
+            self.loader.request_prefetch(
+                self.entry.output.clone(),
+                next.clone(),
+                sizes.clone(),
+                ScaleSettings::new(&self.entry),
+            );
+        }
+    }
+
+    /// Start transitioning away from the frames on screen, if the entry has
+    /// a transition configured.
+    ///
+    /// The transition's clock starts once a frame of the new wallpaper is
+    /// drawn, so it waits out any pending scan or decode. A transition that
+    /// has not drawn anything yet is kept, along with its frames.
+    fn begin_transition(&mut self) {
+        let shown = std::mem::take(&mut self.shown_frames);
+        if let Some(transition) = TransitionState::new(&self.entry.transition, shown) {
+            self.transition = Some(transition);
+        }
+    }
+
+    /// Buffer sizes the current source is rendered at: the span canvas, or
+    /// the size of every layer.
+    fn render_sizes(&self) -> Vec<(u32, u32)> {
+        if self.entry.span.is_some() {
+            return self.span_layout.iter().map(|layout| layout.size).collect();
+        }
+
+        let mut sizes: Vec<(u32, u32)> = self
+            .layers
+            .iter()
+            .filter_map(|layer| self.calculate_layer_dimensions(layer).ok())
+            .collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    }
+
+    /// Identifies the settings pre-scaled cache entries were produced with.
+    fn scale_fingerprint(&self) -> u64 {
+        ScaleSettings::new(&self.entry).fingerprint()
+    }
+
+    /// Drop the cached copies of the images on screen, which a new source
+    /// won't show again.
+    fn forget_cached_images(&self) {
+        let cache = self.loader.cache();
+        let fingerprint = self.scale_fingerprint();
+        let current = match &self.current_source {
+            Some(Source::Path(path)) => Some(path),
+            _ => None,
+        };
+
+        for path in current.into_iter().chain(self.output_images.values()) {
+            cache.remove(&CacheKey::original(path));
+            for layer in &self.layers {
+                if let Ok(size) = self.calculate_layer_dimensions(layer) {
+                    cache.remove(&CacheKey::scaled(path, size, fingerprint));
+                }
+            }
+        }
+    }
+
+    /// Interval until the next animation frame, or `None` if nothing is
+    /// animating.
+    ///
+    /// Transitions also run while paused, so a manual step still animates.
+    pub fn animation_frame_duration(&self) -> Option<Duration> {
+        let transition = self
+            .transition
+            .as_ref()
+            .filter(|transition| transition.is_started())
+            .map(TransitionState::frame_interval);
+
+        if self.paused {
+            return transition;
+        }
+
+        let animated = self
+            .animated_source
+            .as_ref()
+            .map(|source| source.frame_duration())
+            .or_else(|| self.ken_burns_interval());
+
+        match (animated, transition) {
+            (Some(animated), Some(transition)) => Some(animated.min(transition)),
+            (animated, transition) => animated.or(transition),
+        }
+    }
+
+    /// Ken Burns settings, if the entry pans and zooms across still images.
+    fn ken_burns(&self) -> Option<&KenBurns> {
+        match &self.entry.scaling_mode {
+            ScalingMode::KenBurns(ken_burns) if self.animated_source.is_none() => Some(ken_burns),
+            _ => None,
+        }
+    }
+
+    /// Interval between Ken Burns frames, once there is an image to move
+    /// across.
+    fn ken_burns_interval(&self) -> Option<Duration> {
+        let has_image = self.current_image.is_some() || !self.output_originals.is_empty();
+
+        self.ken_burns()
+            .filter(|_| has_image)
+            .map(|ken_burns| Duration::from_secs(1) / ken_burns.clamped_fps())
+    }
+
+    /// Render the next frame of the animated source on every layer the
+    /// compositor is ready for.
+    ///
+    /// Layers still waiting on a frame callback are deferred until it arrives,
+    /// so hidden or throttled outputs don't render at all.
+    pub fn draw_animation_frame(&mut self) {
+        let span = tracing::debug_span!("Wallpaper::draw_animation_frame");
+        let _handle = span.enter();
+
+        for layer in &mut self.layers {
+            if layer.frame_pending {
+                layer.frame_deferred = true;
+            } else {
+                layer.needs_redraw = true;
+            }
+        }
+        self.draw();
+    }
+
+    /// Whether every layer has a deferred frame waiting on the compositor.
+    pub fn awaiting_frame_callbacks(&self) -> bool {
+        !self.layers.is_empty() && self.layers.iter().all(|layer| layer.frame_deferred)
+    }
+
+    /// Pause or resume slideshow rotation and animation playback.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+
+        self.paused = paused;
+
+        if paused {
+            self.ken_burns_clock.pause(Instant::now());
+        } else {
+            self.ken_burns_clock.resume(Instant::now());
+        }
+
+        if let Some(source) = self.animated_source.as_mut() {
+            if paused {
+                source.pause();
+            } else {
+                source.resume();
+            }
+        }
+
+        // Resuming restarts the interval instead of rotating immediately.
+        if !paused && self.timer_token.is_some() {
+            self.restart_timer();
+        }
+    }
+
+    /// Runtime status of each output showing this wallpaper.
+    pub fn status(&self) -> Vec<OutputStatus> {
+        let next_rotation_secs = self
+            .next_rotation
+            .filter(|_| !self.paused)
+            .map(|at| at.saturating_duration_since(Instant::now()).as_secs());
+
+        self.layers
+            .iter()
+            .map(|layer| OutputStatus {
+                output: layer.output_info.name.clone().unwrap_or_default(),
+                entry: self.entry.output.clone(),
+                source: self.output_source(layer),
+                paused: self.paused,
+                queue_len: self.image_queue.len(),
+                next_rotation_secs,
+            })
+            .collect()
+    }
+
+    /// The source shown on a layer's output.
+    fn output_source(&self, layer: &CosmicBgLayer) -> Option<Source> {
+        layer
+            .output_info
+            .name
+            .as_ref()
+            .and_then(|name| self.output_images.get(name))
+            .map(|path| Source::Path(path.clone()))
+            .or_else(|| self.current_source.clone())
     }
 
     pub fn save_state(&self) -> Result<(), cosmic_config::Error> {
-        let Some(cur_source) = self.current_source.clone() else {
+        if self.current_source.is_none() {
             return Ok(());
-        };
+        }
         let state_helper = State::state()?;
         let mut state = State::get_entry(&state_helper).unwrap_or_default();
         for l in &self.layers {
             let name = l.output_info.name.clone().unwrap_or_default();
+            // Keep the last image of outputs that have not been assigned one yet.
+            if self.distinct_outputs() && !self.output_images.contains_key(&name) {
+                continue;
+            }
+            let Some(cur_source) = self.output_source(l) else {
+                continue;
+            };
             if let Some((_, source)) = state
                 .wallpapers
                 .iter_mut()
                 .find(|(output, _)| *output == name)
             {
-                *source = cur_source.clone();
+                *source = cur_source;
             } else {
-                state.wallpapers.push((name, cur_source.clone()))
+                state.wallpapers.push((name, cur_source))
+            }
+        }
+        state.write_entry(&state_helper)
+    }
+
+    pub fn draw(&mut self) {
+        let start = Instant::now();
+
+        if self.entry.span.is_some() {
+            if let Err(why) = self.draw_span(start) {
+                self.log_draw_error(why);
+            }
+            self.finish_transition(start);
+            return;
+        }
+
+        if self.distinct_outputs() {
+            self.draw_distinct(start);
+            self.finish_transition(start);
+            return;
+        }
+
+        let mut cur_resized_img: Option<DynamicImage> = None;
+
+        // Use indices to avoid borrow conflicts with self
+        let layer_indices: Vec<usize> = self.layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.needs_redraw)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        for idx in layer_indices {
+            if let Err(why) = self.draw_layer_by_index(idx, &mut cur_resized_img, start) {
+                self.log_draw_error(why);
+            }
+        }
+
+        self.finish_transition(start);
+    }
+
+    fn log_draw_error(&self, why: DrawError) {
+        match why {
+            DrawError::NoSource => {
+                tracing::info!("No source for wallpaper");
             }
+            DrawError::Loading => {
+                // Keep showing the previous frame until the loader is done.
+                tracing::trace!(state = ?self.loading_state, "wallpaper still loading");
+            }
+            why => {
+                tracing::error!(?why, "wallpaper could not be drawn");
+            }
+        }
+    }
+
+    /// Drop the transition once a draw at or after its end has happened.
+    fn finish_transition(&mut self, drawn_at: Instant) {
+        if self.transition.as_ref().is_some_and(|t| t.is_finished(drawn_at)) {
+            self.transition = None;
+        }
+    }
+
+    /// Draw the wallpaper across the combined layout of its outputs, with
+    /// each layer showing its own crop of one shared frame.
+    fn draw_span(&mut self, start: Instant) -> Result<(), DrawError> {
+        let bezel_gap = self.entry.span.as_ref().map_or(0, |span| span.bezel_gap);
+
+        let outputs: Vec<_> = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let buffer = self.calculate_layer_dimensions(layer).ok()?;
+                Some((SpanOutput::from_info(&layer.output_info)?, buffer))
+            })
+            .collect();
+
+        let layout = SpanLayout::new(&outputs, bezel_gap).ok_or(DrawError::NoSource)?;
+
+        // Added, removed or moved outputs change every crop.
+        if self.span_layout.as_ref() != Some(&layout) {
+            for layer in &mut self.layers {
+                layer.needs_redraw = true;
+            }
+            self.span_layout = Some(layout.clone());
+        }
+
+        if !self.layers.iter().any(|layer| layer.needs_redraw) {
+            return Ok(());
+        }
+
+        let frame = self.render_frame(None, layout.size.0, layout.size.1, start)?;
+        let filter = self.frame_filter();
+
+        for (idx, crop) in layout.crops.iter().enumerate() {
+            if !self.layers[idx].needs_redraw {
+                continue;
+            }
+
+            let Ok((width, height)) = self.calculate_layer_dimensions(&self.layers[idx]) else {
+                continue; // Not configured yet
+            };
+
+            // Outputs without a known position show the whole frame.
+            let image = match crop {
+                Some(crop) => span::crop(&frame, *crop, width, height, filter),
+                None => crate::scaler::stretch(&frame, filter, width, height),
+            };
+
+            // Rotated outputs get their crop upright, in a buffer turned to match.
+            let (width, height) = (image.width(), image.height());
+            if let Err(why) = self.present(idx, &image, width, height, start) {
+                self.log_draw_error(why);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw every layer with the image assigned to its output.
+    fn draw_distinct(&mut self, start: Instant) {
+        self.assign_output_images();
+
+        for idx in 0..self.layers.len() {
+            if !self.layers[idx].needs_redraw {
+                continue;
+            }
+
+            let output = self.layers[idx].output_info.name.clone();
+            let result = self
+                .calculate_layer_dimensions(&self.layers[idx])
+                .and_then(|(width, height)| {
+                    let image = self.render_frame(output.as_deref(), width, height, start)?;
+                    self.present(idx, &image, width, height, start)
+                });
+
+            if let Err(why) = result {
+                self.log_draw_error(why);
+            }
+        }
+    }
+
+    /// Render the source of `output`, or the current source when outputs
+    /// share it, at the given size, blended with the running transition.
+    fn render_frame(
+        &mut self,
+        output: Option<&str>,
+        width: u32,
+        height: u32,
+        start: Instant,
+    ) -> Result<DynamicImage, DrawError> {
+        let output_image = output.and_then(|name| self.output_images.get(name)).cloned();
+
+        let mut image = match output_image {
+            Some(path) => self.scale_output_image(&path, width, height)?,
+            None => self.prepare_scaled_image(width, height)?,
+        };
+
+        let is_static = self.animated_source.is_none() && self.ken_burns().is_none();
+        let high_depth = self.source_high_depth(output);
+        if let Some(transition) = self.transition.as_mut() {
+            image = transition.render(output, image, is_static, high_depth, start);
+        }
+
+        if self.records_frames() {
+            // Kept at the depth of the source rather than of the scaled frame.
+            let frame = if high_depth || !crate::draw::is_high_depth(&image) {
+                image.clone()
+            } else {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            };
+            self.shown_frames
+                .insert(FrameKey::new(output, (width, height)), frame);
+        }
+
+        Ok(image)
+    }
+
+    /// Whether the source drawn on `output` has more than 8 bits per channel,
+    /// which `ColorDepth::Auto` draws in 10-bit buffers.
+    fn source_high_depth(&self, output: Option<&str>) -> bool {
+        if let Some(path) = output.and_then(|name| self.output_images.get(name)) {
+            return self.original_high_depth(path);
+        }
+
+        if let Some(source) = &self.animated_source {
+            return source.is_high_depth();
+        }
+
+        match &self.current_source {
+            Some(Source::Path(path)) => self.original_high_depth(path),
+            // Colors and gradients are computed in floats.
+            Some(Source::Color(_)) => true,
+            _ => false,
         }
-        state.write_entry(&state_helper)
     }
 
-    pub fn draw(&mut self) {
-        let start = Instant::now();
-        let mut cur_resized_img: Option<DynamicImage> = None;
+    /// Whether the decoded image at `path` has more than 8 bits per channel.
+    fn original_high_depth(&self, path: &Path) -> bool {
+        let current = match &self.current_source {
+            Some(Source::Path(current)) if current == path => self.current_image.clone(),
+            _ => None,
+        };
 
-        // Use indices to avoid borrow conflicts with self
-        let layer_indices: Vec<usize> = self.layers
-            .iter()
-            .enumerate()
-            .filter(|(_, layer)| layer.needs_redraw)
-            .map(|(idx, _)| idx)
-            .collect();
+        current
+            .or_else(|| self.output_originals.get(path).cloned())
+            .or_else(|| self.loader.cache().get(&CacheKey::original(path)))
+            .is_some_and(|original| crate::draw::is_high_depth(&original))
+    }
 
-        for idx in layer_indices {
-            match self.draw_layer_by_index(idx, &mut cur_resized_img, start) {
-                Ok(()) => {}
-                Err(DrawError::NoSource) => {
-                    tracing::info!("No source for wallpaper");
-                }
-                Err(why) => {
-                    tracing::error!(?why, "wallpaper could not be drawn");
-                }
+    /// Scale the image assigned to an output, decoding it in the background
+    /// on first use.
+    fn scale_output_image(
+        &mut self,
+        path: &Path,
+        width: u32,
+        height: u32,
+    ) -> Result<DynamicImage, DrawError> {
+        // Ken Burns frames move, so only still ones are cached.
+        if self.ken_burns().is_none() {
+            let key = CacheKey::scaled(path, (width, height), self.scale_fingerprint());
+            if let Some(scaled) = self.loader.cache().get(&key) {
+                return Ok(DynamicImage::clone(&scaled));
             }
         }
+
+        let original = match self.output_originals.get(path) {
+            Some(original) => Arc::clone(original),
+            None => match self.loader.cache().get(&CacheKey::original(path)) {
+                Some(original) => {
+                    self.output_originals
+                        .insert(path.to_path_buf(), Arc::clone(&original));
+                    original
+                }
+                None => {
+                    if self.pending_decodes.insert(path.to_path_buf()) {
+                        self.loader
+                            .request_decode_image(self.entry.output.clone(), path.to_path_buf());
+                    }
+                    return Err(DrawError::Loading);
+                }
+            },
+        };
+
+        self.scale_and_adjust(path, &original, width, height)
     }
 
     fn draw_layer_by_index(
@@ -237,16 +1126,57 @@ impl Wallpaper {
             .map_or(true, |img| img.width() != width || img.height() != height);
 
         if needs_new_image {
-            *cur_resized_img = Some(self.prepare_scaled_image(width, height)?);
+            *cur_resized_img = Some(self.render_frame(None, width, height, start)?);
         }
 
-        // Now we can get mutable access to the layer
+        let image = cur_resized_img.as_ref().expect("cur_resized_img was just set");
+
+        self.present(layer_idx, image, width, height, start)
+    }
+
+    /// Attach `image` to a layer's surface.
+    fn present(
+        &mut self,
+        layer_idx: usize,
+        image: &DynamicImage,
+        width: u32,
+        height: u32,
+        start: Instant,
+    ) -> Result<(), DrawError> {
+        let output = self
+            .layers
+            .get(layer_idx)
+            .and_then(|layer| layer.output_info.name.clone());
+
+        let converted;
+        let image = match output.as_ref().and_then(|name| self.display_profiles.get(name)) {
+            Some(conversion) => match conversion.convert(image) {
+                Ok(image) => {
+                    converted = image;
+                    &converted
+                }
+                Err(why) => {
+                    tracing::warn!(%why, ?output, "display profile conversion failed");
+                    image
+                }
+            },
+            None => image,
+        };
+
+        let high_depth = self.source_high_depth(output.as_deref());
+
         let layer = self.layers.get_mut(layer_idx).ok_or(DrawError::NoSource)?;
         let pool = layer.pool.as_mut().ok_or(DrawError::NoSource)?;
 
-        let image = cur_resized_img.as_ref().expect("cur_resized_img was just set");
-
-        let buffer = crate::draw::canvas(pool, image, width as i32, height as i32, width as i32 * 4)?;
+        let format = crate::draw::format(self.entry.color_depth, layer.ten_bit, high_depth);
+        let buffer = crate::draw::canvas(
+            pool,
+            image,
+            width as i32,
+            height as i32,
+            width as i32 * 4,
+            format,
+        )?;
 
         crate::draw::layer_surface(
             layer,
@@ -281,81 +1211,299 @@ impl Wallpaper {
         let source = self.current_source.clone().ok_or(DrawError::NoSource)?;
 
         match source {
+            // An animation or video that rotation landed on.
+            Source::Path(_) if self.animated_source.is_some() => {
+                self.next_animated_frame(width, height)
+            }
             Source::Path(ref path) => self.scale_image_from_path(path, width, height),
             Source::Color(Color::Single([r, g, b])) => {
-                Ok(self.generate_solid_color([r, g, b], width, height))
-            }
-            Source::Color(Color::Gradient(ref gradient)) => {
-                self.generate_gradient(gradient, width, height)
+                Ok(self.adjust(self.generate_solid_color([r, g, b], width, height)))
             }
+            Source::Color(Color::Gradient(ref gradient)) => self
+                .generate_gradient(gradient, width, height)
+                .map(|image| self.adjust(image)),
+            // Resolved to the active slot's source before it gets here.
+            Source::Schedule(_) | Source::Solar(_) => Err(DrawError::NoSource),
             Source::Shader(_) | Source::Video(_) | Source::Animated(_) => {
-                // Use persistent animated source
-                let animated_source = self
-                    .animated_source
-                    .as_mut()
-                    .ok_or_else(|| DrawError::ImageDecode {
-                        path: PathBuf::from("animated"),
-                        reason: "Animated source not initialized".to_string(),
-                    })?;
+                self.next_animated_frame(width, height)
+            }
+        }
+    }
+
+    /// Render the next frame of the persistent animated source.
+    fn next_animated_frame(&mut self, width: u32, height: u32) -> Result<DynamicImage, DrawError> {
+        let animated_source = self
+            .animated_source
+            .as_mut()
+            .ok_or_else(|| DrawError::ImageDecode {
+                path: PathBuf::from("animated"),
+                reason: "Animated source not initialized".to_string(),
+            })?;
+
+        // Prepare with target dimensions if needed
+        animated_source.prepare(width, height)
+            .map_err(|e| DrawError::ImageDecode {
+                path: PathBuf::from("animated"),
+                reason: format!("Failed to prepare animated source: {}", e),
+            })?;
+
+        // Get the next frame
+        let frame = animated_source.next_frame()
+            .map_err(|e| DrawError::ImageDecode {
+                path: PathBuf::from("animated"),
+                reason: format!("Failed to get next frame: {}", e),
+            })?;
+
+        // Decoded frames keep their own size until scaled here.
+        let image = if (frame.image.width(), frame.image.height()) == (width, height) {
+            frame.image
+        } else {
+            crate::scaler::scale(
+                &frame.image,
+                &self.entry.scaling_mode,
+                self.frame_filter(),
+                width,
+                height,
+            )
+        };
+
+        Ok(self.adjust(image))
+    }
+
+    fn scale_image_from_path(
+        &mut self,
+        path: &Path,
+        width: u32,
+        height: u32,
+    ) -> Result<DynamicImage, DrawError> {
+        if self.ken_burns().is_none() {
+            let key = CacheKey::scaled(path, (width, height), self.scale_fingerprint());
+            if let Some(scaled) = self.loader.cache().get(&key) {
+                return Ok(DynamicImage::clone(&scaled));
+            }
+        }
+
+        let Some(img) = self.current_image.clone() else {
+            self.request_decode(path);
+            return Err(DrawError::Loading);
+        };
+
+        self.scale_and_adjust(path, &img, width, height)
+    }
+
+    /// Queue `path` for decoding unless it is already in flight or has failed.
+    ///
+    /// The loader opens it for playback instead if it is an animation or a
+    /// video, preparing it for the first layer.
+    fn request_decode(&mut self, path: &Path) {
+        match &self.loading_state {
+            LoadingState::LoadingImage(pending) if pending == path => return,
+            LoadingState::Error(_) => return,
+            _ => {}
+        }
+
+        self.loading_state = LoadingState::LoadingImage(path.to_path_buf());
+        let size = self.render_sizes().into_iter().next();
+        self.loader
+            .request_load_media(self.entry.output.clone(), path.to_path_buf(), size);
+    }
+
+    /// Apply a background scan or decode that finished for this wallpaper.
+    pub fn handle_loader_result(&mut self, result: LoaderResult) {
+        match result {
+            LoaderResult::DirectoryScanned { path, paths, .. } => {
+                if self.loading_state != LoadingState::ScanningDirectory(path) {
+                    return; // The source changed while scanning
+                }
+
+                self.loading_state = LoadingState::Idle;
+                self.replace_image_queue(paths);
+            }
+
+            LoaderResult::DirectoryRefined { path, paths, .. } => {
+                // A newer scan is on its way, or the source changed.
+                if matches!(self.loading_state, LoadingState::ScanningDirectory(_))
+                    || !self.shows_directory(&path)
+                {
+                    return;
+                }
+
+                self.replace_image_queue(paths);
+            }
+
+            LoaderResult::ImageDecoded { path, image, .. } => {
+                if self.pending_decodes.remove(&path) {
+                    if !self.output_images.values().any(|p| *p == path) {
+                        return; // Rotated away while decoding
+                    }
+
+                    for layer in &mut self.layers {
+                        let name = layer.output_info.name.as_ref();
+                        if name.and_then(|name| self.output_images.get(name)) == Some(&path) {
+                            layer.needs_redraw = true;
+                        }
+                    }
+                    self.output_originals.insert(path, image);
+                    self.draw();
+
+                    if self.pending_decodes.is_empty() {
+                        self.prefetch_next();
+                    }
+                    return;
+                }
+
+                if self.loading_state != LoadingState::LoadingImage(path) {
+                    return; // Rotated away while decoding
+                }
+
+                self.loading_state = LoadingState::Ready;
+                self.current_image = Some(image);
+                for layer in &mut self.layers {
+                    layer.needs_redraw = true;
+                }
+                self.draw();
+                self.prefetch_next();
+            }
+
+            LoaderResult::ImageRendered { key, .. } => {
+                if !self.pending_renders.remove(&key) {
+                    return;
+                }
+
+                for layer in &mut self.layers {
+                    layer.needs_redraw = true;
+                }
+                self.draw();
+            }
+
+            LoaderResult::MediaLoaded { path, source, .. } => {
+                if self.loading_state != LoadingState::LoadingImage(path) {
+                    return; // Rotated away while loading
+                }
+
+                self.loading_state = LoadingState::Ready;
+                self.animated_source = Some(source.0);
+                for layer in &mut self.layers {
+                    layer.needs_redraw = true;
+                }
+                self.draw();
+                self.prefetch_next();
+            }
 
-                // Prepare with target dimensions if needed
-                animated_source.prepare(width, height)
-                    .map_err(|e| DrawError::ImageDecode {
-                        path: PathBuf::from("animated"),
-                        reason: format!("Failed to prepare animated source: {}", e),
-                    })?;
+            LoaderResult::LoadError { path, error, .. } => {
+                if let Some(path) = &path {
+                    let renders = self.pending_renders.len();
+                    self.pending_renders.retain(|key| key.path != *path);
 
-                // Get the next frame
-                let frame = animated_source.next_frame()
-                    .map_err(|e| DrawError::ImageDecode {
-                        path: PathBuf::from("animated"),
-                        reason: format!("Failed to get next frame: {}", e),
-                    })?;
+                    if self.pending_decodes.remove(path) || self.pending_renders.len() != renders {
+                        tracing::error!(?path, error, output = self.entry.output, "failed to load wallpaper");
+                        return;
+                    }
+                }
 
-                Ok(frame.image)
+                let is_current = match (&self.loading_state, &path) {
+                    (LoadingState::LoadingImage(pending), Some(path)) => pending == path,
+                    (LoadingState::ScanningDirectory(pending), Some(path)) => pending == path,
+                    _ => false,
+                };
+
+                if is_current {
+                    tracing::error!(?path, error, output = self.entry.output, "failed to load wallpaper");
+                    self.loading_state = LoadingState::Error(error);
+                }
             }
         }
     }
 
-    fn scale_image_from_path(
+    /// Scale and adjust the image at `path` for a layer, keeping still
+    /// results in the cache for the next draw.
+    ///
+    /// Vector images are rasterized at the layer size by the loader instead,
+    /// and drawn once it reports back.
+    fn scale_and_adjust(
         &mut self,
         path: &Path,
+        img: &DynamicImage,
         width: u32,
         height: u32,
     ) -> Result<DynamicImage, DrawError> {
-        if self.current_image.is_none() {
-            self.current_image = Some(self.decode_image(path)?);
+        let key = CacheKey::scaled(path, (width, height), self.scale_fingerprint());
+
+        if self.ken_burns().is_none() && crate::svg::is_svg(path) {
+            if self.pending_renders.insert(key) {
+                self.loader.request_render(
+                    self.entry.output.clone(),
+                    path.to_path_buf(),
+                    (width, height),
+                    ScaleSettings::new(&self.entry),
+                );
+            }
+            return Err(DrawError::Loading);
+        }
+
+        let image = self.apply_scaling_mode(path, img, width, height);
+        let image = self.adjust(image);
+
+        if self.ken_burns().is_none() {
+            self.loader.cache().insert(key, image.clone());
         }
 
-        let img = self.current_image.as_ref().expect("current_image was set by the is_none check above");
-        Ok(self.apply_scaling_mode(img, width, height))
+        Ok(image)
     }
 
-    fn decode_image(&self, path: &Path) -> Result<DynamicImage, DrawError> {
-        match path.extension() {
-            Some(ext) if ext == "jxl" => decode_jpegxl(path).map_err(DrawError::from),
-            _ => {
-                let reader = ImageReader::open(path)
-                    .and_then(|r| r.with_guessed_format())
-                    .map_err(|e| DrawError::ImageDecode {
-                        path: path.to_path_buf(),
-                        reason: format!("failed to open image: {}", e),
-                    })?;
+    /// Filter to scale frames with: the entry's own for still images, or a
+    /// cheaper one when frames are scaled at the animation rate.
+    fn frame_filter(&self) -> FilterMethod {
+        if self.animated_source.is_some() || self.ken_burns().is_some() {
+            self.entry.filter_method.for_animation()
+        } else {
+            self.entry.filter_method
+        }
+    }
 
-                reader.decode().map_err(|e| DrawError::ImageDecode {
-                    path: path.to_path_buf(),
-                    reason: format!("failed to decode image: {}", e),
-                })
+    /// Apply the entry's adjustments, if any, to a scaled frame.
+    fn adjust(&self, image: DynamicImage) -> DynamicImage {
+        match &self.entry.adjustments {
+            Some(adjustments) if !adjustments.is_identity() => {
+                crate::adjust::apply(image, adjustments)
             }
+            _ => image,
         }
     }
 
-    fn apply_scaling_mode(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
-        match self.entry.scaling_mode {
-            ScalingMode::Fit(color) => crate::scaler::fit(img, &color, width, height),
-            ScalingMode::Zoom => crate::scaler::zoom(img, width, height),
-            ScalingMode::Stretch => crate::scaler::stretch(img, width, height),
+    fn apply_scaling_mode(
+        &mut self,
+        path: &Path,
+        img: &DynamicImage,
+        width: u32,
+        height: u32,
+    ) -> DynamicImage {
+        let Some(ken_burns) = self.ken_burns() else {
+            return crate::scaler::scale_path(
+                path,
+                img,
+                &self.entry.scaling_mode,
+                self.entry.filter_method,
+                width,
+                height,
+            );
+        };
+
+        let zoom = f64::from(ken_burns.clamped_zoom());
+        let speed = ken_burns.clamped_speed();
+
+        // One pass across the image per rotation, sped up or slowed down.
+        let pass = match self.entry.rotation_frequency {
+            0 => ken_burns::DEFAULT_PASS,
+            secs => Duration::from_secs(secs),
         }
+        .div_f32(speed);
+
+        let elapsed = self.ken_burns_clock.elapsed(Instant::now());
+        let viewport = Motion::for_path(path, zoom).at(ken_burns::progress(elapsed, pass));
+        let rect = ken_burns::crop((img.width(), img.height()), (width, height), viewport);
+
+        crate::scaler::crop(img, rect, self.frame_filter(), width, height)
     }
 
     fn generate_solid_color(&self, color: [f32; 3], width: u32, height: u32) -> DynamicImage {
@@ -374,39 +1522,41 @@ impl Wallpaper {
     }
 
     pub fn load_images(&mut self) {
-        let mut image_queue = VecDeque::new();
         let xdg_data_dirs: Vec<String> = std::env::var("XDG_DATA_DIRS")
             .map(|dirs| dirs.split(':').map(|s| format!("{}/backgrounds/", s)).collect())
             .unwrap_or_default();
 
+        self.stop_slideshow();
+
         match self.entry.source {
             Source::Path(ref source) => {
                 tracing::debug!(?source, "loading images");
 
+                let mut image_queue = VecDeque::new();
                 if let Ok(source) = source.canonicalize() {
                     if source.is_dir() {
-                        if xdg_data_dirs
+                        // Store paths of wallpapers to be used for the slideshow.
+                        let recursive = xdg_data_dirs
                             .iter()
-                            .any(|xdg_data_dir| source.starts_with(xdg_data_dir))
-                        {
-                            // Store paths of wallpapers to be used for the slideshow.
-                            for img_path in WalkDir::new(source)
-                                .follow_links(true)
-                                .into_iter()
-                                .filter_map(Result::ok)
-                                .filter(|p| p.path().is_file())
-                            {
-                                image_queue.push_front(img_path.path().into());
+                            .any(|xdg_data_dir| source.starts_with(xdg_data_dir));
+
+                        // The previous images stay up until the scan completes.
+                        self.loading_state = LoadingState::ScanningDirectory(source.clone());
+                        self.loader.request_scan_directory(
+                            self.entry.output.clone(),
+                            source,
+                            recursive,
+                            self.entry.filter_by_theme,
+                        );
+                        return;
+                    } else if gnome_xml::is_slideshow(&source) {
+                        match GnomeSlideshow::load(&source) {
+                            Ok(slideshow) => {
+                                self.start_slideshow(slideshow);
+                                return;
                             }
-                        } else if let Ok(dir) = source.read_dir() {
-                            for entry in dir.filter_map(Result::ok) {
-                                let Ok(path) = entry.path().canonicalize() else {
-                                    continue;
-                                };
-
-                                if path.is_file() {
-                                    image_queue.push_front(path);
-                                }
+                            Err(why) => {
+                                tracing::error!(?why, ?source, "failed to load slideshow XML");
                             }
                         }
                     } else if source.is_file() {
@@ -414,28 +1564,8 @@ impl Wallpaper {
                     }
                 }
 
-                if image_queue.len() > 1 {
-                    let image_slice = image_queue.make_contiguous();
-                    match self.entry.sampling_method {
-                        SamplingMethod::Alphanumeric => {
-                            image_slice
-                                .sort_by(|a, b| a.to_string_lossy().cmp(&b.to_string_lossy()));
-                        }
-                        SamplingMethod::Random => image_slice.shuffle(&mut rng()),
-                    };
-
-                    // If a wallpaper from this slideshow was previously set, resume with that wallpaper.
-                    if let Some(Source::Path(last_path)) = current_image(&self.entry.output) {
-                        if let Some(pos) = image_queue.iter().position(|p| p == &last_path) {
-                            image_queue.rotate_left(pos);
-                        }
-                    }
-                }
-
-                image_queue.pop_front().map(|current_image_path| {
-                    self.current_source = Some(Source::Path(current_image_path.clone()));
-                    image_queue.push_back(current_image_path);
-                });
+                self.set_image_queue(image_queue);
+                return;
             }
 
             Source::Color(ref c) => {
@@ -450,7 +1580,6 @@ impl Wallpaper {
                 match ShaderSource::new(shader_config.clone()) {
                     Ok(shader_source) => {
                         self.animated_source = Some(Box::new(shader_source));
-                        self.setup_animation_timer();
                     }
                     Err(e) => {
                         tracing::error!("Failed to create shader source: {}", e);
@@ -465,7 +1594,6 @@ impl Wallpaper {
                 match VideoSource::new(video_config.clone()) {
                     Ok(video_source) => {
                         self.animated_source = Some(Box::new(video_source));
-                        self.setup_animation_timer();
                     }
                     Err(e) => {
                         tracing::error!("Failed to create video source: {}", e);
@@ -480,14 +1608,73 @@ impl Wallpaper {
                 match AnimatedSource::new(animated_config.clone()) {
                     Ok(animated_source) => {
                         self.animated_source = Some(Box::new(animated_source));
-                        self.setup_animation_timer();
                     }
                     Err(e) => {
                         tracing::error!("Failed to create animated source: {}", e);
                     }
                 }
             }
+
+            Source::Schedule(_) | Source::Solar(_) => {
+                tracing::error!(output = %self.entry.output, "unresolved timed source");
+            }
+        };
+        if let Err(err) = self.save_state() {
+            error!("{err}");
+        }
+        self.image_queue = VecDeque::new();
+    }
+
+    /// Switch to the images of a scanned directory, moving on from the image
+    /// on screen only if it is no longer among them.
+    fn replace_image_queue(&mut self, paths: Vec<PathBuf>) {
+        let previous = self.current_source.clone();
+        self.set_image_queue(paths.into_iter().collect());
+        if self.current_source != previous {
+            self.begin_transition();
+        }
+        self.clear_image();
+        self.draw();
+    }
+
+    /// Whether the source is the directory at the canonical `path`.
+    fn shows_directory(&self, path: &Path) -> bool {
+        let Source::Path(source) = &self.entry.source else {
+            return false;
         };
+
+        source.canonicalize().is_ok_and(|source| source == path)
+    }
+
+    /// Order a freshly loaded slideshow and make its first image current.
+    fn set_image_queue(&mut self, mut image_queue: VecDeque<PathBuf>) {
+        if image_queue.len() > 1 {
+            let image_slice = image_queue.make_contiguous();
+            match self.entry.sampling_method {
+                SamplingMethod::Alphanumeric => {
+                    image_slice.sort_by(|a, b| a.to_string_lossy().cmp(&b.to_string_lossy()));
+                }
+                SamplingMethod::Random => image_slice.shuffle(&mut rng()),
+            };
+
+            // If a wallpaper from this slideshow was previously set, resume with that wallpaper.
+            if let Some(Source::Path(last_path)) = current_image(&self.entry.output) {
+                if let Some(pos) = image_queue.iter().position(|p| p == &last_path) {
+                    image_queue.rotate_left(pos);
+                }
+            }
+        }
+
+        if let Some(current_image_path) = image_queue.pop_front() {
+            self.current_source = Some(Source::Path(current_image_path.clone()));
+            image_queue.push_back(current_image_path);
+        }
+
+        // With distinct images per output, they are assigned on the next draw.
+        self.output_images.clear();
+        self.resume_outputs = true;
+        self.animated_source = None;
+
         if let Err(err) = self.save_state() {
             error!("{err}");
         }
@@ -534,106 +1721,156 @@ impl Wallpaper {
         self._watcher = Some(watcher);
     }
 
-    fn setup_animation_timer(&mut self) {
-        // Remove existing animation timer if present
-        if let Some(token) = self.animation_timer_token.take() {
-            self.loop_handle.remove(token);
+    fn register_timer(&mut self) {
+        let rotation_freq = self.entry.rotation_frequency;
+        let output = self.entry.output.clone();
+        // set timer for rotation, unless a GNOME slideshow sets the pace
+        if rotation_freq > 0 && self.slideshow.is_none() {
+            let interval = Duration::from_secs(rotation_freq);
+            self.next_rotation = Some(Instant::now() + interval);
+            self.timer_token = self
+                .loop_handle
+                .insert_source(
+                    Timer::from_duration(interval),
+                    move |_, _, state: &mut CosmicBg| {
+                        let span = tracing::debug_span!("Wallpaper::timer");
+                        let _handle = span.enter();
+
+                        let Some(item) = state
+                            .wallpapers
+                            .iter_mut()
+                            .find(|w| w.entry.output == output)
+                        else {
+                            return TimeoutAction::Drop; // Drop if no item found for this timer
+                        };
+
+                        if item.holds_rotation() && !item.paused {
+                            item.next_rotation = Some(Instant::now() + HOLD_RETRY);
+                            return TimeoutAction::ToDuration(HOLD_RETRY);
+                        }
+
+                        item.next_rotation = Some(Instant::now() + interval);
+
+                        if !item.paused {
+                            item.step(true);
+                        }
+
+                        // An animation or video that came up needs scheduling.
+                        state.schedule_animations();
+                        TimeoutAction::ToDuration(interval)
+                    },
+                )
+                .ok();
         }
+    }
 
-        // Get frame duration from the animated source
-        let frame_duration = self
-            .animated_source
-            .as_ref()
-            .map(|source| source.frame_duration())
-            .unwrap_or(crate::source::DEFAULT_FRAME_DURATION);
+    fn start_slideshow(&mut self, slideshow: GnomeSlideshow) {
+        self.slideshow = Some(slideshow);
+        self.current_source = None;
+
+        if let Some(delay) = self.advance_slideshow() {
+            self.register_slideshow_timer(delay);
+        }
+    }
+
+    fn stop_slideshow(&mut self) {
+        if let Some(token) = self.slideshow_token.take() {
+            self.loop_handle.remove(token);
+        }
+        self.slideshow = None;
+    }
 
+    /// Move the GNOME slideshow on after `delay`. Like schedules, it never
+    /// waits longer than a minute, so a suspend doesn't leave it behind.
+    fn register_slideshow_timer(&mut self, delay: Duration) {
         let output = self.entry.output.clone();
 
-        // Register continuous animation timer
-        self.animation_timer_token = self
+        self.slideshow_token = self
             .loop_handle
             .insert_source(
-                Timer::from_duration(frame_duration),
+                Timer::from_duration(delay.min(schedule::RECHECK_INTERVAL)),
                 move |_, _, state: &mut CosmicBg| {
-                    let span = tracing::debug_span!("Wallpaper::animation_timer");
-                    let _handle = span.enter();
-
                     let Some(item) = state
                         .wallpapers
                         .iter_mut()
                         .find(|w| w.entry.output == output)
                     else {
-                        return TimeoutAction::Drop; // Drop if no item found
+                        return TimeoutAction::Drop;
                     };
 
-                    // Trigger redraw for animated content
-                    for layer in &mut item.layers {
-                        layer.needs_redraw = true;
-                    }
-                    item.draw();
+                    let delay = if item.paused {
+                        item.slideshow
+                            .as_ref()
+                            .map(|slideshow| slideshow.frame_now().remaining())
+                    } else {
+                        item.advance_slideshow()
+                    };
 
-                    // Get updated frame duration from source
-                    let next_duration = item
-                        .animated_source
-                        .as_ref()
-                        .map(|source| source.frame_duration())
-                        .unwrap_or(crate::source::DEFAULT_FRAME_DURATION);
+                    let Some(delay) = delay else {
+                        return TimeoutAction::Drop;
+                    };
 
-                    TimeoutAction::ToDuration(next_duration)
+                    // A crossfade that just began needs scheduling.
+                    state.schedule_animations();
+                    TimeoutAction::ToDuration(delay.min(schedule::RECHECK_INTERVAL))
                 },
             )
             .ok();
     }
 
-    fn register_timer(&mut self) {
-        let rotation_freq = self.entry.rotation_frequency;
-        let output = self.entry.output.clone();
-        // set timer for rotation
-        if rotation_freq > 0 {
-            self.timer_token = self
-                .loop_handle
-                .insert_source(
-                    Timer::from_duration(Duration::from_secs(rotation_freq)),
-                    move |_, _, state: &mut CosmicBg| {
-                        let span = tracing::debug_span!("Wallpaper::timer");
-                        let _handle = span.enter();
+    /// Check the schedule when its next source is due, or sooner so a
+    /// suspend or clock change is noticed.
+    fn register_schedule_timer(&mut self) {
+        if let Some(token) = self.schedule_token.take() {
+            self.loop_handle.remove(token);
+        }
 
-                        let Some(item) = state
-                            .wallpapers
-                            .iter_mut()
-                            .find(|w| w.entry.output == output)
-                        else {
-                            return TimeoutAction::Drop; // Drop if no item found for this timer
-                        };
+        let Some(delay) = self.schedule_delay() else {
+            return;
+        };
 
-                        // Skip rotation when there's only one image — it would
-                        // re-decode and re-draw the same wallpaper.
-                        if item.image_queue.len() <= 1 {
-                            return TimeoutAction::ToDuration(Duration::from_secs(rotation_freq));
-                        }
+        let output = self.entry.output.clone();
 
-                        while let Some(next) = item.image_queue.pop_front() {
-                            item.current_source = Some(Source::Path(next.clone()));
-                            if let Err(err) = item.save_state() {
-                                error!("{err}");
-                            }
+        self.schedule_token = self
+            .loop_handle
+            .insert_source(
+                Timer::from_duration(delay),
+                move |_, _, state: &mut CosmicBg| {
+                    let Some(item) = state
+                        .wallpapers
+                        .iter_mut()
+                        .find(|w| w.entry.output == output)
+                    else {
+                        return TimeoutAction::Drop;
+                    };
 
-                            item.image_queue.push_back(next);
-                            item.clear_image();
-                            item.draw();
+                    item.apply_schedule();
 
-                            return TimeoutAction::ToDuration(Duration::from_secs(rotation_freq));
-                        }
+                    let Some(delay) = item.schedule_delay() else {
+                        return TimeoutAction::Drop;
+                    };
 
-                        TimeoutAction::Drop
-                    },
-                )
-                .ok();
+                    // A new animated source or fade needs scheduling.
+                    state.schedule_animations();
+                    TimeoutAction::ToDuration(delay)
+                },
+            )
+            .ok();
+    }
+
+    fn restart_timer(&mut self) {
+        if let Some(token) = self.timer_token.take() {
+            self.loop_handle.remove(token);
         }
+        self.next_rotation = None;
+        self.register_timer();
     }
 
     fn clear_image(&mut self) {
         self.current_image = None;
+        self.pending_renders.clear();
+        self.ken_burns_clock.reset();
+        self.loading_state = LoadingState::Idle;
         for l in &mut self.layers {
             l.needs_redraw = true;
         }
@@ -656,13 +1893,18 @@ fn current_image(output: &str) -> Option<Source> {
     wallpaper.map(|(_name, path)| path)
 }
 
-/// Decodes JPEG XL image files into `image::DynamicImage` via `jxl-oxide`.
-fn decode_jpegxl(path: &std::path::Path) -> eyre::Result<DynamicImage> {
-    let file = File::open(path).map_err(|why| eyre!("failed to open jxl image file: {why}"))?;
-
-    let decoder =
-        JxlDecoder::new(file).map_err(|why| eyre!("failed to read jxl image header: {why}"))?;
-
-    image::DynamicImage::from_decoder(decoder)
-        .map_err(|why| eyre!("failed to decode jxl image: {why}"))
+/// The conversions to the entry's display profiles that load, by output name.
+fn load_display_profiles(entry: &Entry) -> HashMap<String, icc::Conversion> {
+    entry
+        .color_profiles
+        .iter()
+        .filter_map(|(output, path)| {
+            let conversion = icc::Conversion::to_display(path)
+                .inspect_err(|why| {
+                    tracing::error!(?path, %why, output, "failed to load display profile");
+                })
+                .ok()?;
+            Some((output.clone(), conversion))
+        })
+        .collect()
 }